@@ -1,18 +1,34 @@
+use crate::api_config::ApiConfig;
+use crate::avatars::{Avatars, PendingAvatar};
 use crate::{Api, Journal};
 use app_macros::validate_api_secret;
 use app_shared::{
-    chrono::Utc,
+    chrono::{DateTime, Duration, Utc},
     models::ApiCaller,
     models::{
-        Account, AccountId, AccountIntegrations, ActionType, Actor, AnyUserId, ApiError,
-        DonationTier, Rights, Role, RoleId, Secret, UserRights,
+        Account, AccountExport, AccountId, AccountIntegrations, AccountSearch, ActionType, Actor,
+        AnyUserId, ApiError, DonationTier, ExportedSession, IntegrationKind, ProfileVisibility,
+        PublicProfile, Rights, Role, RoleId, Secret, UserRights, UsernameChange,
     },
     prelude::*,
     Database,
 };
 
+/// Наибольшее количество аккаунтов на странице поиска.
+const MAX_SEARCH_PAGE: usize = 100;
+
+/// Аватарка, которая ставится при сбросе.
+const DEFAULT_AVATAR_URL: &str = "/public/images/avatar.png";
+
+/// Сколько номеров перебрать для занятого имени, прежде чем взять имя по Discord ID.
+const MAX_USERNAME_SUFFIX: u32 = 100;
+
+/// Наибольшая длина имени пользователя в символах.
+const MAX_USERNAME_LENGTH: usize = 25;
+
 impl Api {
-    /// Создаёт новый аккаунт.
+    /// Создаёт новый аккаунт. Занятое имя дополняется номером, а запрещённое
+    /// заменяется на имя по Discord ID.
     pub fn create_account(
         &self,
         caller: ApiCaller,
@@ -20,12 +36,12 @@ impl Api {
         avatar_url: String,
         discord_user_id: DiscordUserId,
     ) -> Result<AccountId, ApiError> {
-        let mut new_username = username.clone();
+        let reason = caller.reason();
         let mut counter = 1;
         let mut actor = Actor::System;
 
-        if let ApiCaller::Token(secret) = caller {
-            let token = validate_api_secret!(secret);
+        if let ApiCaller::Token(secret, ip, _) = caller {
+            let token = validate_api_secret!(secret, ip);
 
             if !token.rights.user.contains(UserRights::CREATE_ACCOUNTS) {
                 return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
@@ -38,8 +54,24 @@ impl Api {
             };
         }
 
+        let fallback_username = format!("user{}", discord_user_id.0);
+
+        let username = if is_username_allowed(&username) {
+            username
+        } else {
+            fallback_username.clone()
+        };
+        let mut new_username = username.clone();
+
         loop {
-            if Database::lock(|database| database.is_username_free(new_username.clone())) {
+            if counter > MAX_USERNAME_SUFFIX {
+                new_username = fallback_username;
+                break;
+            }
+
+            if is_username_allowed(&new_username)
+                && Database::lock(|database| database.is_username_free(new_username.clone()))
+            {
                 break;
             } else {
                 new_username = format!("{username}{counter}");
@@ -56,6 +88,7 @@ impl Api {
                 actor,
                 Some(Actor::User(account_id)),
                 ActionType::AccountCreated,
+                reason,
             )
         });
 
@@ -69,6 +102,58 @@ impl Api {
         Database::lock(|database| database.get_accounts())
     }
 
+    /// Ищет аккаунты постранично.
+    #[instrument]
+    pub fn search_accounts(
+        &self,
+        caller: ApiCaller,
+        mut search: AccountSearch,
+    ) -> Result<AccountSearch, ApiError> {
+        trace!("search_accounts");
+
+        if let ApiCaller::Token(secret, ip, _) = caller {
+            let token = validate_api_secret!(secret, ip);
+
+            if !token.rights.user.contains(UserRights::VIEW_ACCOUNTS) {
+                return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
+            }
+        }
+
+        search.max_count = search.max_count.clamp(1, MAX_SEARCH_PAGE);
+
+        Ok(Database::lock(|database| database.search_accounts(search)))
+    }
+
+    /// Возвращает аккаунт для просмотра его данных.
+    /// Свой аккаунт можно просматривать без особых прав.
+    #[instrument]
+    pub fn get_account(
+        &self,
+        caller: ApiCaller,
+        account_id: AccountId,
+    ) -> Result<Account, ApiError> {
+        trace!("get_account");
+
+        if let ApiCaller::Token(secret, ip, _) = caller {
+            let token = validate_api_secret!(secret, ip);
+
+            if token.creator != Some(account_id)
+                && !token.rights.user.contains(UserRights::VIEW_ACCOUNTS)
+            {
+                return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
+            }
+        }
+
+        self.find_account_by_id(AnyUserId::AccountId(account_id))
+    }
+
+    #[instrument]
+    pub fn find_accounts_by_ids(&self, account_ids: Vec<AccountId>) -> Vec<Account> {
+        trace!("find_accounts_by_ids");
+
+        Database::lock(|database| database.find_accounts_by_ids(&account_ids))
+    }
+
     #[instrument]
     pub fn get_donation_tiers(&self) -> Vec<DonationTier> {
         trace!("get_donation_tiers");
@@ -86,15 +171,18 @@ impl Api {
     ) -> Result<(), ApiError> {
         trace!("connect_byond_account_by_2fa");
 
+        let reason = caller.reason();
+
+        let ckey = ByondUserId::parse(&ckey.0)?;
         let mut actor = Actor::System;
+        let mut attempts_key = format!("system:byond:{}", ckey.0);
 
-        if let ApiCaller::Token(secret) = caller {
-            let token = validate_api_secret!(secret);
+        if let ApiCaller::Token(secret, ip, _) = caller {
+            let token = validate_api_secret!(secret, ip);
 
             if !token
                 .rights
-                .user
-                .contains(UserRights::ADD_CONNECTED_ACCOUNTS)
+                .can_manage_integration(UserRights::ADD_CONNECTED_ACCOUNTS, IntegrationKind::Byond)
             {
                 return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
             }
@@ -104,9 +192,11 @@ impl Api {
             } else {
                 Actor::System
             };
+            attempts_key = format!("token:{}:byond:{}", token.id.0, ckey.0);
         }
 
-        let account = self.find_account_by_tfa_token_secret(tfa_secret)?;
+        let account =
+            self.find_account_by_tfa_token_secret(tfa_secret, actor.clone(), attempts_key, None)?;
         self.connect_byond_account(AnyUserId::AccountId(account.id), ckey.clone())?;
 
         Journal::lock(|journal| {
@@ -114,6 +204,7 @@ impl Api {
                 actor,
                 Some(Actor::User(account.id)),
                 ActionType::ByondConnected { ckey },
+                reason,
             )
         });
 
@@ -130,15 +221,17 @@ impl Api {
     ) -> Result<(), ApiError> {
         trace!("connect_ss14_account_by_2fa");
 
+        let reason = caller.reason();
+
         let mut actor = Actor::System;
+        let mut attempts_key = format!("system:ss14:{}", user_id.0);
 
-        if let ApiCaller::Token(secret) = caller {
-            let token = validate_api_secret!(secret);
+        if let ApiCaller::Token(secret, ip, _) = caller {
+            let token = validate_api_secret!(secret, ip);
 
             if !token
                 .rights
-                .user
-                .contains(UserRights::ADD_CONNECTED_ACCOUNTS)
+                .can_manage_integration(UserRights::ADD_CONNECTED_ACCOUNTS, IntegrationKind::SS14)
             {
                 return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
             }
@@ -148,9 +241,11 @@ impl Api {
             } else {
                 Actor::System
             };
+            attempts_key = format!("token:{}:ss14:{}", token.id.0, user_id.0);
         }
 
-        let account = self.find_account_by_tfa_token_secret(tfa_secret)?;
+        let account =
+            self.find_account_by_tfa_token_secret(tfa_secret, actor.clone(), attempts_key, None)?;
         self.connect_ss14_account(AnyUserId::AccountId(account.id), user_id.clone())?;
 
         Journal::lock(|journal| {
@@ -158,24 +253,50 @@ impl Api {
                 actor,
                 Some(Actor::User(account.id)),
                 ActionType::SS14Connected { ss14_guid: user_id },
+                reason,
             )
         });
 
         Ok(())
     }
 
-    /// Находит аккаунт по принадлежащему ему TFA токену.
+    /// Находит аккаунт по принадлежащему ему TFA токену, токен после этого недействителен.
+    /// `attempts_key` - от чьего имени ввели код: IP, а для доверенных сервисов токен
+    /// вместе с игроком. `ip` записывается в журнал, у доверенных сервисов его нет.
+    /// Неверные коды записываются в журнал от имени `actor`, частые неверные коды блокируют ключ.
     #[instrument]
     pub fn find_account_by_tfa_token_secret(
         &mut self,
         secret: Secret,
+        actor: Actor,
+        attempts_key: String,
+        ip: Option<String>,
     ) -> Result<Account, ApiError> {
         trace!("find_account_by_tfa_token_secret");
 
-        let token = self
-            .find_tfa_token_by_secret(secret)
-            .ok_or_else(|| ApiError::Other("Некорректный TFA токен".to_string()))?;
+        let config = ApiConfig::get().unwrap().tfa;
+
+        self.tfa_attempts.check(&attempts_key, &config)?;
+
+        let Some(token) = self.take_tfa_token_by_secret(secret) else {
+            let lockout = self.tfa_attempts.record_failure(&attempts_key, &config);
 
+            Journal::lock(|journal| {
+                journal.log(
+                    actor,
+                    None,
+                    ActionType::TFAAttemptFailed {
+                        ip,
+                        lockout_seconds: lockout.map(|lockout| lockout.num_seconds()),
+                    },
+                    None,
+                )
+            });
+
+            return Err(ApiError::Other("Некорректный TFA токен".to_string()));
+        };
+
+        // Верный код не сбрасывает неудачные попытки: свои коды можно получать без ограничений
         self.find_account_by_id(AnyUserId::DiscordId(token.discord_user_id))
     }
 
@@ -223,17 +344,33 @@ impl Api {
     ) -> Result<(), ApiError> {
         trace!("connect_byond_account");
 
-        if ckey.0.trim().is_empty() {
-            return Err(ApiError::Other("Пустой ckey".to_string()));
-        }
+        let ckey = ByondUserId::parse(&ckey.0)?;
 
-        let integrations = self.find_integrations_by_account_id(user_id.clone())?;
+        let integrations = self.find_integrations_by_account_id(user_id)?;
 
         if integrations.byond_ckey.is_some() {
             return Err(ApiError::Other("Аккаунт BYOND уже подключен".to_string()));
         }
 
-        Database::lock(|database| database.connect_account(user_id, AnyUserId::ByondCkey(ckey)));
+        let ckey_owner = Database::lock(|database| {
+            database.find_account_integrations_by_user_id(AnyUserId::ByondCkey(ckey.clone()))
+        });
+
+        if ckey_owner.is_some() {
+            return Err(ApiError::Other(
+                "Этот ckey уже привязан к другому аккаунту".to_string(),
+            ));
+        }
+
+        self.check_relink_cooldown(
+            integrations.account_id,
+            IntegrationKind::Byond,
+            ckey.0.clone(),
+        )?;
+
+        Database::lock(|database| {
+            database.link_integration(integrations.account_id, AnyUserId::ByondCkey(ckey))
+        });
 
         Ok(())
     }
@@ -250,20 +387,37 @@ impl Api {
             return Err(ApiError::Other("Пустой ss14_user_id".to_string()));
         }
 
-        let integrations = self.find_integrations_by_account_id(user_id.clone())?;
+        let integrations = self.find_integrations_by_account_id(user_id)?;
 
         if integrations.ss14_guid.is_some() {
             return Err(ApiError::Other("Аккаунт SS14 уже подключен".to_string()));
         }
 
+        let guid_owner = Database::lock(|database| {
+            database.find_account_integrations_by_user_id(AnyUserId::SS14Guid(ss14_user_id.clone()))
+        });
+
+        if guid_owner.is_some() {
+            return Err(ApiError::Other(
+                "Этот аккаунт SS14 уже привязан к другому аккаунту".to_string(),
+            ));
+        }
+
+        self.check_relink_cooldown(
+            integrations.account_id,
+            IntegrationKind::SS14,
+            ss14_user_id.0.clone(),
+        )?;
+
         Database::lock(|database| {
-            database.connect_account(user_id, AnyUserId::SS14Guid(ss14_user_id))
+            database.link_integration(integrations.account_id, AnyUserId::SS14Guid(ss14_user_id))
         });
 
         Ok(())
     }
 
-    /// Возвращает права аккаунта.
+    /// Возвращает права аккаунта по всем его ролям, даже если второй фактор не подключён.
+    /// С ними сравниваются права токена, когда действуют над этим аккаунтом.
     #[instrument]
     pub fn get_account_rights(&self, account_id: AccountId, roles: Option<Vec<Role>>) -> Rights {
         trace!("get_account_rights");
@@ -294,13 +448,15 @@ impl Api {
     ) -> Result<(), ApiError> {
         trace!("add_role_to_account");
 
+        let reason = caller.reason();
+
         let mut actor = Actor::System;
         let role = Database::lock(|database| database.find_role_by_id(role_id));
 
-        if let ApiCaller::Token(secret) = caller {
-            let token = validate_api_secret!(secret);
+        if let ApiCaller::Token(secret, ip, _) = caller {
+            let token = validate_api_secret!(secret, ip);
 
-            if !token.rights.user.contains(UserRights::ADD_ROLES) {
+            if !token.rights.can_manage_role(UserRights::ADD_ROLES, role_id) {
                 return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
             }
 
@@ -344,6 +500,7 @@ impl Api {
                 actor,
                 Some(Actor::User(account_id)),
                 ActionType::RoleAdded { role_id },
+                reason,
             )
         });
 
@@ -360,13 +517,18 @@ impl Api {
     ) -> Result<(), ApiError> {
         trace!("remove_role_from_account");
 
+        let reason = caller.reason();
+
         let mut actor = Actor::System;
         let role = Database::lock(|database| database.find_role_by_id(role_id));
 
-        if let ApiCaller::Token(secret) = caller {
-            let token = validate_api_secret!(secret);
+        if let ApiCaller::Token(secret, ip, _) = caller {
+            let token = validate_api_secret!(secret, ip);
 
-            if !token.rights.user.contains(UserRights::REMOVE_ROLES) {
+            if !token
+                .rights
+                .can_manage_role(UserRights::REMOVE_ROLES, role_id)
+            {
                 return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
             }
 
@@ -410,52 +572,375 @@ impl Api {
                 actor,
                 Some(Actor::User(account_id)),
                 ActionType::RoleRemoved { role_id },
+                reason,
+            )
+        });
+
+        Ok(())
+    }
+
+    /// Объединяет аккаунт-дубликат `source_id` в `target_id`: роли, интеграции,
+    /// сессии и токены переходят к `target_id`. Записи журнала остаются как есть
+    /// и находятся по `target_id` через перенаправление `merged_into`.
+    #[instrument]
+    pub fn merge_accounts(
+        &self,
+        caller: ApiCaller,
+        source_id: AccountId,
+        target_id: AccountId,
+    ) -> Result<(), ApiError> {
+        trace!("merge_accounts");
+
+        let reason = caller.reason();
+
+        if source_id == target_id {
+            return Err(ApiError::Other(
+                "Нельзя объединить аккаунт сам с собой".to_string(),
+            ));
+        }
+
+        let mut actor = Actor::System;
+
+        if let ApiCaller::Token(secret, ip, _) = caller {
+            let token = validate_api_secret!(secret, ip);
+
+            if !token.rights.user.contains(UserRights::MERGE_ACCOUNTS) {
+                return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
+            }
+
+            for account_id in [source_id, target_id] {
+                let account_rights = self.get_account_rights(account_id, None);
+
+                if (!token.is_service && token.rights < account_rights)
+                    || (token.is_service && token.rights <= account_rights)
+                {
+                    return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
+                }
+            }
+
+            actor = if let Some(account_id) = token.creator {
+                Actor::User(account_id)
+            } else {
+                Actor::System
+            };
+        }
+
+        let source = self.find_integrations_by_account_id(AnyUserId::AccountId(source_id))?;
+        let target = self.find_integrations_by_account_id(AnyUserId::AccountId(target_id))?;
+
+        // Поиск по ID объединённого аккаунта возвращает аккаунт, в который его объединили
+        if source.account_id != source_id || target.account_id != target_id {
+            return Err(ApiError::Other(
+                "Аккаунт уже объединён с другим".to_string(),
+            ));
+        }
+
+        if source.byond_ckey.is_some() && target.byond_ckey.is_some() {
+            return Err(ApiError::Other(
+                "У обоих аккаунтов подключен BYOND, сначала отвяжите один из них".to_string(),
+            ));
+        }
+
+        if source.ss14_guid.is_some() && target.ss14_guid.is_some() {
+            return Err(ApiError::Other(
+                "У обоих аккаунтов подключен SS14, сначала отвяжите один из них".to_string(),
+            ));
+        }
+
+        if source.github_user_id.is_some() && target.github_user_id.is_some() {
+            return Err(ApiError::Other(
+                "У обоих аккаунтов подключен GitHub, сначала отвяжите один из них".to_string(),
+            ));
+        }
+
+        Database::lock(|database| database.merge_accounts(source_id, target_id));
+
+        Journal::lock(|journal| {
+            journal.log(
+                actor,
+                Some(Actor::User(target_id)),
+                ActionType::AccountsMerged { source_id },
+                reason,
             )
         });
 
         Ok(())
     }
 
-    /// Меняет имя пользователя (если оно не занято).
+    /// Выгружает все данные, связанные с аккаунтом.
+    /// Свой аккаунт можно выгрузить без особых прав.
+    #[instrument]
+    pub fn export_account(
+        &self,
+        caller: ApiCaller,
+        account_id: AccountId,
+    ) -> Result<AccountExport, ApiError> {
+        trace!("export_account");
+
+        let account = self.get_account(caller, account_id)?;
+        let integrations =
+            self.find_integrations_by_account_id(AnyUserId::AccountId(account.id))?;
+        let actor = Actor::User(account.id);
+
+        let user_id = integrations.discord_user_id;
+
+        // Заметки администрации попадают в выгрузку, только если их разрешили показывать
+        let notes = Database::lock(|database| database.get_account_notes(account.id))
+            .into_iter()
+            .filter(|note| note.shareable)
+            .collect_vec();
+
+        let journal = Database::lock(|database| database.find_journal_entries_by_actor(actor))
+            .into_iter()
+            .filter(|entry| match &entry.action {
+                ActionType::NoteAdded { note_id } | ActionType::NoteDeleted { note_id } => {
+                    notes.iter().any(|note| note.id == *note_id)
+                }
+                _ => true,
+            })
+            .collect();
+
+        Ok(AccountExport {
+            exported_at: Utc::now(),
+            integration_links: self.get_integration_links(account.id),
+            sessions: self
+                .get_account_sessions(account.id)
+                .into_iter()
+                .map(ExportedSession::from)
+                .collect(),
+            api_tokens: self.get_account_api_tokens(account.id),
+            journal,
+            notes,
+            feature_votes: Database::lock(|database| {
+                database.find_feature_votes_by_user_id(user_id)
+            }),
+            bug_reports: Database::lock(|database| database.find_bug_reports_by_user_id(user_id)),
+            account,
+        })
+    }
+
+    /// Удаляет аккаунт. Из записей журнала о нём убираются личные данные.
+    /// Свой аккаунт можно удалить без особых прав.
+    #[instrument]
+    pub fn delete_account(&self, caller: ApiCaller, account_id: AccountId) -> Result<(), ApiError> {
+        trace!("delete_account");
+
+        let reason = caller.reason();
+
+        let mut actor = Actor::System;
+
+        if let ApiCaller::Token(secret, ip, _) = caller {
+            let token = validate_api_secret!(secret, ip);
+
+            if token.creator != Some(account_id) {
+                if !token.rights.user.contains(UserRights::DELETE_ACCOUNTS) {
+                    return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
+                }
+
+                let account_rights = self.get_account_rights(account_id, None);
+
+                if (!token.is_service && token.rights < account_rights)
+                    || (token.is_service && token.rights <= account_rights)
+                {
+                    return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
+                }
+            }
+
+            actor = match token.creator {
+                Some(creator) if creator == account_id => Actor::Deleted,
+                Some(creator) => Actor::User(creator),
+                None => Actor::System,
+            };
+        }
+
+        let integrations =
+            self.find_integrations_by_account_id(AnyUserId::AccountId(account_id))?;
+
+        // Поиск по ID объединённого аккаунта возвращает аккаунт, в который его объединили
+        if integrations.account_id != account_id {
+            return Err(ApiError::Other(
+                "Аккаунт уже объединён с другим".to_string(),
+            ));
+        }
+
+        // Из записей об аккаунте и объединённых в него дубликатах убираются личные данные.
+        // Список записей попадает в цепочку раньше правки, чтобы проверка цепочки их пропускала
+        let redactions = Database::lock(|database| {
+            database.find_journal_entries_by_subject(Actor::User(account_id))
+        })
+        .into_iter()
+        .filter_map(|entry| Some((entry.id, entry.action.redacted()?)))
+        .collect_vec();
+
+        if !redactions.is_empty() {
+            let entry_ids = redactions.iter().map(|(entry_id, _)| *entry_id).collect();
+
+            Journal::lock(|journal| {
+                journal.log(
+                    actor.clone(),
+                    Some(Actor::Deleted),
+                    ActionType::JournalEntriesRedacted { entry_ids },
+                    None,
+                )
+            });
+        }
+
+        Database::lock(|database| database.delete_account(account_id, &redactions));
+        Avatars::remove(account_id, None);
+
+        Journal::lock(|journal| {
+            journal.log(
+                actor,
+                Some(Actor::Deleted),
+                ActionType::AccountDeleted,
+                reason,
+            )
+        });
+
+        Ok(())
+    }
+
+    /// Меняет имя пользователя, если оно не занято и не запрещено.
+    /// Сменить имя повторно можно только после задержки из конфига.
     #[instrument]
     pub fn change_username(
         &self,
         user_id: AnyUserId,
-        mut new_username: String,
+        new_username: String,
     ) -> Result<(), ApiError> {
         trace!("change_username");
 
-        new_username = new_username.trim().to_string();
+        let new_username = normalize_username(new_username)?;
 
-        if new_username.is_empty() {
+        if !is_username_allowed(&new_username) {
             return Err(ApiError::Other(
-                "Имя пользователя не должно быть пустым".to_string(),
+                "Это имя пользователя запрещено".to_string(),
             ));
         }
 
-        if new_username.chars().count() > 25 {
-            return Err(ApiError::Other(
-                "Имя пользователя не должно быть длинее 25 символов!".to_string(),
-            ));
+        let account = self.find_account_by_id(user_id)?;
+
+        if let Some(available_at) = self.get_username_change_available_at(account.id) {
+            return Err(ApiError::Other(format!(
+                "Сменить имя снова можно будет после {}",
+                available_at.format("%d.%m.%Y %H:%M UTC")
+            )));
         }
 
         if !Database::lock(|database| database.is_username_free(new_username.clone())) {
             return Err(ApiError::Other("Имя пользователя занято".to_string()));
         }
 
-        let account = self.find_account_by_id(user_id)?;
-        Database::lock(|database| database.change_username(account.id, new_username));
+        Database::lock(|database| database.change_username(account.id, new_username.clone()));
+
+        Journal::lock(|journal| {
+            journal.log(
+                Actor::User(account.id),
+                Some(Actor::User(account.id)),
+                ActionType::UsernameChanged {
+                    old: account.username,
+                    new: new_username,
+                },
+                None,
+            )
+        });
+
+        Ok(())
+    }
+
+    /// Меняет имя пользователя в обход задержки и запрещённых имён.
+    #[instrument]
+    pub fn force_change_username(
+        &self,
+        caller: ApiCaller,
+        account_id: AccountId,
+        new_username: String,
+    ) -> Result<(), ApiError> {
+        trace!("force_change_username");
+
+        let reason = caller.reason();
+
+        let mut actor = Actor::System;
+
+        if let ApiCaller::Token(secret, ip, _) = caller {
+            let token = validate_api_secret!(secret, ip);
+
+            if !token.rights.user.contains(UserRights::RENAME_ACCOUNTS) {
+                return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
+            }
+
+            let account_rights = self.get_account_rights(account_id, None);
+
+            if (!token.is_service && token.rights < account_rights)
+                || (token.is_service && token.rights <= account_rights)
+            {
+                return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
+            }
+
+            actor = if let Some(account_id) = token.creator {
+                Actor::User(account_id)
+            } else {
+                Actor::System
+            };
+        }
+
+        let new_username = normalize_username(new_username)?;
+        let account = self.find_account_by_id(AnyUserId::AccountId(account_id))?;
+
+        if account.username == new_username {
+            return Err(ApiError::Other("У пользователя уже это имя".to_string()));
+        }
+
+        if !Database::lock(|database| database.is_username_free(new_username.clone())) {
+            return Err(ApiError::Other("Имя пользователя занято".to_string()));
+        }
+
+        Database::lock(|database| database.change_username(account.id, new_username.clone()));
+
+        Journal::lock(|journal| {
+            journal.log(
+                actor,
+                Some(Actor::User(account.id)),
+                ActionType::UsernameChanged {
+                    old: account.username,
+                    new: new_username,
+                },
+                reason,
+            )
+        });
 
         Ok(())
     }
 
-    /// Меняет аватарку пользователя.
+    /// Время, после которого пользователь сможет снова сменить имя, `None` - уже может.
+    #[instrument]
+    pub fn get_username_change_available_at(&self, account_id: AccountId) -> Option<DateTime<Utc>> {
+        trace!("get_username_change_available_at");
+
+        let cooldown = Duration::days(ApiConfig::get().unwrap().username_cooldown_days);
+        let last_change = self.get_username_history(account_id).into_iter().next()?;
+        let available_at = last_change.changed_at + cooldown;
+
+        Some(available_at).filter(|available_at| Utc::now() < *available_at)
+    }
+
+    /// История смены имени пользователя, начиная с последней.
+    #[instrument]
+    pub fn get_username_history(&self, account_id: AccountId) -> Vec<UsernameChange> {
+        trace!("get_username_history");
+
+        Database::lock(|database| database.get_username_history(account_id))
+    }
+
+    /// Начинает смену аватарки пользователя на скачанную по ссылке. Возвращает аватарку,
+    /// которую нужно сохранить через `PendingAvatar::store` и `finish_avatar_change`.
+    /// Пустая ссылка сразу сбрасывает аватарку на стандартную, тогда возвращается `None`.
     #[instrument]
     pub fn change_avatar_url(
         &self,
         user_id: AnyUserId,
-        mut new_avatar_url: String,
-    ) -> Result<(), ApiError> {
+        new_avatar_url: String,
+    ) -> Result<Option<PendingAvatar>, ApiError> {
         trace!("change_avatar_url");
 
         if new_avatar_url.chars().count() > 200 {
@@ -464,12 +949,119 @@ impl Api {
             ));
         }
 
+        let account = self.find_account_by_id(user_id)?;
+
+        if new_avatar_url == account.avatar_url {
+            return Ok(None);
+        }
+
         if new_avatar_url.trim().is_empty() {
-            new_avatar_url = String::from("/public/images/avatar.png");
+            Avatars::remove(account.id, None);
+            self.set_avatar_url(account, String::from(DEFAULT_AVATAR_URL));
+
+            return Ok(None);
         }
 
+        self.avatars.fetch(account.id, &new_avatar_url).map(Some)
+    }
+
+    /// Начинает смену аватарки пользователя на загруженное изображение.
+    #[instrument(skip(image))]
+    pub fn upload_avatar(
+        &self,
+        user_id: AnyUserId,
+        image: Vec<u8>,
+    ) -> Result<PendingAvatar, ApiError> {
+        trace!("upload_avatar");
+
         let account = self.find_account_by_id(user_id)?;
-        Database::lock(|database| database.change_avatar_url(account.id, new_avatar_url));
+
+        Ok(Avatars::upload(account.id, image))
+    }
+
+    /// Начинает смену аватарки пользователя на его текущую аватарку в Discord.
+    #[instrument]
+    pub fn sync_discord_avatar(&self, user_id: AnyUserId) -> Result<PendingAvatar, ApiError> {
+        trace!("sync_discord_avatar");
+
+        let account = self.find_account_by_id(user_id)?;
+        let discord_avatar_url = self
+            .discord_api
+            .get_discord_avatar_url(account.integrations.discord_user_id)
+            .ok_or_else(|| {
+                ApiError::Other("Не удалось получить аватарку из Discord".to_string())
+            })?;
+
+        let discord_avatar_url = reqwest::Url::parse(&discord_avatar_url)
+            .map_err(|_| ApiError::Internal("Некорректная ссылка Discord".to_string()))?;
+
+        Ok(self.avatars.fetch_trusted(account.id, discord_avatar_url))
+    }
+
+    /// Ставит пользователю аватарку, сохранённую через `PendingAvatar::store`.
+    #[instrument]
+    pub fn finish_avatar_change(
+        &self,
+        user_id: AnyUserId,
+        new_avatar_url: String,
+    ) -> Result<(), ApiError> {
+        trace!("finish_avatar_change");
+
+        let account = self.find_account_by_id(user_id)?;
+        self.set_avatar_url(account, new_avatar_url);
+
+        Ok(())
+    }
+
+    /// Сохраняет новую аватарку пользователя и записывает смену в журнал.
+    fn set_avatar_url(&self, account: Account, new_avatar_url: String) {
+        Database::lock(|database| database.change_avatar_url(account.id, new_avatar_url.clone()));
+
+        Journal::lock(|journal| {
+            journal.log(
+                Actor::User(account.id),
+                Some(Actor::User(account.id)),
+                ActionType::AvatarChanged {
+                    old: account.avatar_url,
+                    new: new_avatar_url,
+                },
+                None,
+            )
+        });
+    }
+
+    /// Профиль пользователя без скрытых им полей.
+    #[instrument]
+    pub fn get_public_profile(&self, user_id: AnyUserId) -> Result<PublicProfile, ApiError> {
+        trace!("get_public_profile");
+
+        self.find_account_by_id(user_id).map(PublicProfile::new)
+    }
+
+    /// Профиль пользователя по имени без скрытых им полей.
+    #[instrument]
+    pub fn get_public_profile_by_username(
+        &self,
+        username: String,
+    ) -> Result<PublicProfile, ApiError> {
+        trace!("get_public_profile_by_username");
+
+        Database::lock(|database| database.find_account_by_username(username))
+            .map(PublicProfile::new)
+            .ok_or_else(|| ApiError::Other("Пользователь не найден".to_string()))
+    }
+
+    /// Меняет поля профиля, которые видны другим пользователям.
+    #[instrument]
+    pub fn change_profile_visibility(
+        &self,
+        user_id: AnyUserId,
+        visibility: ProfileVisibility,
+    ) -> Result<(), ApiError> {
+        trace!("change_profile_visibility");
+
+        let account = self.find_account_by_id(user_id)?;
+        Database::lock(|database| database.change_profile_visibility(account.id, visibility));
 
         Ok(())
     }
@@ -486,3 +1078,41 @@ impl Api {
         self.find_account_by_id(AnyUserId::AccountId(session.account_id))
     }
 }
+
+/// Обрезает пробелы и проверяет длину имени пользователя.
+fn normalize_username(username: String) -> Result<String, ApiError> {
+    let username = username.trim().to_string();
+
+    if username.is_empty() {
+        return Err(ApiError::Other(
+            "Имя пользователя не должно быть пустым".to_string(),
+        ));
+    }
+
+    if username.chars().count() > MAX_USERNAME_LENGTH {
+        return Err(ApiError::Other(format!(
+            "Имя пользователя не должно быть длинее {MAX_USERNAME_LENGTH} символов!"
+        )));
+    }
+
+    Ok(username)
+}
+
+/// Проверяет имя по зарезервированным именам и запрещённым выражениям из конфига.
+fn is_username_allowed(username: &str) -> bool {
+    let config = ApiConfig::get().unwrap();
+    let lowercase = username.to_lowercase();
+
+    if config
+        .reserved_usernames
+        .iter()
+        .any(|reserved| reserved.to_lowercase() == lowercase)
+    {
+        return false;
+    }
+
+    !config
+        .username_deny_list
+        .iter()
+        .any(|pattern| pattern.is_match(username))
+}
@@ -1,6 +1,6 @@
-use crate::Api;
+use crate::{Api, Journal};
 use app_shared::{
-    models::{BugReport, FeatureVote, FeatureVoteDescriptor},
+    models::{ActionType, Actor, AnyUserId, BugReport, FeatureVote, FeatureVoteDescriptor},
     prelude::*,
     Database,
 };
@@ -19,7 +19,27 @@ impl Api {
     pub fn end_feature_vote(&self, descriptor: FeatureVoteDescriptor) {
         trace!("end_feature_vote api");
 
+        let Some(vote) = self.get_feature_vote(descriptor) else {
+            return;
+        };
+
+        if vote.is_vote_ended {
+            return;
+        }
+
         Database::lock(|database| database.end_feature_vote(descriptor));
+
+        let author =
+            Database::lock(|database| database.find_account(AnyUserId::DiscordId(vote.author_id)));
+
+        Journal::lock(|journal| {
+            journal.log(
+                Actor::System,
+                author.map(|author| Actor::User(author.id)),
+                ActionType::FeatureVoteEnded { descriptor },
+                None,
+            )
+        });
     }
 
     /// Проверяет, является ли голосование оконченным.
@@ -0,0 +1,190 @@
+use crate::github::{GithubConfig, GithubUser};
+use crate::{Api, Journal};
+use app_macros::validate_api_secret;
+use app_shared::{
+    models::{
+        AccountId, ActionType, Actor, AnyUserId, ApiCaller, ApiError, GithubLinkCode,
+        IntegrationKind,
+    },
+    prelude::*,
+    serde::Deserialize,
+    serde_json, Database,
+};
+
+/// Событие вебхука GitHub о PR, нужные поля.
+#[derive(Debug, Clone, Deserialize)]
+struct PullRequestEvent {
+    action: String,
+    pull_request: PullRequest,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PullRequest {
+    merged: bool,
+    user: GithubUser,
+}
+
+impl Api {
+    /// Начинает привязку GitHub аккаунта. Возвращает код, который нужно ввести на GitHub.
+    /// Привязать GitHub может только владелец аккаунта.
+    #[instrument]
+    pub fn start_github_link(
+        &mut self,
+        caller: ApiCaller,
+        account_id: AccountId,
+    ) -> Result<GithubLinkCode, ApiError> {
+        trace!("start_github_link");
+
+        self.check_github_link_caller(caller, account_id)?;
+
+        let integrations =
+            self.find_integrations_by_account_id(AnyUserId::AccountId(account_id))?;
+
+        if integrations.github_user_id.is_some() {
+            return Err(ApiError::Other("Аккаунт GitHub уже подключен".to_string()));
+        }
+
+        self.github.start_device_flow(account_id)
+    }
+
+    /// Код незавершённой привязки GitHub аккаунта.
+    #[instrument]
+    pub fn find_github_link_code(&self, account_id: AccountId) -> Option<GithubLinkCode> {
+        trace!("find_github_link_code");
+
+        self.github.find_device_flow(account_id)
+    }
+
+    /// Завершает привязку GitHub аккаунта, если код уже подтверждён на GitHub.
+    /// Возвращает `false`, пока пользователь не ввёл код.
+    #[instrument]
+    pub fn finish_github_link(
+        &mut self,
+        caller: ApiCaller,
+        account_id: AccountId,
+    ) -> Result<bool, ApiError> {
+        trace!("finish_github_link");
+
+        let reason = caller.reason();
+
+        let actor = self.check_github_link_caller(caller, account_id)?;
+
+        let Some(user) = self.github.poll_device_flow(account_id)? else {
+            return Ok(false);
+        };
+
+        let integrations =
+            self.find_integrations_by_account_id(AnyUserId::AccountId(account_id))?;
+
+        if integrations.github_user_id.is_some() {
+            return Err(ApiError::Other("Аккаунт GitHub уже подключен".to_string()));
+        }
+
+        let owner = Database::lock(|database| {
+            database.find_account_integrations_by_user_id(AnyUserId::GithubId(user.id))
+        });
+
+        if owner.is_some() {
+            return Err(ApiError::Other(
+                "Этот GitHub аккаунт уже привязан к другому аккаунту".to_string(),
+            ));
+        }
+
+        self.check_relink_cooldown(account_id, IntegrationKind::Github, user.id.0.to_string())?;
+
+        Database::lock(|database| {
+            database.link_integration(account_id, AnyUserId::GithubId(user.id));
+            database.update_github_login(account_id, user.login);
+        });
+
+        Journal::lock(|journal| {
+            journal.log(
+                actor,
+                Some(Actor::User(account_id)),
+                ActionType::GithubConnected {
+                    github_user_id: user.id,
+                },
+                reason,
+            )
+        });
+
+        Ok(true)
+    }
+
+    /// Обрабатывает вебхук GitHub: автору принятого PR выдаётся роль программиста.
+    #[instrument(skip(body))]
+    pub fn handle_github_webhook(
+        &self,
+        event: String,
+        signature: String,
+        body: Vec<u8>,
+    ) -> Result<(), ApiError> {
+        trace!("handle_github_webhook");
+
+        if !self.github.is_webhook_signature_valid(&body, &signature) {
+            return Err(ApiError::Unauthorized("Некорректная подпись".to_string()));
+        }
+
+        if event != "pull_request" {
+            return Ok(());
+        }
+
+        let event = serde_json::from_slice::<PullRequestEvent>(&body)
+            .map_err(|err| ApiError::Other(err.to_string()))?;
+
+        if event.action != "closed" || !event.pull_request.merged {
+            return Ok(());
+        }
+
+        let Some(role_id) = GithubConfig::get().unwrap().coder_role_id else {
+            return Ok(());
+        };
+
+        let author = event.pull_request.user;
+
+        let Some(integrations) = Database::lock(|database| {
+            database.find_account_integrations_by_user_id(AnyUserId::GithubId(author.id))
+        }) else {
+            debug!(
+                "author of merged pull request {} is not linked",
+                author.login
+            );
+            return Ok(());
+        };
+
+        let account_id = integrations.account_id;
+        Database::lock(|database| database.update_github_login(account_id, author.login));
+
+        if self
+            .get_account_roles(account_id)
+            .iter()
+            .any(|role| role.id == role_id)
+        {
+            return Ok(());
+        }
+
+        self.add_role_to_account(ApiCaller::System, account_id, role_id)
+    }
+
+    /// Привязывать GitHub может только сам владелец аккаунта.
+    #[instrument]
+    fn check_github_link_caller(
+        &self,
+        caller: ApiCaller,
+        account_id: AccountId,
+    ) -> Result<Actor, ApiError> {
+        trace!("check_github_link_caller");
+
+        if let ApiCaller::Token(secret, ip, _) = caller {
+            let token = validate_api_secret!(secret, ip);
+
+            if token.creator != Some(account_id) {
+                return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
+            }
+
+            return Ok(Actor::User(account_id));
+        }
+
+        Ok(Actor::System)
+    }
+}
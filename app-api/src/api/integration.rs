@@ -0,0 +1,225 @@
+use crate::api_config::ApiConfig;
+use crate::{Api, Journal};
+use app_macros::validate_api_secret;
+use app_shared::{
+    chrono::{Duration, Utc},
+    models::{
+        AccountId, AccountIntegrations, ActionType, Actor, AnyUserId, ApiCaller, ApiError,
+        IntegrationKind, IntegrationLink, UserRights,
+    },
+    prelude::*,
+    Database,
+};
+
+impl Api {
+    /// Отвязывает BYOND, SS14 или GitHub аккаунт. Владелец аккаунта может сделать это сам.
+    #[instrument]
+    pub fn disconnect_integration(
+        &self,
+        caller: ApiCaller,
+        account_id: AccountId,
+        kind: IntegrationKind,
+    ) -> Result<(), ApiError> {
+        trace!("disconnect_integration");
+
+        let reason = caller.reason();
+        let mut actor = Actor::System;
+
+        if let ApiCaller::Token(secret, ip, _) = caller {
+            let token = validate_api_secret!(secret, ip);
+
+            if token.creator != Some(account_id)
+                && !token
+                    .rights
+                    .can_manage_integration(UserRights::REMOVE_CONNECTED_ACCOUNTS, kind)
+            {
+                return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
+            }
+
+            actor = if let Some(account_id) = token.creator {
+                Actor::User(account_id)
+            } else {
+                Actor::System
+            };
+        }
+
+        let integrations =
+            self.find_integrations_by_account_id(AnyUserId::AccountId(account_id))?;
+
+        let Some(action) = disconnected_action(&integrations, kind) else {
+            return Err(ApiError::Other("Интеграция не подключена".to_string()));
+        };
+
+        Database::lock(|database| database.unlink_integration(account_id, kind));
+
+        Journal::lock(|journal| journal.log(actor, Some(Actor::User(account_id)), action, reason));
+
+        Ok(())
+    }
+
+    /// Принудительно привязывает BYOND, SS14 или GitHub аккаунт, минуя задержку.
+    /// Прежние привязки отвязываются как у этого аккаунта, так и у чужого.
+    #[instrument]
+    pub fn force_relink_integration(
+        &self,
+        caller: ApiCaller,
+        account_id: AccountId,
+        integration_id: AnyUserId,
+    ) -> Result<(), ApiError> {
+        trace!("force_relink_integration");
+
+        let reason = caller.reason();
+
+        let Some((kind, _)) = IntegrationKind::of(&integration_id) else {
+            return Err(ApiError::Other(
+                "Перепривязать можно только BYOND, SS14 или GitHub аккаунт".to_string(),
+            ));
+        };
+
+        let integration_id = match integration_id {
+            AnyUserId::ByondCkey(ckey) => AnyUserId::ByondCkey(ByondUserId::parse(&ckey.0)?),
+            integration_id => integration_id,
+        };
+
+        let mut actor = Actor::System;
+
+        if let ApiCaller::Token(secret, ip, _) = caller {
+            let token = validate_api_secret!(secret, ip);
+
+            if !token
+                .rights
+                .can_manage_integration(UserRights::ADD_CONNECTED_ACCOUNTS, kind)
+                || !token
+                    .rights
+                    .can_manage_integration(UserRights::REMOVE_CONNECTED_ACCOUNTS, kind)
+            {
+                return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
+            }
+
+            actor = if let Some(account_id) = token.creator {
+                Actor::User(account_id)
+            } else {
+                Actor::System
+            };
+        }
+
+        let integrations =
+            self.find_integrations_by_account_id(AnyUserId::AccountId(account_id))?;
+
+        let previous_owner = Database::lock(|database| {
+            database.find_account_integrations_by_user_id(integration_id.clone())
+        });
+
+        if previous_owner
+            .as_ref()
+            .is_some_and(|previous_owner| previous_owner.account_id == account_id)
+        {
+            return Err(ApiError::Other(
+                "Интеграция уже привязана к этому аккаунту".to_string(),
+            ));
+        }
+
+        // Прежние привязки отвязываются в одной транзакции с новой привязкой
+        let mut unlinked = previous_owner
+            .iter()
+            .filter_map(|previous_owner| {
+                disconnected_action(previous_owner, kind)
+                    .map(|action| (previous_owner.account_id, action))
+            })
+            .collect_vec();
+
+        let current_action = disconnected_action(&integrations, kind);
+        let unlink_current = current_action.is_some();
+        unlinked.extend(current_action.map(|action| (account_id, action)));
+
+        Database::lock(|database| {
+            database.relink_integration(
+                account_id,
+                integration_id.clone(),
+                previous_owner.map(|previous_owner| previous_owner.account_id),
+                unlink_current,
+            )
+        });
+
+        for (unlinked_id, action) in unlinked {
+            Journal::lock(|journal| {
+                journal.log(
+                    actor.clone(),
+                    Some(Actor::User(unlinked_id)),
+                    action,
+                    reason.clone(),
+                )
+            });
+        }
+
+        let action = match integration_id {
+            AnyUserId::ByondCkey(ckey) => ActionType::ByondConnected { ckey },
+            AnyUserId::SS14Guid(ss14_guid) => ActionType::SS14Connected { ss14_guid },
+            AnyUserId::GithubId(github_user_id) => ActionType::GithubConnected { github_user_id },
+            AnyUserId::DiscordId(_) | AnyUserId::AccountId(_) => unreachable!(),
+        };
+
+        Journal::lock(|journal| journal.log(actor, Some(Actor::User(account_id)), action, reason));
+
+        Ok(())
+    }
+
+    /// Возвращает историю привязок интеграций аккаунта, начиная с последней.
+    #[instrument]
+    pub fn get_integration_links(&self, account_id: AccountId) -> Vec<IntegrationLink> {
+        trace!("get_integration_links");
+
+        Database::lock(|database| database.get_integration_links(account_id))
+    }
+
+    /// Проверяет, что ID интеграции недавно не отвязывали от другого аккаунта.
+    #[instrument]
+    pub(crate) fn check_relink_cooldown(
+        &self,
+        account_id: AccountId,
+        kind: IntegrationKind,
+        integration_id: String,
+    ) -> Result<(), ApiError> {
+        trace!("check_relink_cooldown");
+
+        let last_unlink = Database::lock(|database| {
+            database.find_last_integration_unlink(account_id, kind, integration_id)
+        });
+
+        let Some(last_unlink) = last_unlink else {
+            return Ok(());
+        };
+
+        let cooldown = Duration::days(ApiConfig::get().unwrap().relink_cooldown_days);
+        let available_at = last_unlink + cooldown;
+
+        if Utc::now() < available_at {
+            return Err(ApiError::Other(format!(
+                "Привязать аккаунт повторно можно будет после {}",
+                available_at.format("%d.%m.%Y %H:%M UTC")
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Запись журнала об отвязке интеграции, `None` - интеграция не подключена.
+fn disconnected_action(
+    integrations: &AccountIntegrations,
+    kind: IntegrationKind,
+) -> Option<ActionType> {
+    match kind {
+        IntegrationKind::Byond => integrations
+            .byond_ckey
+            .clone()
+            .map(|ckey| ActionType::ByondDisconnected { ckey }),
+        IntegrationKind::SS14 => integrations
+            .ss14_guid
+            .clone()
+            .map(|ss14_guid| ActionType::SS14Disconnected { ss14_guid }),
+        IntegrationKind::Github => integrations
+            .github_user_id
+            .map(|github_user_id| ActionType::GithubDisconnected { github_user_id }),
+    }
+}
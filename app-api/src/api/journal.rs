@@ -1,5 +1,16 @@
-use crate::Api;
-use app_shared::{models::JournalEntryCursor, prelude::*, Database};
+use crate::{Api, Journal};
+use app_macros::validate_api_secret;
+use app_shared::{
+    models::{
+        Actor, ApiCaller, ApiError, JournalChainReport, JournalEntryCursor, JournalQuery,
+        UserRights,
+    },
+    prelude::*,
+    Database,
+};
+
+/// Наибольшее количество записей журнала на странице выборки.
+const MAX_JOURNAL_PAGE: usize = 100;
 
 impl Api {
     pub fn get_journal_entries(&self, current: JournalEntryCursor) -> Option<JournalEntryCursor> {
@@ -7,4 +18,67 @@ impl Api {
 
         Database::lock(|database| database.get_journal_entries(current))
     }
+
+    /// Выбирает записи журнала по фильтрам.
+    /// Записи о своём аккаунте можно выбирать без особых прав.
+    #[instrument]
+    pub fn query_journal(
+        &self,
+        caller: ApiCaller,
+        mut query: JournalQuery,
+    ) -> Result<JournalQuery, ApiError> {
+        trace!("query_journal");
+
+        if let ApiCaller::Token(secret, ip, _) = caller {
+            let token = validate_api_secret!(secret, ip);
+
+            let is_own_journal = match (&query.subject, token.creator) {
+                (Some(Actor::User(subject)), Some(creator)) => *subject == creator,
+                _ => false,
+            };
+
+            let can_view_journal = token.rights.user.contains(UserRights::VIEW_JOURNAL);
+
+            if !is_own_journal && !can_view_journal {
+                return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
+            }
+
+            // Скрытые заметки администрации не видны владельцу аккаунта, как и в выгрузке
+            if !can_view_journal {
+                query.shareable_only = true;
+            }
+        }
+
+        if let (Some(from), Some(to)) = (query.from, query.to) {
+            if from >= to {
+                return Err(ApiError::Other(
+                    "Начало периода должно быть раньше конца".to_string(),
+                ));
+            }
+        }
+
+        query.action = query
+            .action
+            .map(|action| action.trim().to_string())
+            .filter(|action| !action.is_empty());
+        query.max_count = query.max_count.clamp(1, MAX_JOURNAL_PAGE);
+
+        Ok(Database::lock(|database| database.query_journal(query)))
+    }
+
+    /// Проверяет цепочку хэшей журнала.
+    #[instrument]
+    pub fn verify_journal_chain(&self, caller: ApiCaller) -> Result<JournalChainReport, ApiError> {
+        trace!("verify_journal_chain");
+
+        if let ApiCaller::Token(secret, ip, _) = caller {
+            let token = validate_api_secret!(secret, ip);
+
+            if !token.rights.user.contains(UserRights::VIEW_JOURNAL) {
+                return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
+            }
+        }
+
+        Ok(Journal::lock(|journal| journal.verify_chain()))
+    }
 }
@@ -0,0 +1,323 @@
+use crate::api_config::ApiConfig;
+use crate::totp::Totp;
+use crate::{Api, Journal};
+use app_shared::{
+    chrono::Utc,
+    models::{
+        AccountId, AccountMfa, ActionType, Actor, AnyUserId, ApiError, MfaStatus, Rights, Role,
+        Secret, SecretHash, Session, TotpEnrollment,
+    },
+    prelude::*,
+    Database,
+};
+
+/// Сколько кодов восстановления выдаётся за раз.
+const RECOVERY_CODES_COUNT: usize = 10;
+const RECOVERY_CODE_LENGTH: usize = 10;
+
+impl Api {
+    /// Возвращает состояние второго фактора аккаунта.
+    #[instrument]
+    pub fn get_mfa_status(&self, account_id: AccountId) -> MfaStatus {
+        trace!("get_mfa_status");
+
+        let mfa = self.find_enabled_mfa(account_id);
+
+        MfaStatus {
+            enabled: mfa.is_some(),
+            required: self.is_mfa_required(account_id),
+            recovery_codes_left: mfa.map_or(0, |mfa| mfa.recovery_codes.len()),
+        }
+    }
+
+    #[instrument]
+    pub fn is_mfa_enabled(&self, account_id: AccountId) -> bool {
+        trace!("is_mfa_enabled");
+
+        self.find_enabled_mfa(account_id).is_some()
+    }
+
+    /// Одна из ролей аккаунта требует второй фактор.
+    #[instrument]
+    pub fn is_mfa_required(&self, account_id: AccountId) -> bool {
+        trace!("is_mfa_required");
+
+        self.get_account_roles(account_id)
+            .iter()
+            .any(|role| role.requires_mfa)
+    }
+
+    /// Права сессий аккаунта: роли, требующие второй фактор, без него не действуют.
+    #[instrument]
+    pub fn get_session_rights(&self, account_id: AccountId) -> Rights {
+        trace!("get_session_rights");
+
+        Role::sum_session_rights(
+            self.get_account_roles(account_id),
+            self.is_mfa_enabled(account_id),
+        )
+    }
+
+    /// Начинает подключение TOTP. Начатое, но не подтверждённое подключение начинается заново.
+    #[instrument]
+    pub fn start_totp_enrollment(&self, account_id: AccountId) -> Result<TotpEnrollment, ApiError> {
+        trace!("start_totp_enrollment");
+
+        let account = self.find_account_by_id(AnyUserId::AccountId(account_id))?;
+        let secret = Totp::new_secret();
+
+        if !Database::lock(|database| database.start_account_mfa(account_id, secret.clone())) {
+            return Err(ApiError::Other("Второй фактор уже подключён".to_string()));
+        }
+
+        Ok(Totp::enrollment(&secret, &account.username))
+    }
+
+    /// Возвращает начатое, но ещё не подтверждённое подключение TOTP.
+    #[instrument]
+    pub fn get_pending_totp_enrollment(&self, account_id: AccountId) -> Option<TotpEnrollment> {
+        trace!("get_pending_totp_enrollment");
+
+        let mfa = Database::lock(|database| database.find_account_mfa(account_id))
+            .filter(|mfa| !mfa.is_enabled())?;
+        let account = self
+            .find_account_by_id(AnyUserId::AccountId(account_id))
+            .ok()?;
+
+        Some(Totp::enrollment(&mfa.totp_secret, &account.username))
+    }
+
+    /// Подключает TOTP по первому коду из приложения. Возвращает коды восстановления,
+    /// они показываются только один раз. Остальные сессии аккаунта вошли без второго фактора,
+    /// поэтому завершаются, а права текущей обновляются.
+    #[instrument(skip(code))]
+    pub fn confirm_totp_enrollment(
+        &mut self,
+        session: Session,
+        code: String,
+        ip: String,
+    ) -> Result<Vec<String>, ApiError> {
+        trace!("confirm_totp_enrollment");
+
+        let account_id = session.account_id;
+
+        let Some(mfa) = Database::lock(|database| database.find_account_mfa(account_id))
+            .filter(|mfa| !mfa.is_enabled())
+        else {
+            return Err(ApiError::Other(
+                "Подключение второго фактора не начато".to_string(),
+            ));
+        };
+
+        let config = ApiConfig::get().unwrap().tfa;
+        let key = account_id.0.to_string();
+
+        self.mfa_attempts.check(&key, &config)?;
+
+        let is_valid =
+            Totp::verify(&mfa.totp_secret, code.trim(), Utc::now()).is_some_and(|step| {
+                Database::lock(|database| database.use_account_mfa_step(account_id, step))
+            });
+
+        if !is_valid {
+            self.mfa_attempts.record_failure(&key, &config);
+            self.log_mfa_failure(account_id, Some(ip));
+
+            return Err(ApiError::Other("Неверный код".to_string()));
+        }
+
+        let (codes, hashes) = new_recovery_codes();
+
+        if !Database::lock(|database| database.enable_account_mfa(account_id, hashes)) {
+            return Err(ApiError::Other("Второй фактор уже подключён".to_string()));
+        }
+
+        Journal::lock(|journal| {
+            journal.log(
+                Actor::User(account_id),
+                Some(Actor::User(account_id)),
+                ActionType::MfaEnabled,
+                None,
+            )
+        });
+
+        self.revoke_account_sessions(account_id, Some(&session.secret_hash));
+
+        let rights = self.get_session_rights(account_id);
+        Database::lock(|database| {
+            database.update_api_token_rights(session.api_secret_hash, rights)
+        });
+
+        Ok(codes)
+    }
+
+    /// Отключает второй фактор, если роли аккаунта его не требуют.
+    #[instrument(skip(code))]
+    pub fn disable_totp(
+        &mut self,
+        account_id: AccountId,
+        code: String,
+        ip: String,
+    ) -> Result<(), ApiError> {
+        trace!("disable_totp");
+
+        if self.is_mfa_required(account_id) {
+            return Err(ApiError::Forbidden(
+                "Одна из ваших ролей требует второй фактор".to_string(),
+            ));
+        }
+
+        let Some(mfa) = self.find_enabled_mfa(account_id) else {
+            return Err(ApiError::Other("Второй фактор не подключён".to_string()));
+        };
+
+        self.verify_mfa_code(mfa, code, Some(ip))?;
+
+        Database::lock(|database| database.delete_account_mfa(account_id));
+
+        Journal::lock(|journal| {
+            journal.log(
+                Actor::User(account_id),
+                Some(Actor::User(account_id)),
+                ActionType::MfaDisabled,
+                None,
+            )
+        });
+
+        Ok(())
+    }
+
+    /// Заменяет коды восстановления новыми, старые перестают действовать.
+    #[instrument(skip(code))]
+    pub fn regenerate_recovery_codes(
+        &mut self,
+        account_id: AccountId,
+        code: String,
+        ip: String,
+    ) -> Result<Vec<String>, ApiError> {
+        trace!("regenerate_recovery_codes");
+
+        let Some(mfa) = self.find_enabled_mfa(account_id) else {
+            return Err(ApiError::Other("Второй фактор не подключён".to_string()));
+        };
+
+        self.verify_mfa_code(mfa, code, Some(ip))?;
+
+        let (codes, hashes) = new_recovery_codes();
+        Database::lock(|database| database.update_account_mfa_recovery_codes(account_id, hashes));
+
+        Ok(codes)
+    }
+
+    /// Проверяет код второго фактора перед важным действием.
+    /// Если второй фактор у аккаунта не подключён, код не нужен.
+    #[instrument(skip(code))]
+    pub fn check_mfa_code(
+        &mut self,
+        account_id: AccountId,
+        code: Option<String>,
+        ip: Option<String>,
+    ) -> Result<(), ApiError> {
+        trace!("check_mfa_code");
+
+        let Some(mfa) = self.find_enabled_mfa(account_id) else {
+            return Ok(());
+        };
+
+        let Some(code) = code.filter(|code| !code.trim().is_empty()) else {
+            return Err(ApiError::Forbidden(
+                "Введите код второго фактора".to_string(),
+            ));
+        };
+
+        self.verify_mfa_code(mfa, code, ip)
+    }
+
+    /// Принимает код TOTP или код восстановления. Каждый код действует один раз,
+    /// частые неверные коды блокируют ввод для аккаунта.
+    #[instrument(skip(self, mfa, code))]
+    pub(crate) fn verify_mfa_code(
+        &mut self,
+        mfa: AccountMfa,
+        code: String,
+        ip: Option<String>,
+    ) -> Result<(), ApiError> {
+        trace!("verify_mfa_code");
+
+        let account_id = mfa.account_id;
+        let config = ApiConfig::get().unwrap().tfa;
+        let key = account_id.0.to_string();
+
+        self.mfa_attempts.check(&key, &config)?;
+
+        let code = normalize_code(&code);
+
+        if let Some(step) = Totp::verify(&mfa.totp_secret, &code, Utc::now()) {
+            if Database::lock(|database| database.use_account_mfa_step(account_id, step)) {
+                return Ok(());
+            }
+        } else if code.len() == RECOVERY_CODE_LENGTH
+            && Database::lock(|database| {
+                database.use_account_mfa_recovery_code(account_id, Secret(code.clone()))
+            })
+        {
+            Journal::lock(|journal| {
+                journal.log(
+                    Actor::User(account_id),
+                    Some(Actor::User(account_id)),
+                    ActionType::MfaRecoveryCodeUsed,
+                    None,
+                )
+            });
+
+            return Ok(());
+        }
+
+        self.mfa_attempts.record_failure(&key, &config);
+        self.log_mfa_failure(account_id, ip);
+
+        Err(ApiError::Other("Неверный код второго фактора".to_string()))
+    }
+
+    #[instrument]
+    fn find_enabled_mfa(&self, account_id: AccountId) -> Option<AccountMfa> {
+        trace!("find_enabled_mfa");
+
+        Database::lock(|database| database.find_account_mfa(account_id))
+            .filter(AccountMfa::is_enabled)
+    }
+
+    #[instrument]
+    fn log_mfa_failure(&self, account_id: AccountId, ip: Option<String>) {
+        trace!("log_mfa_failure");
+
+        Journal::lock(|journal| {
+            journal.log(
+                Actor::System,
+                Some(Actor::User(account_id)),
+                ActionType::MfaAttemptFailed { ip },
+                None,
+            )
+        });
+    }
+}
+
+/// Коды восстановления в виде `XXXXX-XXXXX` и их хэши.
+fn new_recovery_codes() -> (Vec<String>, Vec<SecretHash>) {
+    (0..RECOVERY_CODES_COUNT)
+        .map(|_| {
+            let code = Secret::new_random_tfa_secret(RECOVERY_CODE_LENGTH);
+            let (first, second) = code.0.split_at(RECOVERY_CODE_LENGTH / 2);
+
+            (format!("{first}-{second}"), code.hash())
+        })
+        .unzip()
+}
+
+/// Коды вводят как угодно: с дефисом, пробелами, строчными буквами.
+fn normalize_code(code: &str) -> String {
+    code.chars()
+        .filter(|char| !char.is_whitespace() && *char != '-')
+        .collect::<String>()
+        .to_uppercase()
+}
@@ -1,18 +1,26 @@
 mod account;
 mod bug_feature;
+mod github;
+mod integration;
 mod journal;
+mod mfa;
+mod notes;
 mod roles;
 mod session;
 mod tfa;
 mod token;
 mod webhook;
 
+use crate::avatars::Avatars;
+use crate::discord_oauth::DiscordOAuth;
 use crate::github::Github;
+use crate::mfa_logins::MfaLogins;
 use crate::services::ServicesStorage;
+use crate::tfa_attempts::TFAAttempts;
 use crate::tfa_tokens_storage::TFATokensStorage;
 use app_macros::global;
 use app_shared::{
-    models::{ApiToken, Rights},
+    models::{ApiToken, Rights, Secret, SecretHash, SecretKind},
     prelude::*,
     Database,
 };
@@ -25,8 +33,14 @@ use crate::discord_api::DiscordApi;
 pub struct Api {
     pub github: Github,
     pub tokens_storage: TFATokensStorage,
+    pub tfa_attempts: TFAAttempts,
+    pub mfa_logins: MfaLogins,
+    /// Неудачные попытки ввода кодов второго фактора, по аккаунтам.
+    pub mfa_attempts: TFAAttempts,
     pub services_storage: ServicesStorage,
     pub discord_api: DiscordApi,
+    pub avatars: Avatars,
+    pub discord_oauth: DiscordOAuth,
 }
 
 impl Default for Api {
@@ -37,10 +51,34 @@ impl Default for Api {
         // Tokens storage
         let tokens_storage = TFATokensStorage::default();
         let config = ApiConfig::get().unwrap();
+        let root_secret_hash = config.root_secret.as_ref().map(Secret::hash);
 
-        let root_token = ApiToken::new(config.root_secret, Rights::full(), None, None, true, None);
+        if let Some(root_secret) = config.root_secret {
+            warn!("root_secret is stored in api.yml as plaintext, consider `indigo-bot admin mint`");
 
-        Database::lock(|database| database.create_root_token_if_does_not_exist(root_token));
+            if let Err(err) = root_secret.check_format(SecretKind::ApiToken) {
+                warn!("root_secret from api.yml will be rejected: {err}");
+            }
+
+            let root_token = ApiToken {
+                is_root: true,
+                ..ApiToken::new(
+                    root_secret,
+                    String::from(ROOT_TOKEN_LABEL),
+                    Rights::full(),
+                    None,
+                    None,
+                    true,
+                    None,
+                )
+            };
+
+            Database::lock(|database| database.create_root_token_if_does_not_exist(root_token));
+        }
+
+        Database::lock(|database| database.update_root_tokens_rights(Rights::full()));
+
+        warn_about_stale_root_tokens(root_secret_hash);
 
         // Services storage
         let mut services_storage = ServicesStorage::default();
@@ -49,11 +87,49 @@ impl Default for Api {
         // Discord API
         let discord_api = DiscordApi::default();
 
+        // Avatars
+        let avatars = Avatars::default();
+
+        // Discord OAuth
+        let discord_oauth = DiscordOAuth::default();
+
         Self {
             github,
             tokens_storage,
+            tfa_attempts: TFAAttempts::default(),
+            mfa_logins: MfaLogins::default(),
+            mfa_attempts: TFAAttempts::default(),
             services_storage,
             discord_api,
+            avatars,
+            discord_oauth,
         }
     }
 }
+
+/// Название рутового токена из конфига.
+const ROOT_TOKEN_LABEL: &str = "root";
+
+/// Предупреждает о рутовых токенах из конфига, секрет которых уже сменили или убрали.
+#[instrument]
+fn warn_about_stale_root_tokens(root_secret_hash: Option<SecretHash>) {
+    trace!("warn_about_stale_root_tokens");
+
+    for token in Database::lock(|database| database.get_system_api_tokens()) {
+        // У токенов, созданных до появления названий, оно пустое.
+        let is_from_config = token.label == ROOT_TOKEN_LABEL || token.label.is_empty();
+
+        if !token.is_root || !is_from_config || token.is_expired() {
+            continue;
+        }
+
+        if root_secret_hash.as_ref() == Some(&token.secret_hash) {
+            continue;
+        }
+
+        warn!(
+            "stale root token #{id} is still valid, revoke it with `indigo-bot admin revoke {id}`",
+            id = token.id.0
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use crate::{Api, Journal};
+use app_macros::validate_api_secret;
+use app_shared::{
+    chrono::Utc,
+    models::{
+        AccountId, AccountNote, AccountNoteId, ActionType, Actor, AnyUserId, ApiCaller, ApiError,
+        GameServerId, NoteSeverity, UserRights,
+    },
+    prelude::*,
+    Database,
+};
+
+/// Наибольшая длина заметки в символах.
+const MAX_NOTE_LENGTH: usize = 4000;
+
+impl Api {
+    /// Оставляет заметку администрации о пользователе.
+    #[instrument]
+    pub fn add_account_note(
+        &self,
+        caller: ApiCaller,
+        account_id: AccountId,
+        severity: NoteSeverity,
+        server_id: Option<GameServerId>,
+        text: String,
+        shareable: bool,
+    ) -> Result<AccountNoteId, ApiError> {
+        trace!("add_account_note");
+
+        let reason = caller.reason();
+
+        let account = self.find_account_by_id(AnyUserId::AccountId(account_id))?;
+        let actor = self.check_notes_access(caller, UserRights::EDIT_NOTES, Some(account.id))?;
+
+        let text = text.trim().to_string();
+
+        if text.is_empty() {
+            return Err(ApiError::Other("Заметка не может быть пустой".to_string()));
+        }
+
+        if text.chars().count() > MAX_NOTE_LENGTH {
+            return Err(ApiError::Other(format!(
+                "Заметка не должна быть длинее {MAX_NOTE_LENGTH} символов"
+            )));
+        }
+
+        let server_id = server_id.filter(|server_id| !server_id.0.trim().is_empty());
+        let author = match actor {
+            Actor::User(author) => Some(author),
+            _ => None,
+        };
+
+        let note_id = Database::lock(|database| {
+            database.add_account_note(
+                account.id,
+                author,
+                Utc::now(),
+                severity,
+                server_id,
+                text,
+                shareable,
+            )
+        });
+
+        Journal::lock(|journal| {
+            journal.log(
+                actor,
+                Some(Actor::User(account.id)),
+                ActionType::NoteAdded { note_id },
+                reason,
+            )
+        });
+
+        Ok(note_id)
+    }
+
+    /// Удаляет заметку о пользователе.
+    #[instrument]
+    pub fn delete_account_note(
+        &self,
+        caller: ApiCaller,
+        note_id: AccountNoteId,
+    ) -> Result<(), ApiError> {
+        trace!("delete_account_note");
+
+        let reason = caller.reason();
+
+        let note = Database::lock(|database| database.find_account_note(note_id))
+            .ok_or_else(|| ApiError::Other("Заметка не существует".to_string()))?;
+
+        let actor =
+            self.check_notes_access(caller, UserRights::EDIT_NOTES, Some(note.account_id))?;
+
+        Database::lock(|database| database.delete_account_note(note_id));
+
+        Journal::lock(|journal| {
+            journal.log(
+                actor,
+                Some(Actor::User(note.account_id)),
+                ActionType::NoteDeleted { note_id },
+                reason,
+            )
+        });
+
+        Ok(())
+    }
+
+    /// Возвращает заметки о пользователе, новые первыми.
+    #[instrument]
+    pub fn get_account_notes(
+        &self,
+        caller: ApiCaller,
+        account_id: AccountId,
+    ) -> Result<Vec<AccountNote>, ApiError> {
+        trace!("get_account_notes");
+
+        self.check_notes_access(caller, UserRights::VIEW_NOTES, None)?;
+
+        let account = self.find_account_by_id(AnyUserId::AccountId(account_id))?;
+
+        Ok(Database::lock(|database| {
+            database.get_account_notes(account.id)
+        }))
+    }
+
+    /// Возвращает заметки о пользователе по его ckey, которые относятся к серверу.
+    #[instrument]
+    pub fn get_account_notes_by_ckey(
+        &self,
+        caller: ApiCaller,
+        ckey: ByondUserId,
+        server_id: Option<GameServerId>,
+    ) -> Result<Vec<AccountNote>, ApiError> {
+        trace!("get_account_notes_by_ckey");
+
+        self.check_notes_access(caller, UserRights::VIEW_NOTES, None)?;
+
+        let account = self.find_account_by_id(AnyUserId::ByondCkey(ckey))?;
+
+        let notes = Database::lock(|database| database.get_account_notes(account.id));
+
+        Ok(notes
+            .into_iter()
+            .filter(|note| note.applies_to(server_id.as_ref()))
+            .collect())
+    }
+
+    /// Проверяет право на работу с заметками и возвращает того, кто действует.
+    /// Если указан аккаунт, его права не должны быть выше прав токена.
+    fn check_notes_access(
+        &self,
+        caller: ApiCaller,
+        right: UserRights,
+        account_id: Option<AccountId>,
+    ) -> Result<Actor, ApiError> {
+        let ApiCaller::Token(secret, ip, _) = caller else {
+            return Ok(Actor::System);
+        };
+
+        let token = validate_api_secret!(secret, ip);
+
+        if !token.rights.user.contains(right) {
+            return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
+        }
+
+        if let Some(account_id) = account_id {
+            let account_rights = self.get_account_rights(account_id, None);
+
+            if (!token.is_service && token.rights < account_rights)
+                || (token.is_service && token.rights <= account_rights)
+            {
+                return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
+            }
+        }
+
+        Ok(match token.creator {
+            Some(account_id) => Actor::User(account_id),
+            None => Actor::System,
+        })
+    }
+}
@@ -1,12 +1,19 @@
-use crate::Api;
+use crate::discord_oauth::{DiscordOAuth, DiscordOAuthGrant, DiscordOAuthUser};
+use crate::{Api, Journal};
 use app_shared::{
     chrono::Duration,
     chrono::{DateTime, Utc},
-    models::{AccountId, AnyUserId, ApiError, ApiToken, Secret, Session},
+    models::{
+        AccountId, ActionType, Actor, AnyUserId, ApiCaller, ApiError, ApiToken, Login, Secret,
+        SecretHash, Session, SessionId, SessionSecrets,
+    },
     prelude::*,
     Database,
 };
 
+/// Максимальная длина названия устройства.
+const MAX_SESSION_LABEL_LENGTH: usize = 50;
+
 impl Api {
     #[instrument]
     fn cleanup_sessions(&self) {
@@ -15,14 +22,14 @@ impl Api {
         Database::lock(|database| database.delete_expired_sessions());
     }
 
-    /// Продлевает сессию и создаёт новые секреты.
+    /// Продлевает сессию и создаёт новые секреты. Название устройства сохраняется.
     #[instrument]
     pub fn extend_session(
         &self,
         session_secret: Secret,
         user_agent: String,
         ip: String,
-    ) -> Result<Session, ApiError> {
+    ) -> Result<(Session, SessionSecrets), ApiError> {
         trace!("extend_session");
 
         self.cleanup_sessions();
@@ -33,12 +40,21 @@ impl Api {
 
         self.delete_session(session_secret)?;
 
-        self.create_session_for_account(
+        let (mut new_session, secrets) = self.create_session_for_account(
             AnyUserId::AccountId(session.account_id),
             Some(session.created_at),
             user_agent,
             ip,
-        )
+        )?;
+
+        if session.label.is_some() {
+            new_session.label = session.label;
+            Database::lock(|database| {
+                database.update_session_label(new_session.id, new_session.label.clone())
+            });
+        }
+
+        Ok((new_session, secrets))
     }
 
     /// Удаляет сессию.
@@ -52,44 +68,148 @@ impl Api {
             return Err(ApiError::Other("Некорректная сессия".to_string()))
         };
 
-        Database::lock(|database| database.delete_session_by_secret(session.secret));
-        Database::lock(|database| database.delete_api_token_by_secret(session.api_secret));
+        self.remove_session(session);
 
         Ok(())
     }
 
-    /// Создаёт уникальный секрет для сессии.
+    /// Удаляет все сессии аккаунта.
     #[instrument]
-    pub fn create_unique_session_secret(&self) -> Secret {
-        trace!("create_unique_session_secret");
-
-        self.cleanup_sessions();
+    pub fn delete_account_sessions(&self, account_id: AccountId) {
+        trace!("delete_account_sessions");
 
-        loop {
-            let secret = Secret::new_random_session_secret();
+        self.revoke_account_sessions(account_id, None);
+    }
 
-            if Database::lock(|database| database.find_session_by_secret(secret.clone()).is_none())
-            {
-                break secret;
+    /// Удаляет сессии аккаунта, кроме `except`, и записывает это в журнал.
+    #[instrument]
+    pub(crate) fn revoke_account_sessions(
+        &self,
+        account_id: AccountId,
+        except: Option<&SecretHash>,
+    ) {
+        trace!("revoke_account_sessions");
+
+        for session in Database::lock(|database| database.get_account_sessions(account_id)) {
+            if Some(&session.secret_hash) == except {
+                continue;
             }
+
+            self.revoke_session(account_id, session);
+        }
+    }
+
+    /// Удаляет все сессии аккаунта, кроме текущей.
+    #[instrument]
+    pub fn delete_other_account_sessions(&self, current_session: &Session) {
+        trace!("delete_other_account_sessions");
+
+        self.revoke_account_sessions(
+            current_session.account_id,
+            Some(&current_session.secret_hash),
+        );
+    }
+
+    /// Удаляет одну сессию аккаунта по её ID.
+    #[instrument]
+    pub fn delete_account_session_by_id(
+        &self,
+        account_id: AccountId,
+        session_id: SessionId,
+    ) -> Result<(), ApiError> {
+        trace!("delete_account_session_by_id");
+
+        let session = self.find_account_session_by_id(account_id, session_id)?;
+        self.revoke_session(account_id, session);
+
+        Ok(())
+    }
+
+    /// Задаёт название устройства сессии. Пустое название убирает его.
+    #[instrument]
+    pub fn set_session_label(
+        &self,
+        account_id: AccountId,
+        session_id: SessionId,
+        label: String,
+    ) -> Result<(), ApiError> {
+        trace!("set_session_label");
+
+        let session = self.find_account_session_by_id(account_id, session_id)?;
+        let label = label.trim().to_string();
+
+        if label.chars().count() > MAX_SESSION_LABEL_LENGTH {
+            return Err(ApiError::Other(format!(
+                "Название устройства не должно быть длиннее {MAX_SESSION_LABEL_LENGTH} символов"
+            )));
         }
+
+        let label = (!label.is_empty()).then_some(label);
+        Database::lock(|database| database.update_session_label(session.id, label));
+
+        Ok(())
     }
 
-    /// Создаёт уникальный CSRF секрет.
+    /// Сессия с чужим ID не отличается от несуществующей.
     #[instrument]
-    pub fn create_unique_csrf_secret(&self) -> Secret {
-        trace!("create_unique_csrf_secret");
+    fn find_account_session_by_id(
+        &self,
+        account_id: AccountId,
+        session_id: SessionId,
+    ) -> Result<Session, ApiError> {
+        trace!("find_account_session_by_id");
+
+        self.cleanup_sessions();
+
+        Database::lock(|database| database.find_session_by_id(session_id))
+            .filter(|session| session.account_id == account_id)
+            .ok_or_else(|| ApiError::Other("Сессия не найдена".to_string()))
+    }
+
+    /// Удаляет сессию и записывает это в журнал.
+    #[instrument]
+    fn revoke_session(&self, account_id: AccountId, session: Session) {
+        trace!("revoke_session");
+
+        let action = ActionType::SessionRevoked {
+            ip: session.ip.clone(),
+            user_agent: session.user_agent.clone(),
+        };
+
+        self.remove_session(session);
+
+        Journal::lock(|journal| {
+            journal.log(
+                Actor::User(account_id),
+                Some(Actor::User(account_id)),
+                action,
+                None,
+            )
+        });
+    }
+
+    #[instrument]
+    fn remove_session(&self, session: Session) {
+        trace!("remove_session");
+
+        Database::lock(|database| database.delete_session_by_secret_hash(session.secret_hash));
+        Database::lock(|database| {
+            database.delete_api_token_by_secret_hash(session.api_secret_hash)
+        });
+    }
+
+    /// Создаёт уникальный секрет для сессии.
+    #[instrument]
+    pub fn create_unique_session_secret(&self) -> Secret {
+        trace!("create_unique_session_secret");
 
         self.cleanup_sessions();
 
         loop {
-            let secret = Secret::new_random_csrf_secret();
+            let secret = Secret::new_random_session_secret();
 
-            if Database::lock(|database| {
-                database
-                    .find_session_by_csrf_secret(secret.clone())
-                    .is_none()
-            }) {
+            if Database::lock(|database| database.find_session_by_secret(secret.clone()).is_none())
+            {
                 break secret;
             }
         }
@@ -112,7 +232,7 @@ impl Api {
         custom_creation_date: Option<DateTime<Utc>>,
         user_agent: String,
         ip: String,
-    ) -> Result<Session, ApiError> {
+    ) -> Result<(Session, SessionSecrets), ApiError> {
         trace!("create_session_for_account");
 
         self.cleanup_sessions();
@@ -120,21 +240,22 @@ impl Api {
             return Err(ApiError::Internal("Некорректный user_id".to_string()))
         };
 
+        let secrets = SessionSecrets::new(self.create_unique_session_secret());
+
         let api_token = ApiToken::new(
-            self.create_unique_api_secret(),
-            self.get_account_rights(account.id, None),
-            None,
+            secrets.api_secret.clone(),
+            String::from("Сессия"),
+            self.get_session_rights(account.id),
+            Some(account.id),
             Some(Duration::days(3)),
             false,
             custom_creation_date,
         );
 
-        Database::lock(|database| database.add_api_token(api_token.clone()));
+        Database::lock(|database| database.add_api_token(api_token));
 
         let session = Session::new(
-            self.create_unique_session_secret(),
-            api_token.secret,
-            self.create_unique_csrf_secret(),
+            &secrets,
             account.id,
             custom_creation_date,
             Duration::days(3),
@@ -142,9 +263,9 @@ impl Api {
             ip,
         );
 
-        Database::lock(|database| database.add_session(session.clone()));
+        let session = Database::lock(|database| database.add_session(session));
 
-        Ok(session)
+        Ok((session, secrets))
     }
 
     #[instrument]
@@ -162,24 +283,163 @@ impl Api {
     }
 
     /// Создаёт сессию в обмен на TFA.
+    /// Если у аккаунта подключён второй фактор, вход нужно завершить его кодом.
     #[instrument]
     pub fn create_session_by_tfa(
         &mut self,
         tfa_secret: Secret,
         user_agent: String,
         ip: String,
-    ) -> Result<Session, ApiError> {
+    ) -> Result<Login, ApiError> {
         trace!("create_session_by_tfa");
 
-        let account = self.find_account_by_tfa_token_secret(tfa_secret)?;
+        let account = self.find_account_by_tfa_token_secret(
+            tfa_secret,
+            Actor::System,
+            ip.clone(),
+            Some(ip.clone()),
+        )?;
+
+        self.login(account.id, user_agent, ip)
+    }
+
+    /// Завершает вход кодом второго фактора.
+    #[instrument(skip(code))]
+    pub fn complete_mfa_login(
+        &mut self,
+        login_secret: Secret,
+        code: String,
+        user_agent: String,
+        ip: String,
+    ) -> Result<(Session, SessionSecrets), ApiError> {
+        trace!("complete_mfa_login");
+
+        let Some(account_id) = self.mfa_logins.find(&login_secret) else {
+            return Err(ApiError::Other(
+                "Время на ввод кода истекло, войдите заново".to_string(),
+            ));
+        };
+
+        let mfa = Database::lock(|database| database.find_account_mfa(account_id))
+            .filter(|mfa| mfa.is_enabled());
+
+        if let Some(mfa) = mfa {
+            if let Err(err) = self.verify_mfa_code(mfa, code, Some(ip.clone())) {
+                self.mfa_logins.record_failure(&login_secret);
+                return Err(err);
+            }
+        }
+
+        self.mfa_logins.finish(&login_secret);
+
+        self.create_logged_session(account_id, user_agent, ip)
+    }
+
+    /// Вход через Discord настроен.
+    #[instrument]
+    pub fn is_discord_login_enabled(&self) -> bool {
+        trace!("is_discord_login_enabled");
+
+        DiscordOAuth::is_enabled()
+    }
+
+    /// Начинает вход через Discord. Возвращает адрес, на который нужно перенаправить
+    /// пользователя, и `state`, который браузер должен вернуть вместе с кодом.
+    #[instrument]
+    pub fn start_discord_login(&mut self, ip: String) -> Result<(String, String), ApiError> {
+        trace!("start_discord_login");
+
+        self.discord_oauth.start_flow(ip)
+    }
 
-        let session = self.create_session_for_account(
-            AnyUserId::AccountId(account.id),
+    /// Проверяет `state`, с которым Discord вернул пользователя. Код из редиректа
+    /// обменивается через `DiscordOAuthGrant::fetch_user` без блокировки `Api`.
+    #[instrument]
+    pub fn finish_discord_login(&mut self, state: String) -> Result<DiscordOAuthGrant, ApiError> {
+        trace!("finish_discord_login");
+
+        self.discord_oauth.finish_flow(&state)
+    }
+
+    /// Создаёт сессию для пользователя Discord, прошедшего вход.
+    /// Если аккаунта с этим Discord ещё нет, он создаётся.
+    #[instrument]
+    pub fn create_session_by_discord_user(
+        &mut self,
+        user: DiscordOAuthUser,
+        user_agent: String,
+        ip: String,
+    ) -> Result<Login, ApiError> {
+        trace!("create_session_by_discord_user");
+
+        let discord_user_id = user.discord_user_id()?;
+
+        let account =
+            Database::lock(|database| database.find_account(AnyUserId::DiscordId(discord_user_id)));
+
+        let account_id = match account {
+            Some(account) => account.id,
+            None => self.create_account(
+                ApiCaller::System,
+                user.name(),
+                user.avatar_url(),
+                discord_user_id,
+            )?,
+        };
+
+        self.login(account_id, user_agent, ip)
+    }
+
+    /// Входит в аккаунт, прошедший первый фактор.
+    #[instrument]
+    fn login(
+        &mut self,
+        account_id: AccountId,
+        user_agent: String,
+        ip: String,
+    ) -> Result<Login, ApiError> {
+        trace!("login");
+
+        if self.is_mfa_enabled(account_id) {
+            return Ok(Login::MfaRequired(self.mfa_logins.start(account_id)));
+        }
+
+        let (session, secrets) = self.create_logged_session(account_id, user_agent, ip)?;
+
+        Ok(Login::Completed(Box::new(session), secrets))
+    }
+
+    /// Создаёт сессию и записывает вход в журнал.
+    #[instrument]
+    fn create_logged_session(
+        &self,
+        account_id: AccountId,
+        user_agent: String,
+        ip: String,
+    ) -> Result<(Session, SessionSecrets), ApiError> {
+        trace!("create_logged_session");
+
+        let action = ActionType::SessionCreated {
+            ip: ip.clone(),
+            user_agent: user_agent.clone(),
+        };
+
+        let result = self.create_session_for_account(
+            AnyUserId::AccountId(account_id),
             None,
             user_agent,
             ip,
         )?;
 
-        Ok(session)
+        Journal::lock(|journal| {
+            journal.log(
+                Actor::User(account_id),
+                Some(Actor::User(account_id)),
+                action,
+                None,
+            )
+        });
+
+        Ok(result)
     }
 }
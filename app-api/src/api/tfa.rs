@@ -1,7 +1,8 @@
-use crate::Api;
+use crate::api_config::ApiConfig;
+use crate::{Api, Journal};
 use app_shared::{
     chrono::Duration,
-    models::{AnyUserId, ApiCaller, ApiError, Secret, TFAToken},
+    models::{ActionType, Actor, AnyUserId, ApiCaller, ApiError, Secret, TFAToken},
     prelude::*,
     Database,
 };
@@ -16,33 +17,49 @@ impl Api {
     ) -> Result<TFAToken, ApiError> {
         trace!("get_or_create_tfa_for_account");
 
-        if Database::lock(|database| {
-            database
-                .find_account(AnyUserId::DiscordId(discord_user_id))
-                .is_none()
-        }) {
-            let discord_user = self
-                .discord_api
-                .get_discord_user(discord_user_id)
-                .ok_or_else(|| ApiError::Other("Пользователя не существует".to_string()))?;
-
-            self.create_account(
-                caller,
-                discord_user.name.clone(),
-                discord_user
-                    .avatar_url()
-                    .unwrap_or_else(|| discord_user.default_avatar_url()),
-                discord_user_id,
-            )?;
-        }
+        let account =
+            Database::lock(|database| database.find_account(AnyUserId::DiscordId(discord_user_id)));
+
+        let account_id = match account {
+            Some(account) => account.id,
+            None => {
+                let discord_user = self
+                    .discord_api
+                    .get_discord_user(discord_user_id)
+                    .ok_or_else(|| ApiError::Other("Пользователя не существует".to_string()))?;
+
+                self.create_account(
+                    caller,
+                    discord_user.name.clone(),
+                    discord_user
+                        .avatar_url()
+                        .unwrap_or_else(|| discord_user.default_avatar_url()),
+                    discord_user_id,
+                )?
+            }
+        };
 
         match self.tokens_storage.find_by_discord_user_id(discord_user_id) {
             None => {
                 debug!("existing token not found");
 
-                Ok(self
-                    .tokens_storage
-                    .new_token(discord_user_id, Duration::seconds(60)))
+                let config = ApiConfig::get().unwrap().tfa;
+                let token = self.tokens_storage.new_token(
+                    discord_user_id,
+                    Duration::seconds(config.lifetime_seconds),
+                    config.code_length,
+                );
+
+                Journal::lock(|journal| {
+                    journal.log(
+                        Actor::System,
+                        Some(Actor::User(account_id)),
+                        ActionType::TFATokenIssued,
+                        None,
+                    )
+                });
+
+                Ok(token)
             }
             Some(token) => {
                 debug!("existing token found");
@@ -51,11 +68,19 @@ impl Api {
         }
     }
 
-    /// Возвращает TFA токен по его секрету.
+    /// Число символов в TFA токенах.
+    #[instrument]
+    pub fn get_tfa_code_length(&self) -> usize {
+        trace!("get_tfa_code_length");
+
+        ApiConfig::get().unwrap().tfa.code_length
+    }
+
+    /// Забирает TFA токен по его секрету, каждый токен можно использовать один раз.
     #[instrument]
-    pub fn find_tfa_token_by_secret(&mut self, secret: Secret) -> Option<TFAToken> {
-        trace!("find_tfa_token_by_secret");
+    pub fn take_tfa_token_by_secret(&mut self, secret: Secret) -> Option<TFAToken> {
+        trace!("take_tfa_token_by_secret");
 
-        self.tokens_storage.find_by_secret(secret).cloned()
+        self.tokens_storage.take_by_secret(secret)
     }
 }
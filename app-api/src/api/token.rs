@@ -2,7 +2,10 @@ use crate::{Api, Journal};
 use app_macros::validate_api_secret;
 use app_shared::{
     chrono::Duration,
-    models::{ActionType, Actor, ApiCaller, ApiError, ApiToken, Rights, Secret, TokenRights},
+    models::{
+        AccountId, ActionType, Actor, ApiCaller, ApiError, ApiToken, ApiTokenId, ApiTokenInfo,
+        Rights, Secret, TokenRights,
+    },
     prelude::*,
     Database,
 };
@@ -13,17 +16,20 @@ impl Api {
     pub fn create_api_token(
         &self,
         caller: ApiCaller,
+        label: String,
         rights: Rights,
         duration: Option<Duration>,
         is_service: bool,
     ) -> Result<ApiToken, ApiError> {
         trace!("create_api_token");
 
+        let reason = caller.reason();
+
         let mut actor = Actor::System;
         let mut creator = None;
 
-        if let ApiCaller::Token(secret) = caller {
-            let token = validate_api_secret!(secret);
+        if let ApiCaller::Token(secret, ip, _) = caller {
+            let token = validate_api_secret!(secret, ip);
 
             if (is_service
                 && !token
@@ -47,8 +53,17 @@ impl Api {
             };
         }
 
+        let label = label.trim().to_string();
+
+        if label.chars().count() > 50 {
+            return Err(ApiError::Other(
+                "Название токена не должно быть длиннее 50 символов".to_string(),
+            ));
+        }
+
         let new_token = ApiToken::new(
             self.create_unique_api_secret(),
+            label,
             rights,
             creator,
             duration,
@@ -60,53 +75,104 @@ impl Api {
             return Err(ApiError::Other("Новый токен уже устаревший".to_string()));
         }
 
-        Database::lock(|database| database.add_api_token(new_token.clone()));
+        let new_token = Database::lock(|database| database.add_api_token(new_token));
 
         Journal::lock(|journal| {
-            journal.log(actor, creator.map(Actor::User), ActionType::ApiTokenCreated)
+            journal.log(
+                actor,
+                creator.map(Actor::User),
+                ActionType::ApiTokenIssued {
+                    token_id: new_token.id,
+                    label: new_token.label.clone(),
+                },
+                reason,
+            )
         });
 
         Ok(new_token)
     }
 
-    /// Удаляет API токен.
+    /// Удаляет API токен по его секрету.
     #[instrument]
     pub fn delete_api_token(&self, caller: ApiCaller, target: Secret) -> Result<(), ApiError> {
         trace!("delete_api_token");
 
-        let mut actor = Actor::System;
         let target_token = Database::lock(|database| database.find_api_token_by_secret(target));
 
-        if let ApiCaller::Token(secret) = caller {
-            let token = validate_api_secret!(secret);
+        self.delete_found_api_token(caller, target_token)
+    }
 
-            // Никакого брутфорса токенов без прав!
-            if !token.rights.token.contains(TokenRights::TOKEN_DELETE)
-                && token
-                    .rights
-                    .token
-                    .contains(TokenRights::SERVICE_TOKEN_DELETE)
-            {
-                return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
-            }
+    /// Удаляет API токен по его ID.
+    #[instrument]
+    pub fn delete_api_token_by_id(
+        &self,
+        caller: ApiCaller,
+        target: ApiTokenId,
+    ) -> Result<(), ApiError> {
+        trace!("delete_api_token_by_id");
 
-            let Some(ref target_token) = target_token else {
-            	return Err(ApiError::Other("Целевой токен не существует".to_string()))
-        	};
+        let target_token = Database::lock(|database| database.find_api_token_by_id(target));
 
-            if token.rights < target_token.rights {
-                return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
+        self.delete_found_api_token(caller, target_token)
+    }
+
+    #[instrument]
+    fn delete_found_api_token(
+        &self,
+        caller: ApiCaller,
+        target_token: Option<ApiToken>,
+    ) -> Result<(), ApiError> {
+        trace!("delete_found_api_token");
+
+        let reason = caller.reason();
+
+        let mut actor = Actor::System;
+
+        if let ApiCaller::Token(secret, ip, _) = caller {
+            let token = validate_api_secret!(secret, ip);
+
+            // Созданные владельцем токены можно удалять без каких-либо прав.
+            // Токены сессий под это не попадают, иначе любая сессия могла бы завершать остальные.
+            let is_owner = match (token.creator, target_token.as_ref()) {
+                (Some(account_id), Some(target_token))
+                    if target_token.creator == Some(account_id) =>
+                {
+                    Database::lock(|database| database.get_account_api_tokens(account_id))
+                        .iter()
+                        .any(|account_token| account_token.id == target_token.id)
+                }
+                _ => false,
             };
 
-            if (target_token.is_service
-                && !token
-                    .rights
-                    .token
-                    .contains(TokenRights::SERVICE_TOKEN_DELETE))
-                || (!target_token.is_service
-                    && !token.rights.token.contains(TokenRights::TOKEN_DELETE))
-            {
-                return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
+            if !is_owner {
+                // Никакого брутфорса токенов без прав!
+                if !token.rights.token.contains(TokenRights::TOKEN_DELETE)
+                    && !token
+                        .rights
+                        .token
+                        .contains(TokenRights::SERVICE_TOKEN_DELETE)
+                {
+                    return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
+                }
+
+                let Some(ref target_token) = target_token else {
+                    return Err(ApiError::Other("Целевой токен не существует".to_string()))
+                };
+
+                if token.rights < target_token.rights {
+                    return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
+                };
+
+                if (target_token.is_service
+                    && !token
+                        .rights
+                        .token
+                        .contains(TokenRights::SERVICE_TOKEN_DELETE))
+                    || (!target_token.is_service
+                        && !token.rights.token.contains(TokenRights::TOKEN_DELETE))
+                {
+                    return Err(ApiError::Forbidden("Недостаточно доступа".to_string()));
+                }
             }
 
             actor = if let Some(account_id) = token.creator {
@@ -120,19 +186,52 @@ impl Api {
 			return Err(ApiError::Other("Целевой токен не существует".to_string()))
 		};
 
-        Database::lock(|database| database.delete_api_token_by_secret(target_token.secret));
+        Database::lock(|database| database.delete_api_token_by_id(target_token.id));
 
         Journal::lock(|journal| {
             journal.log(
                 actor,
                 target_token.creator.map(Actor::User),
-                ActionType::ApiTokenDeleted,
+                ActionType::ApiTokenRevoked {
+                    token_id: target_token.id,
+                    label: target_token.label,
+                },
+                reason,
             )
         });
 
         Ok(())
     }
 
+    /// Возвращает токены, созданные владельцем токена вызывающего.
+    #[instrument]
+    pub fn get_caller_api_tokens(&self, caller: ApiCaller) -> Result<Vec<ApiTokenInfo>, ApiError> {
+        trace!("get_caller_api_tokens");
+
+        let ApiCaller::Token(secret, ip, _) = caller else {
+            return Err(ApiError::Other("Система не владеет токенами".to_string()));
+        };
+
+        let token = validate_api_secret!(secret, ip);
+
+        let Some(account_id) = token.creator else {
+            return Err(ApiError::Other("Токен не принадлежит аккаунту".to_string()));
+        };
+
+        Ok(self.get_account_api_tokens(account_id))
+    }
+
+    /// Возвращает токены аккаунта без их секретов.
+    #[instrument]
+    pub fn get_account_api_tokens(&self, account_id: AccountId) -> Vec<ApiTokenInfo> {
+        trace!("get_account_api_tokens");
+
+        Database::lock(|database| database.get_account_api_tokens(account_id))
+            .into_iter()
+            .map(ApiTokenInfo::from)
+            .collect()
+    }
+
     /// Создаёт уникальный секрет для Api токена.
     #[instrument]
     pub fn create_unique_api_secret(&self) -> Secret {
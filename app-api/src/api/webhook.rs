@@ -3,8 +3,8 @@ use app_macros::validate_api_secret;
 use app_shared::prelude::*;
 use app_shared::{
     models::{
-        ActionType, Actor, ApiCaller, ApiError, Secret, ServiceError, ServiceId, Webhook,
-        WebhookConfiguration, WebhookPayload, WebhookResponse,
+        ActionType, Actor, ApiCaller, ApiError, Secret, SecretKind, ServiceError, ServiceId,
+        Webhook, WebhookConfiguration, WebhookPayload, WebhookResponse,
     },
     Database,
 };
@@ -36,10 +36,12 @@ impl Api {
     ) -> Result<Webhook, ApiError> {
         trace!("create_webhook");
 
+        let reason = caller.reason();
+
         let mut actor = Actor::System;
 
-        if let ApiCaller::Token(secret) = caller {
-            let token = validate_api_secret!(secret);
+        if let ApiCaller::Token(secret, ip, _) = caller {
+            let token = validate_api_secret!(secret, ip);
 
             if !token
                 .rights
@@ -87,6 +89,7 @@ impl Api {
                 actor,
                 Some(Actor::Webhook(webhook.name.clone())),
                 ActionType::WebhookCreated,
+                reason,
             )
         });
 
@@ -102,11 +105,17 @@ impl Api {
     ) -> Result<(), ApiError> {
         trace!("delete_webhook");
 
+        let reason = caller.reason();
+
+        if let Err(err) = webhook_secret.check_format(SecretKind::Webhook) {
+            return Err(ApiError::Other(err));
+        }
+
         let mut actor = Actor::System;
         let webhook = Database::lock(|database| database.find_webhook_by_secret(webhook_secret));
 
-        if let ApiCaller::Token(secret) = caller {
-            let token = validate_api_secret!(secret);
+        if let ApiCaller::Token(secret, ip, _) = caller {
+            let token = validate_api_secret!(secret, ip);
 
             // Никакого брутфорса вебхуков без прав!
             if !token.rights.service.can_delete_webhooks_at_all() {
@@ -136,13 +145,14 @@ impl Api {
 			return Err(ApiError::Other("Некорректный вебхук".to_string()))
 		};
 
-        Database::lock(|database| database.delete_webhook_by_secret(webhook.secret));
+        Database::lock(|database| database.delete_webhook_by_secret_hash(webhook.secret_hash));
 
         Journal::lock(|journal| {
             journal.log(
                 actor,
                 Some(Actor::Webhook(webhook.name)),
                 ActionType::WebhookDeleted,
+                reason,
             )
         });
 
@@ -158,6 +168,10 @@ impl Api {
     ) -> Result<WebhookResponse, ServiceError> {
         trace!("handle_webhook");
 
+        if let Err(err) = webhook_secret.check_format(SecretKind::Webhook) {
+            return Err(ServiceError::Any(err));
+        }
+
         let webhook = Database::lock(|database| database.find_webhook_by_secret(webhook_secret));
 
         let Some(webhook) = webhook else {
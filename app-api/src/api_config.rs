@@ -1,8 +1,168 @@
 ﻿use app_macros::config;
 use app_shared::models::Secret;
+use app_shared::regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
 
 #[config]
 #[derive(Debug)]
 pub struct ApiConfig {
-    pub root_secret: Secret,
+    pub root_secret: Option<Secret>,
+    /// Сколько дней нужно подождать между сменами имени пользователя.
+    #[serde(default = "default_username_cooldown_days")]
+    pub username_cooldown_days: i64,
+    /// Сколько дней нужно подождать, прежде чем отвязанную интеграцию можно будет привязать к другому аккаунту.
+    #[serde(default = "default_relink_cooldown_days")]
+    pub relink_cooldown_days: i64,
+    /// Имена, которые нельзя занять, регистр не учитывается.
+    #[serde(default)]
+    pub reserved_usernames: BTreeSet<String>,
+    /// Регулярные выражения, которым не должно соответствовать имя пользователя.
+    #[serde(default)]
+    pub username_deny_list: Vec<UsernamePattern>,
+    /// Хранение загруженных аватарок.
+    #[serde(default)]
+    pub avatars: AvatarsConfig,
+    /// Сроки хранения записей журнала.
+    #[serde(default)]
+    pub journal_retention: JournalRetentionConfig,
+    /// Коды входа от бота и защита от их перебора, те же ограничения действуют
+    /// на коды второго фактора для каждого аккаунта.
+    #[serde(default)]
+    pub tfa: TFAConfig,
+}
+
+/// Выражение для имён пользователей без учёта регистра. Собирается при загрузке конфига,
+/// с некорректным выражением конфиг не загружается.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct UsernamePattern(Regex);
+
+impl UsernamePattern {
+    pub fn is_match(&self, username: &str) -> bool {
+        self.0.is_match(username)
+    }
+}
+
+impl TryFrom<String> for UsernamePattern {
+    type Error = String;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        RegexBuilder::new(&pattern)
+            .case_insensitive(true)
+            .build()
+            .map(Self)
+            .map_err(|err| format!("invalid username deny pattern '{pattern}': {err}"))
+    }
+}
+
+impl From<UsernamePattern> for String {
+    fn from(pattern: UsernamePattern) -> Self {
+        pattern.0.as_str().to_string()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AvatarsConfig {
+    /// Папка, в которую сохраняются аватарки.
+    pub storage_path: PathBuf,
+    /// Путь, по которому веб-сервер отдаёт папку с аватарками.
+    pub public_path: String,
+    /// Стороны квадратных аватарок в пикселях, наибольшая используется в профиле.
+    pub sizes: Vec<u32>,
+    /// Наибольший размер файла аватарки в байтах.
+    pub max_file_size: usize,
+    /// Наибольшая ширина и высота исходного изображения в пикселях.
+    pub max_dimensions: u32,
+    /// Сайты, с которых можно скачать аватарку по ссылке.
+    pub allowed_hosts: BTreeSet<String>,
+}
+
+impl Default for AvatarsConfig {
+    fn default() -> Self {
+        Self {
+            storage_path: PathBuf::from("./public/avatars"),
+            public_path: String::from("/public/avatars"),
+            sizes: vec![64, 256],
+            max_file_size: 4 * 1024 * 1024,
+            max_dimensions: 4096,
+            allowed_hosts: BTreeSet::from([
+                String::from("cdn.discordapp.com"),
+                String::from("media.discordapp.net"),
+                String::from("avatars.githubusercontent.com"),
+                String::from("i.imgur.com"),
+            ]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JournalRetentionConfig {
+    /// Папка, в которую сохраняются архивы устаревших записей.
+    pub archive_path: PathBuf,
+    /// Как часто искать устаревшие записи, в часах.
+    pub interval_hours: u64,
+    /// Сколько дней хранить записи каждого типа действия. Остальные хранятся всегда.
+    pub actions: BTreeMap<String, i64>,
+}
+
+impl Default for JournalRetentionConfig {
+    fn default() -> Self {
+        Self {
+            archive_path: PathBuf::from("./journal_archive"),
+            interval_hours: 24,
+            actions: BTreeMap::from([
+                (String::from("SessionCreated"), 90),
+                (String::from("SessionRevoked"), 90),
+                (String::from("TFATokenIssued"), 90),
+                (String::from("TFAAttemptFailed"), 90),
+                (String::from("MfaAttemptFailed"), 90),
+            ]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TFAConfig {
+    /// Число символов в коде.
+    pub code_length: usize,
+    /// Сколько секунд действует код.
+    pub lifetime_seconds: i64,
+    /// Окно, в котором считаются неудачные попытки, в секундах.
+    pub window_seconds: i64,
+    /// Сколько неудачных попыток за окно можно сделать с одного IP до блокировки.
+    pub max_failures_per_ip: usize,
+    /// Сколько неудачных попыток за окно можно сделать со всех IP вместе,
+    /// после этого IP блокируется после первого же неверного кода.
+    pub max_failures_global: usize,
+    /// Первая блокировка IP в секундах, каждая следующая вдвое дольше.
+    pub lockout_base_seconds: i64,
+    /// Самая долгая блокировка IP в секундах.
+    pub lockout_max_seconds: i64,
+}
+
+impl Default for TFAConfig {
+    fn default() -> Self {
+        Self {
+            code_length: 5,
+            lifetime_seconds: 60,
+            window_seconds: 300,
+            max_failures_per_ip: 5,
+            max_failures_global: 100,
+            lockout_base_seconds: 60,
+            lockout_max_seconds: 3600,
+        }
+    }
+}
+
+fn default_username_cooldown_days() -> i64 {
+    30
+}
+
+fn default_relink_cooldown_days() -> i64 {
+    7
 }
@@ -0,0 +1,295 @@
+use image::imageops::FilterType;
+use image::io::{Limits, Reader};
+use image::ImageFormat;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Cursor;
+use std::time::Duration;
+
+use crate::api_config::{ApiConfig, AvatarsConfig};
+use app_shared::models::{AccountId, ApiError};
+use app_shared::prelude::*;
+use app_shared::tokio;
+
+/// Форматы, которые можно загрузить как аватарку.
+const ALLOWED_FORMATS: [ImageFormat; 4] = [
+    ImageFormat::Png,
+    ImageFormat::Jpeg,
+    ImageFormat::Gif,
+    ImageFormat::WebP,
+];
+
+/// Сколько ждать подключения к сайту с аватаркой.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Сколько может длиться скачивание аватарки целиком.
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Хранилище аватарок пользователей на диске.
+#[derive(Debug)]
+pub struct Avatars {
+    http: reqwest::Client,
+}
+
+/// Новая аватарка, которую ещё нужно скачать и обработать.
+/// Это делается через `PendingAvatar::store` без блокировки `Api`.
+#[derive(Debug)]
+pub struct PendingAvatar {
+    account_id: AccountId,
+    source: AvatarSource,
+}
+
+#[derive(Debug)]
+enum AvatarSource {
+    Url(reqwest::Client, reqwest::Url),
+    Image(Vec<u8>),
+}
+
+impl Default for Avatars {
+    fn default() -> Self {
+        // Разрешённый сайт проверяется только у исходной ссылки, поэтому перенаправления не выполняются
+        let http = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(DOWNLOAD_TIMEOUT)
+            .build()
+            .unwrap();
+
+        Self { http }
+    }
+}
+
+impl Avatars {
+    /// Готовит скачивание аватарки с разрешённого сайта.
+    #[instrument(skip(self))]
+    pub fn fetch(&self, account_id: AccountId, url: &str) -> Result<PendingAvatar, ApiError> {
+        trace!("fetch");
+
+        let config = ApiConfig::get().unwrap().avatars;
+        let url = reqwest::Url::parse(url.trim())
+            .map_err(|_| ApiError::Other("Некорректная ссылка".to_string()))?;
+
+        let is_allowed = url.scheme() == "https"
+            && url
+                .host_str()
+                .is_some_and(|host| config.allowed_hosts.contains(host));
+
+        if !is_allowed {
+            return Err(ApiError::Other(
+                "Аватарку можно указать только ссылкой на разрешённый сайт".to_string(),
+            ));
+        }
+
+        Ok(self.fetch_trusted(account_id, url))
+    }
+
+    /// Готовит скачивание аватарки без проверки сайта, например из Discord.
+    #[instrument(skip(self))]
+    pub fn fetch_trusted(&self, account_id: AccountId, url: reqwest::Url) -> PendingAvatar {
+        trace!("fetch_trusted");
+
+        PendingAvatar {
+            account_id,
+            source: AvatarSource::Url(self.http.clone(), url),
+        }
+    }
+
+    /// Готовит сохранение загруженного изображения.
+    #[instrument(skip(image))]
+    pub fn upload(account_id: AccountId, image: Vec<u8>) -> PendingAvatar {
+        trace!("upload");
+
+        PendingAvatar {
+            account_id,
+            source: AvatarSource::Image(image),
+        }
+    }
+
+    /// Скачивает аватарку, если она не больше `max_file_size`.
+    async fn download(http: &reqwest::Client, url: reqwest::Url) -> Result<Vec<u8>, ApiError> {
+        let max_file_size = ApiConfig::get().unwrap().avatars.max_file_size;
+
+        let bytes = async {
+            let mut response = http.get(url).send().await?.error_for_status()?;
+
+            if response.status().is_redirection() {
+                return Ok(Err(ApiError::Other(
+                    "Ссылка на аватарку ведёт на другой адрес, укажите прямую ссылку".to_string(),
+                )));
+            }
+
+            if response
+                .content_length()
+                .is_some_and(|length| length as usize > max_file_size)
+            {
+                return Ok(Err(file_too_large()));
+            }
+
+            let mut bytes = Vec::new();
+            while let Some(chunk) = response.chunk().await? {
+                bytes.extend_from_slice(&chunk);
+
+                if bytes.len() > max_file_size {
+                    return Ok(Err(file_too_large()));
+                }
+            }
+
+            Ok::<_, reqwest::Error>(Ok(bytes))
+        }
+        .await;
+
+        match bytes {
+            Ok(result) => result,
+            Err(err) => {
+                warn!("failed to download avatar: {err}");
+                Err(ApiError::Other("Не удалось скачать аватарку".to_string()))
+            }
+        }
+    }
+
+    /// Проверяет изображение и сохраняет его во всех размерах, возвращает ссылку на наибольший.
+    #[instrument(skip(bytes))]
+    pub fn store(account_id: AccountId, bytes: &[u8]) -> Result<String, ApiError> {
+        trace!("store");
+
+        let config = ApiConfig::get().unwrap().avatars;
+
+        if bytes.len() > config.max_file_size {
+            return Err(file_too_large());
+        }
+
+        let unsupported_format = || {
+            ApiError::Other("Аватарка должна быть в формате PNG, JPEG, GIF или WebP".to_string())
+        };
+
+        let format = image::guess_format(bytes).map_err(|_| unsupported_format())?;
+        if !ALLOWED_FORMATS.contains(&format) {
+            return Err(unsupported_format());
+        }
+
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(config.max_dimensions);
+        limits.max_image_height = Some(config.max_dimensions);
+
+        let mut reader = Reader::with_format(Cursor::new(bytes), format);
+        reader.limits(limits);
+
+        let image = reader.decode().map_err(|err| {
+            debug!("invalid avatar image: {err}");
+            ApiError::Other("Не удалось прочитать изображение".to_string())
+        })?;
+
+        let version = hex::encode(&Sha256::digest(bytes)[..8]);
+        let sizes = sizes(&config);
+
+        let saved = fs::create_dir_all(&config.storage_path).and_then(|_| {
+            for &size in &sizes {
+                image
+                    .resize_to_fill(size, size, FilterType::Lanczos3)
+                    .save_with_format(
+                        config
+                            .storage_path
+                            .join(file_name(account_id, &version, size)),
+                        ImageFormat::Png,
+                    )
+                    .map_err(std::io::Error::other)?;
+            }
+
+            Ok(())
+        });
+
+        if let Err(err) = saved {
+            error!("failed to save avatar: {err}");
+            return Err(ApiError::Internal(
+                "Не удалось сохранить аватарку".to_string(),
+            ));
+        }
+
+        Self::remove(account_id, Some(&version));
+
+        let main_size = sizes.iter().max().copied().unwrap();
+
+        Ok(format!(
+            "{}/{}",
+            config.public_path.trim_end_matches('/'),
+            file_name(account_id, &version, main_size)
+        ))
+    }
+
+    /// Удаляет сохранённые аватарки аккаунта, кроме версии `keep_version`.
+    #[instrument]
+    pub fn remove(account_id: AccountId, keep_version: Option<&str>) {
+        trace!("remove");
+
+        let config = ApiConfig::get().unwrap().avatars;
+        let Ok(entries) = fs::read_dir(&config.storage_path) else {
+            return;
+        };
+
+        let prefix = format!("{}-", account_id.0);
+
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let Some(rest) = name.strip_prefix(&prefix) else {
+                continue;
+            };
+
+            if keep_version.is_some_and(|version| rest.starts_with(&format!("{version}-"))) {
+                continue;
+            }
+
+            if let Err(err) = fs::remove_file(entry.path()) {
+                warn!("failed to remove avatar {name}: {err}");
+            }
+        }
+    }
+}
+
+impl PendingAvatar {
+    /// Скачивает и сохраняет аватарку во всех размерах, возвращает ссылку на наибольший.
+    /// Обработка изображения выполняется в отдельном потоке.
+    #[instrument]
+    pub async fn store(self) -> Result<String, ApiError> {
+        trace!("store");
+
+        let account_id = self.account_id;
+        let bytes = match self.source {
+            AvatarSource::Url(http, url) => Avatars::download(&http, url).await?,
+            AvatarSource::Image(bytes) => bytes,
+        };
+
+        tokio::task::spawn_blocking(move || Avatars::store(account_id, &bytes))
+            .await
+            .unwrap()
+    }
+}
+
+/// Стороны сохраняемых аватарок, без повторов.
+fn sizes(config: &AvatarsConfig) -> Vec<u32> {
+    let sizes = config
+        .sizes
+        .iter()
+        .copied()
+        .filter(|size| *size > 0)
+        .unique()
+        .collect_vec();
+
+    if sizes.is_empty() {
+        vec![AvatarsConfig::default().sizes.into_iter().max().unwrap()]
+    } else {
+        sizes
+    }
+}
+
+fn file_name(account_id: AccountId, version: &str, size: u32) -> String {
+    format!("{}-{version}-{size}.png", account_id.0)
+}
+
+fn file_too_large() -> ApiError {
+    let max_file_size = ApiConfig::get().unwrap().avatars.max_file_size;
+
+    ApiError::Other(format!(
+        "Аватарка не должна быть больше {} КБ",
+        max_file_size / 1024
+    ))
+}
@@ -12,6 +12,19 @@ impl DiscordApi {
         self.rt
             .block_on(async move { self.http.get_user(user_id.0).await.ok() })
     }
+
+    /// Ссылка на текущую аватарку пользователя в PNG.
+    pub fn get_discord_avatar_url(&self, user_id: DiscordUserId) -> Option<String> {
+        let user = self.get_discord_user(user_id)?;
+
+        Some(match &user.avatar {
+            Some(hash) => format!(
+                "https://cdn.discordapp.com/avatars/{}/{hash}.png?size=512",
+                user.id.0
+            ),
+            None => user.default_avatar_url(),
+        })
+    }
 }
 
 impl Default for DiscordApi {
@@ -0,0 +1,469 @@
+use app_macros::config;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+use app_shared::chrono::{DateTime, Duration, Utc};
+use app_shared::models::ApiError;
+use app_shared::prelude::*;
+
+const AUTHORIZE_URL: &str = "https://discord.com/oauth2/authorize";
+const TOKEN_URL: &str = "https://discord.com/api/v10/oauth2/token";
+const USER_URL: &str = "https://discord.com/api/v10/users/@me";
+const AVATARS_URL: &str = "https://cdn.discordapp.com";
+
+/// Сколько даётся на подтверждение входа в Discord.
+const FLOW_LIFETIME_MINUTES: i64 = 10;
+
+/// Ограничение на число незавершённых входов, чтобы их нельзя было накопить без конца.
+const MAX_PENDING_FLOWS: usize = 10_000;
+
+/// Ограничение на число незавершённых входов с одного IP.
+const MAX_PENDING_FLOWS_PER_IP: usize = 10;
+
+const STATE_LENGTH: usize = 32;
+const CODE_VERIFIER_LENGTH: usize = 64;
+
+/// Вход через Discord OAuth2 (authorization code с PKCE).
+#[derive(Debug)]
+pub struct DiscordOAuth {
+    http: reqwest::Client,
+    /// Незавершённые входы по их `state`.
+    flows: BTreeMap<String, PendingFlow>,
+}
+
+#[derive(Debug, Clone)]
+struct PendingFlow {
+    code_verifier: String,
+    ip: String,
+    expires_at: DateTime<Utc>,
+}
+
+/// Подтверждённый `state` входа. Код обменивается на пользователя уже без блокировки `Api`.
+#[derive(Debug, Clone)]
+pub struct DiscordOAuthGrant {
+    http: reqwest::Client,
+    config: DiscordOAuthConfig,
+    code_verifier: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct AccessTokenResponse {
+    access_token: String,
+}
+
+/// Пользователь Discord, как его возвращает `/users/@me`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DiscordOAuthUser {
+    pub id: String,
+    pub username: String,
+    #[serde(default)]
+    pub global_name: Option<String>,
+    #[serde(default)]
+    pub avatar: Option<String>,
+}
+
+/// Без этого конфига вход через Discord выключен.
+#[config]
+#[derive(Debug)]
+pub struct DiscordOAuthConfig {
+    /// Client ID приложения Discord.
+    pub client_id: String,
+    /// Client secret приложения Discord.
+    pub client_secret: String,
+    /// Адрес `/auth/discord/callback` хаба, указанный в Redirects приложения.
+    pub redirect_uri: String,
+    /// Адреса OAuth2 Discord, их можно заменить на адреса тестового сервера.
+    #[serde(default = "default_authorize_url")]
+    pub authorize_url: String,
+    #[serde(default = "default_token_url")]
+    pub token_url: String,
+    #[serde(default = "default_user_url")]
+    pub user_url: String,
+}
+
+impl DiscordOAuthUser {
+    pub fn discord_user_id(&self) -> Result<DiscordUserId, ApiError> {
+        self.id.parse().map(DiscordUserId).map_err(|_| {
+            error!("discord returned invalid user id: {}", self.id);
+            ApiError::Internal("Discord вернул некорректного пользователя".to_string())
+        })
+    }
+
+    /// Отображаемое имя, если оно задано, иначе имя пользователя.
+    pub fn name(&self) -> String {
+        self.global_name
+            .clone()
+            .unwrap_or_else(|| self.username.clone())
+    }
+
+    pub fn avatar_url(&self) -> String {
+        match &self.avatar {
+            Some(avatar) => format!("{AVATARS_URL}/avatars/{}/{avatar}.png", self.id),
+            None => {
+                let index = self.id.parse::<u64>().unwrap_or_default() >> 22;
+                format!("{AVATARS_URL}/embed/avatars/{}.png", index % 6)
+            }
+        }
+    }
+}
+
+impl DiscordOAuth {
+    /// Вход через Discord настроен.
+    pub fn is_enabled() -> bool {
+        DiscordOAuthConfig::get().is_some()
+    }
+
+    /// Начинает вход: возвращает адрес страницы подтверждения в Discord и `state`,
+    /// который нужно сохранить в браузере и сверить при возврате.
+    #[instrument(skip(self))]
+    pub fn start_flow(&mut self, ip: String) -> Result<(String, String), ApiError> {
+        trace!("start_flow");
+
+        self.start_flow_with(&Self::config()?, ip)
+    }
+
+    fn start_flow_with(
+        &mut self,
+        config: &DiscordOAuthConfig,
+        ip: String,
+    ) -> Result<(String, String), ApiError> {
+        let now = Utc::now();
+        self.flows.retain(|_, flow| flow.expires_at > now);
+
+        let flows_from_ip = self.flows.values().filter(|flow| flow.ip == ip).count();
+
+        if self.flows.len() >= MAX_PENDING_FLOWS || flows_from_ip >= MAX_PENDING_FLOWS_PER_IP {
+            return Err(ApiError::Other(
+                "Слишком много попыток входа, попробуйте позже".to_string(),
+            ));
+        }
+
+        let state = random_string(STATE_LENGTH);
+        let code_verifier = random_string(CODE_VERIFIER_LENGTH);
+        let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+
+        let url = reqwest::Url::parse_with_params(
+            &config.authorize_url,
+            [
+                ("response_type", "code"),
+                ("client_id", config.client_id.as_str()),
+                ("scope", "identify"),
+                ("redirect_uri", config.redirect_uri.as_str()),
+                ("state", state.as_str()),
+                ("code_challenge", code_challenge.as_str()),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(|err| {
+            error!("invalid discord authorize_url: {err}");
+            ApiError::Internal("Вход через Discord настроен неправильно".to_string())
+        })?;
+
+        self.flows.insert(
+            state.clone(),
+            PendingFlow {
+                code_verifier,
+                ip,
+                expires_at: now + Duration::minutes(FLOW_LIFETIME_MINUTES),
+            },
+        );
+
+        Ok((url.to_string(), state))
+    }
+
+    /// Завершает вход с этим `state`, код из редиректа затем обменивается через
+    /// `DiscordOAuthGrant::fetch_user`. Каждый `state` можно использовать только один раз.
+    #[instrument(skip(self))]
+    pub fn finish_flow(&mut self, state: &str) -> Result<DiscordOAuthGrant, ApiError> {
+        trace!("finish_flow");
+
+        self.finish_flow_with(Self::config()?, state)
+    }
+
+    fn finish_flow_with(
+        &mut self,
+        config: DiscordOAuthConfig,
+        state: &str,
+    ) -> Result<DiscordOAuthGrant, ApiError> {
+        let Some(flow) = self.flows.remove(state) else {
+            return Err(ApiError::Other(
+                "Вход через Discord не начат или уже завершён".to_string(),
+            ));
+        };
+
+        if flow.expires_at <= Utc::now() {
+            return Err(ApiError::Other(
+                "Время на вход истекло, попробуйте ещё раз".to_string(),
+            ));
+        }
+
+        Ok(DiscordOAuthGrant {
+            http: self.http.clone(),
+            config,
+            code_verifier: flow.code_verifier,
+        })
+    }
+
+    fn config() -> Result<DiscordOAuthConfig, ApiError> {
+        DiscordOAuthConfig::get()
+            .ok_or_else(|| ApiError::Other("Вход через Discord не настроен".to_string()))
+    }
+}
+
+impl DiscordOAuthGrant {
+    /// Обменивает код из редиректа Discord на пользователя.
+    #[instrument(skip(self, code))]
+    pub async fn fetch_user(self, code: String) -> Result<DiscordOAuthUser, ApiError> {
+        trace!("fetch_user");
+
+        let config = &self.config;
+
+        let response = self
+            .http
+            .post(&config.token_url)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .form(&[
+                ("client_id", config.client_id.as_str()),
+                ("client_secret", config.client_secret.as_str()),
+                ("grant_type", "authorization_code"),
+                ("code", code.as_str()),
+                ("redirect_uri", config.redirect_uri.as_str()),
+                ("code_verifier", self.code_verifier.as_str()),
+            ])
+            .send()
+            .await
+            .map_err(Self::unavailable)?;
+
+        if response.status().is_client_error() {
+            warn!("discord rejected authorization code: {}", response.status());
+            return Err(ApiError::Other(
+                "Discord отклонил вход, попробуйте ещё раз".to_string(),
+            ));
+        }
+
+        let response: AccessTokenResponse = async { response.error_for_status()?.json().await }
+            .await
+            .map_err(Self::unavailable)?;
+
+        async {
+            self.http
+                .get(&config.user_url)
+                .bearer_auth(response.access_token)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+        }
+        .await
+        .map_err(Self::unavailable)
+    }
+
+    fn unavailable(err: reqwest::Error) -> ApiError {
+        error!("discord oauth request failed: {err}");
+        ApiError::Internal("Discord недоступен, попробуйте позже".to_string())
+    }
+}
+
+impl Default for DiscordOAuth {
+    fn default() -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            flows: BTreeMap::new(),
+        }
+    }
+}
+
+/// Случайная строка из символов, допустимых и в `state`, и в `code_verifier` PKCE.
+fn random_string(length: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+fn default_authorize_url() -> String {
+    AUTHORIZE_URL.to_string()
+}
+
+fn default_token_url() -> String {
+    TOKEN_URL.to_string()
+}
+
+fn default_user_url() -> String {
+    USER_URL.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use app_shared::serde_json::{self, json};
+    use app_shared::tokio::{
+        self,
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    const CODE: &str = "test-code";
+    const ACCESS_TOKEN: &str = "test-access-token";
+
+    /// Поднимает на случайном порту заглушку адресов токена и пользователя Discord.
+    /// Токен выдаётся только в обмен на `CODE` и `code_verifier`, подходящий к `code_challenge`.
+    async fn start_mock(code_challenge: String) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(handle_mock_request(stream, code_challenge.clone()));
+            }
+        });
+
+        address
+    }
+
+    async fn handle_mock_request(mut stream: TcpStream, code_challenge: String) {
+        let mut request = Vec::new();
+        let mut buffer = [0; 4096];
+
+        let (head, body) = loop {
+            let read = stream.read(&mut buffer).await.unwrap();
+            request.extend_from_slice(&buffer[..read]);
+
+            let text = String::from_utf8_lossy(&request).to_string();
+            let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                continue;
+            };
+
+            let content_length = head
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                .map(|(_, value)| value.trim().parse::<usize>().unwrap())
+                .unwrap_or_default();
+
+            if body.len() >= content_length {
+                break (head.to_string(), body.to_string());
+            }
+        };
+
+        let (status, response) = if head.starts_with("POST /token ") {
+            let form: BTreeMap<String, String> = reqwest::Url::parse(&format!("http://x/?{body}"))
+                .unwrap()
+                .query_pairs()
+                .into_owned()
+                .collect();
+
+            let verifier = form.get("code_verifier").cloned().unwrap_or_default();
+            let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+
+            if form.get("code").map(String::as_str) == Some(CODE) && challenge == code_challenge {
+                ("200 OK", json!({ "access_token": ACCESS_TOKEN }))
+            } else {
+                ("400 Bad Request", json!({ "error": "invalid_grant" }))
+            }
+        } else if head.starts_with("GET /users/@me ")
+            && head.contains(&format!("Bearer {ACCESS_TOKEN}"))
+        {
+            let user = json!({
+                "id": "123456789012345678",
+                "username": "tester",
+                "global_name": "Tester",
+                "avatar": null,
+            });
+
+            ("200 OK", user)
+        } else {
+            ("401 Unauthorized", json!({}))
+        };
+
+        let response = response.to_string();
+        let response = format!(
+            "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}",
+            response.len()
+        );
+
+        stream.write_all(response.as_bytes()).await.unwrap();
+    }
+
+    fn config(address: &str) -> DiscordOAuthConfig {
+        serde_json::from_value(json!({
+            "type": "DiscordOAuthConfig",
+            "client_id": "client",
+            "client_secret": "secret",
+            "redirect_uri": "http://hub/auth/discord/callback",
+            "token_url": format!("{address}/token"),
+            "user_url": format!("{address}/users/@me"),
+        }))
+        .unwrap()
+    }
+
+    fn code_challenge(url: &str) -> String {
+        reqwest::Url::parse(url)
+            .unwrap()
+            .query_pairs()
+            .find(|(name, _)| name == "code_challenge")
+            .map(|(_, value)| value.to_string())
+            .unwrap()
+    }
+
+    #[tokio::test(crate = "tokio")]
+    async fn flow_returns_user() {
+        let mut oauth = DiscordOAuth::default();
+        let (url, state) = oauth
+            .start_flow_with(&config(""), "127.0.0.1".to_string())
+            .unwrap();
+
+        let address = start_mock(code_challenge(&url)).await;
+        let grant = oauth.finish_flow_with(config(&address), &state).unwrap();
+        let user = grant.fetch_user(CODE.to_string()).await.unwrap();
+
+        assert_eq!(user.discord_user_id().unwrap().0, 123456789012345678);
+        assert_eq!(user.name(), "Tester");
+
+        // state одноразовый
+        assert!(oauth.finish_flow_with(config(&address), &state).is_err());
+    }
+
+    #[tokio::test(crate = "tokio")]
+    async fn flow_rejects_wrong_code() {
+        let mut oauth = DiscordOAuth::default();
+        let (url, state) = oauth
+            .start_flow_with(&config(""), "127.0.0.1".to_string())
+            .unwrap();
+
+        let address = start_mock(code_challenge(&url)).await;
+        let grant = oauth.finish_flow_with(config(&address), &state).unwrap();
+
+        assert!(matches!(
+            grant.fetch_user("wrong-code".to_string()).await,
+            Err(ApiError::Other(_))
+        ));
+    }
+
+    #[test]
+    fn pending_flows_are_limited_per_ip() {
+        let mut oauth = DiscordOAuth::default();
+        let config = config("");
+
+        for _ in 0..MAX_PENDING_FLOWS_PER_IP {
+            assert!(oauth
+                .start_flow_with(&config, "10.0.0.1".to_string())
+                .is_ok());
+        }
+
+        assert!(oauth
+            .start_flow_with(&config, "10.0.0.1".to_string())
+            .is_err());
+        assert!(oauth
+            .start_flow_with(&config, "10.0.0.2".to_string())
+            .is_ok());
+    }
+}
@@ -1,15 +1,54 @@
 use app_macros::config;
-use std::collections::BTreeSet;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use std::collections::{BTreeMap, BTreeSet};
 
+use app_shared::chrono::{Duration, Utc};
+use app_shared::models::{AccountId, ApiError, GithubLinkCode, GithubUserId, RoleId};
 use app_shared::tokio::runtime::Runtime;
 use app_shared::{octocrab::Octocrab, prelude::*};
 
+const DEVICE_CODE_URL: &str = "https://github.com/login/device/code";
+const ACCESS_TOKEN_URL: &str = "https://github.com/login/oauth/access_token";
+const USER_URL: &str = "https://api.github.com/user";
+
 #[derive(Debug)]
 pub struct Github {
     client: Octocrab,
+    http: reqwest::Client,
+    /// Незавершённые привязки аккаунтов через device flow.
+    device_flows: BTreeMap<AccountId, DeviceFlow>,
     rt: Runtime,
 }
 
+#[derive(Debug, Clone)]
+struct DeviceFlow {
+    device_code: String,
+    code: GithubLinkCode,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct DeviceCodeResponse {
+    device_code: String,
+    user_code: String,
+    verification_uri: String,
+    expires_in: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct AccessTokenResponse {
+    access_token: Option<String>,
+    error: Option<String>,
+}
+
+/// Пользователь GitHub, как его возвращает API и вебхуки.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GithubUser {
+    pub id: GithubUserId,
+    pub login: String,
+}
+
 #[config]
 #[derive(Debug)]
 pub struct GithubConfig {
@@ -18,6 +57,15 @@ pub struct GithubConfig {
     pub bugs_repository: String,
     pub bug_issue_labels: BTreeSet<String>,
     pub feature_issue_labels: BTreeSet<String>,
+    /// Client ID OAuth приложения, через которое привязываются аккаунты.
+    #[serde(default)]
+    pub oauth_client_id: Option<String>,
+    /// Секрет, которым GitHub подписывает вебхуки.
+    #[serde(default)]
+    pub webhook_secret: Option<String>,
+    /// Роль, которая выдаётся авторам принятых PR.
+    #[serde(default)]
+    pub coder_role_id: Option<RoleId>,
 }
 
 impl Github {
@@ -72,6 +120,168 @@ impl Github {
     }
 }
 
+impl Github {
+    /// Запрашивает код для привязки GitHub аккаунта через device flow.
+    #[instrument(skip(self))]
+    pub fn start_device_flow(&mut self, account_id: AccountId) -> Result<GithubLinkCode, ApiError> {
+        trace!("start_device_flow");
+
+        let client_id = Self::oauth_client_id()?;
+
+        let response: DeviceCodeResponse = self
+            .rt
+            .block_on(async {
+                self.http
+                    .post(DEVICE_CODE_URL)
+                    .header(reqwest::header::ACCEPT, "application/json")
+                    .form(&[("client_id", client_id.as_str()), ("scope", "")])
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await
+            })
+            .map_err(Self::unavailable)?;
+
+        let code = GithubLinkCode {
+            user_code: response.user_code,
+            verification_uri: response.verification_uri,
+            expires_at: Utc::now() + Duration::seconds(response.expires_in),
+        };
+
+        self.device_flows.insert(
+            account_id,
+            DeviceFlow {
+                device_code: response.device_code,
+                code: code.clone(),
+            },
+        );
+
+        Ok(code)
+    }
+
+    /// Код незавершённой привязки, если он ещё не истёк.
+    #[instrument(skip(self))]
+    pub fn find_device_flow(&self, account_id: AccountId) -> Option<GithubLinkCode> {
+        trace!("find_device_flow");
+
+        self.device_flows
+            .get(&account_id)
+            .map(|flow| flow.code.clone())
+            .filter(|code| code.expires_at > Utc::now())
+    }
+
+    /// Проверяет, ввёл ли пользователь код на GitHub.
+    /// Возвращает `None`, пока код не подтверждён.
+    #[instrument(skip(self))]
+    pub fn poll_device_flow(
+        &mut self,
+        account_id: AccountId,
+    ) -> Result<Option<GithubUser>, ApiError> {
+        trace!("poll_device_flow");
+
+        let client_id = Self::oauth_client_id()?;
+
+        let Some(flow) = self.device_flows.get(&account_id).cloned() else {
+            return Err(ApiError::Other("Привязка GitHub не начата".to_string()));
+        };
+
+        if flow.code.expires_at <= Utc::now() {
+            self.device_flows.remove(&account_id);
+            return Err(ApiError::Other(
+                "Код истёк, начните привязку заново".to_string(),
+            ));
+        }
+
+        let response: AccessTokenResponse = self
+            .rt
+            .block_on(async {
+                self.http
+                    .post(ACCESS_TOKEN_URL)
+                    .header(reqwest::header::ACCEPT, "application/json")
+                    .form(&[
+                        ("client_id", client_id.as_str()),
+                        ("device_code", flow.device_code.as_str()),
+                        ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+                    ])
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await
+            })
+            .map_err(Self::unavailable)?;
+
+        let access_token = match (response.access_token, response.error) {
+            (Some(access_token), _) => access_token,
+            (None, Some(error)) if error == "authorization_pending" || error == "slow_down" => {
+                return Ok(None)
+            }
+            (None, error) => {
+                self.device_flows.remove(&account_id);
+                warn!("github device flow failed: {error:?}");
+                return Err(ApiError::Other(
+                    "GitHub отклонил привязку, начните её заново".to_string(),
+                ));
+            }
+        };
+
+        let user: GithubUser = self
+            .rt
+            .block_on(async {
+                self.http
+                    .get(USER_URL)
+                    .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+                    .header(reqwest::header::USER_AGENT, "indigo-bot")
+                    .bearer_auth(access_token)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await
+            })
+            .map_err(Self::unavailable)?;
+
+        self.device_flows.remove(&account_id);
+
+        Ok(Some(user))
+    }
+
+    /// Проверяет подпись вебхука из заголовка `X-Hub-Signature-256`.
+    #[instrument(skip(self, body))]
+    pub fn is_webhook_signature_valid(&self, body: &[u8], signature: &str) -> bool {
+        trace!("is_webhook_signature_valid");
+
+        let Some(secret) = GithubConfig::get().unwrap().webhook_secret else {
+            return false;
+        };
+
+        let Some(signature) = signature
+            .strip_prefix("sha256=")
+            .and_then(|signature| hex::decode(signature).ok())
+        else {
+            return false;
+        };
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(body);
+
+        mac.verify_slice(&signature).is_ok()
+    }
+
+    fn oauth_client_id() -> Result<String, ApiError> {
+        GithubConfig::get()
+            .unwrap()
+            .oauth_client_id
+            .ok_or_else(|| ApiError::Other("Привязка GitHub не настроена".to_string()))
+    }
+
+    fn unavailable(err: reqwest::Error) -> ApiError {
+        error!("github request failed: {err}");
+        ApiError::Internal("GitHub недоступен, попробуйте позже".to_string())
+    }
+}
+
 impl Default for Github {
     fn default() -> Self {
         let rt = app_shared::tokio::runtime::Builder::new_current_thread()
@@ -84,6 +294,11 @@ impl Default for Github {
             .build()
             .unwrap();
 
-        Self { client, rt }
+        Self {
+            client,
+            http: reqwest::Client::new(),
+            device_flows: BTreeMap::new(),
+            rt,
+        }
     }
 }
@@ -1,20 +1,233 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+
+use crate::api_config::ApiConfig;
 use app_macros::global;
 use app_shared::{
-    chrono::Utc,
-    models::{ActionType, Actor, JournalEntry},
+    chrono::{Duration, Utc},
+    models::{
+        ActionType, Actor, JournalChainBreak, JournalChainProblem, JournalChainReport, JournalEntry,
+    },
     prelude::*,
+    serde_json,
+    tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
     Database,
 };
 
+/// Сколько записей выбирается из БД за раз при архивации и проверке цепочки.
+const BATCH_SIZE: usize = 1000;
+
 #[derive(Debug, Default)]
 #[global(set, lock)]
-pub struct Journal;
+pub struct Journal {
+    subscribers: Vec<UnboundedSender<JournalEntry>>,
+}
 
 impl Journal {
+    /// Записывает действие в журнал. Причина обрезается до `JournalEntry::MAX_REASON_LENGTH` символов.
+    /// Новая запись рассылается всем подписчикам.
     #[instrument]
-    pub fn log(&self, object: Actor, subject: Option<Actor>, action: ActionType) -> JournalEntry {
+    pub fn log(
+        &mut self,
+        object: Actor,
+        subject: Option<Actor>,
+        action: ActionType,
+        reason: Option<String>,
+    ) -> JournalEntry {
         trace!("log");
 
-        Database::lock(|database| database.add_journal_entry(object, Utc::now(), subject, action))
+        let reason = reason
+            .map(|reason| {
+                reason
+                    .trim()
+                    .chars()
+                    .take(JournalEntry::MAX_REASON_LENGTH)
+                    .collect::<String>()
+            })
+            .filter(|reason| !reason.is_empty());
+
+        let entry = Database::lock(|database| {
+            database.add_journal_entry(object, Utc::now(), subject, action, reason)
+        });
+
+        self.subscribers
+            .retain(|subscriber| subscriber.send(entry.clone()).is_ok());
+
+        entry
+    }
+
+    /// Подписывается на новые записи журнала. Подписка снимается, когда получатель удалён.
+    #[instrument]
+    pub fn subscribe(&mut self) -> UnboundedReceiver<JournalEntry> {
+        trace!("subscribe");
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.subscribers.push(sender);
+
+        receiver
+    }
+
+    /// Сохраняет записи старше срока хранения их типа в архив `.ndjson.gz` и удаляет их из БД.
+    /// Возвращает путь к архиву и количество записей, `None` - устаревших записей нет.
+    /// Архив пишется без блокировки журнала, она берётся только на удаление записей.
+    #[instrument]
+    pub fn archive_expired() -> io::Result<Option<(PathBuf, usize)>> {
+        trace!("archive_expired");
+
+        let config = ApiConfig::get().unwrap().journal_retention;
+        let now = Utc::now();
+        let path = config
+            .archive_path
+            .join(format!("journal-{}.ndjson.gz", now.format("%Y%m%d-%H%M%S")));
+
+        let mut encoder = None;
+        let mut archived = Vec::new();
+
+        for (action, days) in &config.actions {
+            let before = now - Duration::days(*days);
+            let mut after_id = None;
+
+            loop {
+                let entries = Database::lock(|database| {
+                    database.get_expired_journal_entries(action, before, after_id, BATCH_SIZE)
+                });
+
+                let Some(last) = entries.last() else {
+                    break;
+                };
+
+                after_id = Some(last.id);
+
+                if encoder.is_none() {
+                    fs::create_dir_all(&config.archive_path)?;
+                    let file = OpenOptions::new()
+                        .write(true)
+                        .create_new(true)
+                        .open(&path)?;
+                    encoder = Some(GzEncoder::new(BufWriter::new(file), Compression::default()));
+                }
+
+                let writer = encoder.as_mut().unwrap();
+
+                for entry in entries {
+                    serde_json::to_writer(&mut *writer, &entry)?;
+                    writer.write_all(b"\n")?;
+                    archived.push(entry.id);
+                }
+            }
+        }
+
+        let Some(encoder) = encoder else {
+            return Ok(None);
+        };
+
+        // Записи удаляются только после того, как архив целиком записан на диск
+        encoder.finish()?.into_inner()?.sync_all()?;
+
+        for ids in archived.chunks(BATCH_SIZE) {
+            Journal::lock(|_| Database::lock(|database| database.archive_journal_entries(ids)));
+        }
+
+        Ok(Some((path, archived.len())))
+    }
+
+    /// Возвращает записи из архива в БД с прежними ID, уже существующие записи пропускаются.
+    /// Возвращает количество добавленных записей.
+    #[instrument]
+    pub fn import_archive(&self, path: &Path) -> io::Result<usize> {
+        trace!("import_archive");
+
+        let reader = BufReader::new(GzDecoder::new(File::open(path)?));
+        let mut imported = 0;
+
+        for line in reader.lines() {
+            let line = line?;
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let entry: JournalEntry = serde_json::from_str(&line)?;
+
+            if Database::lock(|database| database.restore_journal_entry(&entry)) {
+                imported += 1;
+            }
+        }
+
+        Ok(imported)
+    }
+
+    /// Проходит цепочку хэшей от первой записи и останавливается на первом нарушении.
+    #[instrument]
+    pub fn verify_chain(&self) -> JournalChainReport {
+        trace!("verify_chain");
+
+        let mut report = JournalChainReport::default();
+        let redacted = Database::lock(|database| database.get_redacted_journal_entry_ids());
+        let mut prev_hash = None;
+        let mut after_id = None;
+
+        loop {
+            let links =
+                Database::lock(|database| database.get_journal_chain_links(after_id, BATCH_SIZE));
+
+            let Some(last) = links.last() else {
+                return report;
+            };
+
+            after_id = Some(last.id);
+
+            for link in links {
+                report.checked += 1;
+
+                let problem = match &link.entry {
+                    _ if link.prev_hash != prev_hash => Some(JournalChainProblem::LinkBroken),
+                    _ if link.hash.is_none() => Some(JournalChainProblem::Unsigned),
+                    Some(entry) if link.hash.as_ref() != Some(&entry.compute_hash()) => {
+                        if redacted.contains(&entry.id) {
+                            report.redacted += 1;
+                            None
+                        } else {
+                            Some(JournalChainProblem::ContentChanged)
+                        }
+                    }
+                    Some(_) => None,
+                    None => {
+                        report.archived += 1;
+                        None
+                    }
+                };
+
+                if let Some(problem) = problem {
+                    report.broken = Some(JournalChainBreak {
+                        id: link.id,
+                        problem,
+                    });
+
+                    return report;
+                }
+
+                prev_hash = link.hash;
+            }
+        }
+    }
+
+    /// Архивирует устаревшие записи раз в `interval_hours` часов. Не возвращает управление.
+    pub fn run_archiver() {
+        loop {
+            match Journal::archive_expired() {
+                Ok(Some((path, count))) => info!("archived {count} journal entries to {path:?}"),
+                Ok(None) => debug!("no expired journal entries"),
+                Err(err) => error!("journal archivation failed: {err}"),
+            }
+
+            let interval_hours = ApiConfig::get().unwrap().journal_retention.interval_hours;
+            std::thread::sleep(std::time::Duration::from_secs(
+                interval_hours.max(1) * 60 * 60,
+            ));
+        }
     }
 }
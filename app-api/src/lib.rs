@@ -1,12 +1,19 @@
 #[allow(clippy::module_inception)]
 mod api;
 mod api_config;
+mod avatars;
 mod discord_api;
+mod discord_oauth;
 mod github;
 mod journal;
+mod mfa_logins;
 mod services;
+mod tfa_attempts;
 mod tfa_tokens_storage;
+mod totp;
 
 pub use api::Api;
+pub use avatars::PendingAvatar;
+pub use discord_oauth::{DiscordOAuthGrant, DiscordOAuthUser};
 pub use journal::Journal;
 use services::Service;
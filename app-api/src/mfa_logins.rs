@@ -0,0 +1,94 @@
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use std::collections::BTreeMap;
+
+use app_shared::{
+    chrono::{DateTime, Duration, Utc},
+    models::{AccountId, Secret},
+    prelude::*,
+};
+
+/// Сколько даётся на ввод кода второго фактора после первого.
+const LOGIN_LIFETIME_MINUTES: i64 = 5;
+
+/// Сколько неверных кодов можно ввести в одном входе.
+const MAX_FAILURES: u32 = 5;
+
+const SECRET_LENGTH: usize = 32;
+
+/// Входы, прошедшие первый фактор и ждущие код второго.
+#[derive(Debug, Clone, Default)]
+pub struct MfaLogins {
+    logins: BTreeMap<Secret, PendingLogin>,
+}
+
+#[derive(Debug, Clone)]
+struct PendingLogin {
+    account_id: AccountId,
+    expires_at: DateTime<Utc>,
+    failures: u32,
+}
+
+impl MfaLogins {
+    #[instrument(skip(self))]
+    pub fn start(&mut self, account_id: AccountId) -> Secret {
+        trace!("start");
+
+        let now = Utc::now();
+        self.logins.retain(|_, login| login.expires_at > now);
+
+        let secret = Secret(
+            rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(SECRET_LENGTH)
+                .map(char::from)
+                .collect(),
+        );
+
+        self.logins.insert(
+            secret.clone(),
+            PendingLogin {
+                account_id,
+                expires_at: now + Duration::minutes(LOGIN_LIFETIME_MINUTES),
+                failures: 0,
+            },
+        );
+
+        secret
+    }
+
+    /// Аккаунт, вход в который ждёт код второго фактора.
+    #[instrument(skip(self))]
+    pub fn find(&mut self, secret: &Secret) -> Option<AccountId> {
+        trace!("find");
+
+        let now = Utc::now();
+        self.logins.retain(|_, login| login.expires_at > now);
+
+        self.logins.get(secret).map(|login| login.account_id)
+    }
+
+    /// Завершает вход, второй раз тот же секрет уже не найдётся.
+    #[instrument(skip(self))]
+    pub fn finish(&mut self, secret: &Secret) {
+        trace!("finish");
+
+        self.logins.remove(secret);
+    }
+
+    /// Запоминает неверный код, после нескольких таких вход нужно начинать заново.
+    #[instrument(skip(self))]
+    pub fn record_failure(&mut self, secret: &Secret) {
+        trace!("record_failure");
+
+        let Some(login) = self.logins.get_mut(secret) else {
+            return;
+        };
+
+        login.failures += 1;
+
+        if login.failures >= MAX_FAILURES {
+            self.logins.remove(secret);
+        }
+    }
+}
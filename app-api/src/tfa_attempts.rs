@@ -0,0 +1,191 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use app_shared::{
+    chrono::{DateTime, Duration, Utc},
+    models::ApiError,
+    prelude::*,
+};
+
+use crate::api_config::TFAConfig;
+
+/// Неудачные попытки ввода кодов, по ключу (IP, аккаунту или токену сервиса) и со всех ключей вместе.
+/// Общий счётчик не запрещает ввод, а ужесточает блокировку ключей, пока он превышен.
+#[derive(Debug, Clone, Default)]
+pub struct TFAAttempts {
+    by_key: BTreeMap<String, KeyAttempts>,
+    failures: VecDeque<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct KeyAttempts {
+    /// Неудачные попытки в текущем окне.
+    failures: VecDeque<DateTime<Utc>>,
+    /// Сколько раз ключ уже блокировался, от этого зависит длина следующей блокировки.
+    lockouts: u32,
+    locked_until: Option<DateTime<Utc>>,
+}
+
+impl TFAAttempts {
+    /// Проверяет, можно ли сейчас вводить код с этого ключа.
+    #[instrument(skip(self, config))]
+    pub fn check(&mut self, key: &str, config: &TFAConfig) -> Result<(), ApiError> {
+        trace!("check");
+
+        let now = Utc::now();
+        self.cleanup(now, config);
+
+        if let Some(locked_until) = self
+            .by_key
+            .get(key)
+            .and_then(|attempts| attempts.locked_until)
+            .filter(|locked_until| *locked_until > now)
+        {
+            return Err(ApiError::Other(format!(
+                "Слишком много неверных кодов, попробуйте через {} с",
+                (locked_until - now).num_seconds() + 1
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Запоминает неверный код. Возвращает длину блокировки, если ключ заблокирован.
+    /// Пока превышено общее ограничение, ключ блокируется после первого же неверного кода.
+    #[instrument(skip(self, config))]
+    pub fn record_failure(&mut self, key: &str, config: &TFAConfig) -> Option<Duration> {
+        trace!("record_failure");
+
+        let now = Utc::now();
+        self.failures.push_back(now);
+
+        let max_failures = if self.failures.len() > config.max_failures_global {
+            warn!("global tfa failure limit reached");
+            1
+        } else {
+            config.max_failures_per_ip
+        };
+
+        let attempts = self.by_key.entry(key.to_string()).or_default();
+        attempts.failures.push_back(now);
+
+        if attempts.failures.len() < max_failures {
+            return None;
+        }
+
+        let lockout = Duration::seconds(
+            config
+                .lockout_base_seconds
+                .saturating_mul(1 << attempts.lockouts.min(30))
+                .min(config.lockout_max_seconds),
+        );
+
+        attempts.failures.clear();
+        attempts.lockouts += 1;
+        attempts.locked_until = Some(now + lockout);
+
+        Some(lockout)
+    }
+
+    /// Забывает попытки старше окна. Счётчик блокировок ключа сбрасывается,
+    /// если после последней блокировки прошло больше самой долгой блокировки.
+    fn cleanup(&mut self, now: DateTime<Utc>, config: &TFAConfig) {
+        let window_start = now - Duration::seconds(config.window_seconds);
+        let lockouts_reset = now - Duration::seconds(config.lockout_max_seconds);
+
+        while self
+            .failures
+            .front()
+            .is_some_and(|failure| *failure <= window_start)
+        {
+            self.failures.pop_front();
+        }
+
+        self.by_key.retain(|_, attempts| {
+            while attempts
+                .failures
+                .front()
+                .is_some_and(|failure| *failure <= window_start)
+            {
+                attempts.failures.pop_front();
+            }
+
+            !attempts.failures.is_empty()
+                || attempts
+                    .locked_until
+                    .is_some_and(|locked_until| locked_until > lockouts_reset)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> TFAConfig {
+        TFAConfig {
+            max_failures_per_ip: 3,
+            max_failures_global: 10,
+            lockout_base_seconds: 60,
+            lockout_max_seconds: 200,
+            ..Default::default()
+        }
+    }
+
+    /// Снимает блокировку ключа, как будто она истекла, но счётчик блокировок остаётся.
+    fn expire_lockout(attempts: &mut TFAAttempts, key: &str) {
+        attempts.by_key.get_mut(key).unwrap().locked_until = Some(Utc::now());
+    }
+
+    #[test]
+    fn locks_key_after_max_failures() {
+        let config = config();
+        let mut attempts = TFAAttempts::default();
+
+        assert_eq!(attempts.record_failure("ip", &config), None);
+        assert_eq!(attempts.record_failure("ip", &config), None);
+        assert!(attempts.check("ip", &config).is_ok());
+
+        assert_eq!(
+            attempts.record_failure("ip", &config),
+            Some(Duration::seconds(60))
+        );
+        assert!(attempts.check("ip", &config).is_err());
+        assert!(attempts.check("other", &config).is_ok());
+    }
+
+    #[test]
+    fn lockouts_double_up_to_max() {
+        let config = config();
+        let mut attempts = TFAAttempts::default();
+        let mut lockouts = Vec::new();
+
+        for _ in 0..4 {
+            for _ in 0..config.max_failures_per_ip {
+                if let Some(lockout) = attempts.record_failure("ip", &config) {
+                    lockouts.push(lockout.num_seconds());
+                }
+            }
+
+            expire_lockout(&mut attempts, "ip");
+            // Глобальный счётчик не должен влиять на проверку эскалации
+            attempts.failures.clear();
+        }
+
+        assert_eq!(lockouts, [60, 120, 200, 200]);
+    }
+
+    #[test]
+    fn global_limit_locks_on_first_failure() {
+        let config = config();
+        let mut attempts = TFAAttempts::default();
+
+        for index in 0..config.max_failures_global {
+            attempts.record_failure(&format!("ip{index}"), &config);
+        }
+
+        assert!(attempts.check("fresh", &config).is_ok());
+        assert!(attempts.record_failure("fresh", &config).is_some());
+        assert!(attempts.check("fresh", &config).is_err());
+        assert!(attempts.check("another", &config).is_ok());
+    }
+}
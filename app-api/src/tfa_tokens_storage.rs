@@ -17,7 +17,12 @@ impl TFATokensStorage {
     }
 
     #[instrument(skip(self))]
-    pub fn new_token(&mut self, discord_user_id: DiscordUserId, duration: Duration) -> TFAToken {
+    pub fn new_token(
+        &mut self,
+        discord_user_id: DiscordUserId,
+        duration: Duration,
+        length: usize,
+    ) -> TFAToken {
         trace!("new_token");
 
         self.remove_expired_tokens();
@@ -25,7 +30,7 @@ impl TFATokensStorage {
         let mut secret;
 
         loop {
-            secret = Secret::new_random_tfa_secret();
+            secret = Secret::new_random_tfa_secret(length);
 
             if self.tokens.iter().any(|t| t.secret == secret) {
                 continue;
@@ -40,15 +45,19 @@ impl TFATokensStorage {
         token
     }
 
+    /// Забирает токен по секрету, второй раз тот же токен уже не найдётся.
     #[instrument(skip(self))]
-    pub fn find_by_secret(&mut self, secret: Secret) -> Option<&TFAToken> {
-        trace!("find_by_secret");
+    pub fn take_by_secret(&mut self, secret: Secret) -> Option<TFAToken> {
+        trace!("take_by_secret");
 
         self.remove_expired_tokens();
 
-        self.tokens
+        let index = self
+            .tokens
             .iter()
-            .find(|t| t.secret == secret && !t.is_expired())
+            .position(|t| t.secret == secret && !t.is_expired())?;
+
+        Some(self.tokens.remove(index))
     }
 
     #[instrument(skip(self))]
@@ -0,0 +1,172 @@
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha1::Sha1;
+
+use app_shared::{
+    chrono::{DateTime, Utc},
+    models::{Secret, TotpEnrollment},
+};
+
+type HmacSha1 = Hmac<Sha1>;
+
+/// Название сервиса в приложении-аутентификаторе.
+const ISSUER: &str = "Indigo";
+
+const SECRET_LENGTH: usize = 20;
+const STEP_SECONDS: i64 = 30;
+const DIGITS: u32 = 6;
+
+/// Сколько соседних шагов принимается, чтобы пережить расхождение часов.
+const ALLOWED_DRIFT: i64 = 1;
+
+const BASE32_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// TOTP по RFC 6238: HMAC-SHA1, шаг 30 секунд, 6 цифр.
+pub struct Totp;
+
+impl Totp {
+    /// Новый случайный ключ в hex.
+    pub fn new_secret() -> Secret {
+        let mut key = [0u8; SECRET_LENGTH];
+        rand::thread_rng().fill_bytes(&mut key);
+
+        Secret(hex::encode(key))
+    }
+
+    /// Ключ в base32 и ссылка `otpauth://` для приложения-аутентификатора.
+    pub fn enrollment(secret: &Secret, account_name: &str) -> TotpEnrollment {
+        let encoded = encode_base32(&hex::decode(&secret.0).unwrap_or_default());
+
+        let mut uri = reqwest::Url::parse("otpauth://totp/").unwrap();
+        uri.set_path(&format!("{ISSUER}:{account_name}"));
+        uri.query_pairs_mut()
+            .append_pair("secret", &encoded)
+            .append_pair("issuer", ISSUER)
+            .append_pair("digits", &DIGITS.to_string())
+            .append_pair("period", &STEP_SECONDS.to_string());
+
+        TotpEnrollment {
+            secret: encoded,
+            uri: uri.to_string(),
+        }
+    }
+
+    /// Проверяет код. Возвращает шаг, которому он соответствует.
+    pub fn verify(secret: &Secret, code: &str, now: DateTime<Utc>) -> Option<i64> {
+        if code.len() != DIGITS as usize || !code.chars().all(|char| char.is_ascii_digit()) {
+            return None;
+        }
+
+        let key = hex::decode(&secret.0).ok()?;
+        let current_step = now.timestamp().div_euclid(STEP_SECONDS);
+
+        (current_step - ALLOWED_DRIFT..=current_step + ALLOWED_DRIFT)
+            .find(|step| Self::code(&key, *step) == code)
+    }
+
+    fn code(key: &[u8], step: i64) -> String {
+        let mut mac = HmacSha1::new_from_slice(key).unwrap();
+        mac.update(&step.to_be_bytes());
+        let hash = mac.finalize().into_bytes();
+
+        let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+        let binary = u32::from_be_bytes(hash[offset..offset + 4].try_into().unwrap()) & 0x7fff_ffff;
+
+        format!(
+            "{:0width$}",
+            binary % 10u32.pow(DIGITS),
+            width = DIGITS as usize
+        )
+    }
+}
+
+/// Base32 по RFC 4648 без выравнивания, так ключ вводится в приложения.
+fn encode_base32(bytes: &[u8]) -> String {
+    let mut result = String::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for byte in bytes {
+        buffer = (buffer << 8) | *byte as u32;
+        bits += 8;
+
+        while bits >= 5 {
+            bits -= 5;
+            result.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+
+    if bits > 0 {
+        result.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use app_shared::chrono::TimeZone;
+
+    /// Ключ SHA1 из приложения B RFC 6238.
+    const RFC_KEY: &[u8] = b"12345678901234567890";
+
+    fn rfc_secret() -> Secret {
+        Secret(hex::encode(RFC_KEY))
+    }
+
+    #[test]
+    fn matches_rfc_6238_vectors() {
+        // Последние 6 цифр восьмизначных кодов из RFC.
+        let vectors = [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1111111111, "050471"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+            (20000000000, "353130"),
+        ];
+
+        for (time, code) in vectors {
+            assert_eq!(
+                Totp::code(RFC_KEY, time / STEP_SECONDS),
+                code,
+                "time {time}"
+            );
+        }
+    }
+
+    #[test]
+    fn verify_accepts_adjacent_steps_only() {
+        let now = Utc.timestamp_opt(1111111111, 0).unwrap();
+        let step = now.timestamp() / STEP_SECONDS;
+
+        for drift in -ALLOWED_DRIFT..=ALLOWED_DRIFT {
+            let code = Totp::code(RFC_KEY, step + drift);
+            assert_eq!(Totp::verify(&rfc_secret(), &code, now), Some(step + drift));
+        }
+
+        let stale = Totp::code(RFC_KEY, step - ALLOWED_DRIFT - 1);
+        assert_eq!(Totp::verify(&rfc_secret(), &stale, now), None);
+    }
+
+    #[test]
+    fn verify_rejects_malformed_codes() {
+        let now = Utc.timestamp_opt(59, 0).unwrap();
+
+        assert_eq!(Totp::verify(&rfc_secret(), "94287082", now), None);
+        assert_eq!(Totp::verify(&rfc_secret(), "28708", now), None);
+        assert_eq!(Totp::verify(&rfc_secret(), "28708a", now), None);
+        assert_eq!(
+            Totp::verify(&Secret("не hex".to_string()), "287082", now),
+            None
+        );
+    }
+
+    #[test]
+    fn encodes_base32_without_padding() {
+        assert_eq!(encode_base32(RFC_KEY), "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+        assert_eq!(encode_base32(b"f"), "MY");
+        assert_eq!(encode_base32(b"foobar"), "MZXW6YTBOI");
+    }
+}
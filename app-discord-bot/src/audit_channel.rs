@@ -0,0 +1,347 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+use app_api::{Api, Journal};
+use app_macros::config;
+use app_shared::{
+    models::{Account, AccountId, ActionType, Actor, ApiTokenId, JournalEntry, Role, RoleId},
+    prelude::*,
+    serde_json,
+    serenity::{
+        builder::CreateEmbed,
+        http::Http,
+        model::{id::ChannelId, prelude::Mention, Timestamp},
+    },
+    tokio::{self, sync::mpsc::UnboundedReceiver},
+    DiscordConfig,
+};
+
+/// Сколько ждать остальные записи пачки после первой.
+const BATCH_DELAY: Duration = Duration::from_secs(2);
+
+/// Discord принимает не больше 10 embed в одном сообщении.
+const MAX_EMBEDS: usize = 10;
+
+/// Сколько раз пробовать отправить сообщение, прежде чем отбросить записи.
+const MAX_SEND_ATTEMPTS: u32 = 5;
+
+/// Пауза перед первым повтором, каждая следующая вдвое дольше.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Без этого конфига записи журнала в Discord не пересылаются.
+#[config]
+#[derive(Debug)]
+struct AuditChannelConfig {
+    /// Канал для администрации, в который пересылаются записи.
+    pub channel_id: ChannelId,
+    /// Типы действий, которые пересылаются, например `RoleAdded`.
+    pub actions: BTreeSet<String>,
+}
+
+/// Аккаунты и роли, на которые ссылаются записи пачки.
+#[derive(Debug, Default)]
+struct Names {
+    accounts: HashMap<AccountId, Account>,
+    roles: HashMap<RoleId, Role>,
+}
+
+/// Подписывается на журнал и пересылает новые записи в канал, пока работает бот.
+/// Подписка оформляется до подключения к Discord, чтобы не пропустить записи из `ready`.
+#[instrument(skip(http))]
+pub fn spawn(http: Arc<Http>) {
+    trace!("spawn");
+
+    let Some(config) = AuditChannelConfig::get() else {
+        return;
+    };
+
+    if config.actions.is_empty() {
+        return;
+    }
+
+    let receiver = Journal::lock(|journal| journal.subscribe());
+
+    tokio::spawn(run(http, config, receiver));
+}
+
+/// Собирает записи в пачки, чтобы всплеск записей уходил несколькими сообщениями, а не сотней.
+async fn run(
+    http: Arc<Http>,
+    config: AuditChannelConfig,
+    mut receiver: UnboundedReceiver<JournalEntry>,
+) {
+    while let Some(entry) = receiver.recv().await {
+        if !is_selected(&config, &entry) {
+            continue;
+        }
+
+        let mut batch = vec![entry];
+
+        tokio::time::sleep(BATCH_DELAY).await;
+
+        while let Ok(entry) = receiver.try_recv() {
+            if is_selected(&config, &entry) {
+                batch.push(entry);
+            }
+        }
+
+        let names = resolve_names(&batch).await;
+
+        for entries in batch.chunks(MAX_EMBEDS) {
+            let embeds = entries
+                .iter()
+                .map(|entry| {
+                    let mut embed = CreateEmbed::default();
+                    create_entry_embed(&mut embed, entry, &names);
+                    embed
+                })
+                .collect::<Vec<_>>();
+
+            send_embeds(&http, config.channel_id, embeds).await;
+        }
+    }
+}
+
+/// Отправляет сообщение, при ошибке повторяя попытку с нарастающей паузой.
+async fn send_embeds(http: &Http, channel_id: ChannelId, embeds: Vec<CreateEmbed>) {
+    let mut delay = RETRY_DELAY;
+
+    for attempt in 1..=MAX_SEND_ATTEMPTS {
+        let result = channel_id
+            .send_message(http, |message| message.set_embeds(embeds.clone()))
+            .await;
+
+        let Err(err) = result else {
+            return;
+        };
+
+        if attempt == MAX_SEND_ATTEMPTS {
+            error!(
+                "failed to send {} journal entries to audit channel, dropping them: {err}",
+                embeds.len()
+            );
+            return;
+        }
+
+        warn!("failed to send journal entries to audit channel, retrying in {delay:?}: {err}");
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+    }
+}
+
+fn is_selected(config: &AuditChannelConfig, entry: &JournalEntry) -> bool {
+    config.actions.contains(&action_name(&entry.action))
+}
+
+fn action_name(action: &ActionType) -> String {
+    serde_json::to_value(action).unwrap_or_default()["type"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string()
+}
+
+async fn resolve_names(entries: &[JournalEntry]) -> Names {
+    let account_ids = entries
+        .iter()
+        .flat_map(|entry| [Some(&entry.object), entry.subject.as_ref()])
+        .filter_map(|actor| match actor {
+            Some(Actor::User(account_id)) => Some(*account_id),
+            _ => None,
+        })
+        .unique()
+        .collect::<Vec<_>>();
+
+    Api::lock_async(move |api| Names {
+        accounts: api
+            .find_accounts_by_ids(account_ids)
+            .into_iter()
+            .map(|account| (account.id, account))
+            .collect(),
+        roles: api
+            .get_roles()
+            .into_iter()
+            .map(|role| (role.id, role))
+            .collect(),
+    })
+    .await
+    .unwrap()
+}
+
+#[instrument(skip(embed))]
+fn create_entry_embed<'a>(
+    embed: &'a mut CreateEmbed,
+    entry: &JournalEntry,
+    names: &Names,
+) -> &'a mut CreateEmbed {
+    debug!("create_entry_embed");
+
+    let (title, details) = describe_action(&entry.action, names);
+
+    embed
+        .title(title)
+        .field("Кто", describe_actor(&entry.object, names), true)
+        .footer(|footer| footer.text(format!("Запись #{}", entry.id.0)))
+        .timestamp(Timestamp::from(entry.datetime));
+
+    if let Some(subject) = &entry.subject {
+        embed.field("Над кем", describe_actor(subject, names), true);
+    }
+
+    if let Some(details) = details {
+        embed.description(details);
+    }
+
+    if let Some(reason) = &entry.reason {
+        embed.field("Причина", reason, false);
+    }
+
+    if let Some(site_url) = DiscordConfig::get().unwrap().site_url {
+        embed.url(format!(
+            "{}/admin/journal?before_id={}",
+            site_url.trim_end_matches('/'),
+            entry.id.0 + 1
+        ));
+    }
+
+    embed
+}
+
+/// Имя пользователя с упоминанием в Discord.
+fn describe_actor(actor: &Actor, names: &Names) -> String {
+    match actor {
+        Actor::System => "Система".to_string(),
+        Actor::User(account_id) => match names.accounts.get(account_id) {
+            Some(account) => format!(
+                "{} ({})",
+                account.username,
+                Mention::User(account.integrations.discord_user_id)
+            ),
+            None => format!("Аккаунт #{}", account_id.0),
+        },
+        Actor::Webhook(name) => format!("Вебхук {name}"),
+        Actor::Deleted => "Удалённый аккаунт".to_string(),
+    }
+}
+
+/// Название действия и подробности, как на странице журнала.
+fn describe_action(action: &ActionType, names: &Names) -> (&'static str, Option<String>) {
+    match action {
+        ActionType::AccountCreated => ("Создан аккаунт", None),
+        ActionType::ByondConnected { ckey } => {
+            ("Подключен BYOND", Some(format!("Ckey: {}", ckey.0)))
+        }
+        ActionType::ByondDisconnected { ckey } => {
+            ("Отключен BYOND", Some(format!("Ckey: {}", ckey.0)))
+        }
+        ActionType::SS14Connected { ss14_guid } => {
+            ("Подключен SS14", Some(format!("Guid: {}", ss14_guid.0)))
+        }
+        ActionType::SS14Disconnected { ss14_guid } => {
+            ("Отключен SS14", Some(format!("Guid: {}", ss14_guid.0)))
+        }
+        ActionType::GithubConnected { github_user_id } => (
+            "Подключен GitHub",
+            Some(format!("GitHub ID: {}", github_user_id.0)),
+        ),
+        ActionType::GithubDisconnected { github_user_id } => (
+            "Отключен GitHub",
+            Some(format!("GitHub ID: {}", github_user_id.0)),
+        ),
+        ActionType::RoleAdded { role_id } => {
+            ("Добавлена роль", Some(describe_role(role_id, names)))
+        }
+        ActionType::RoleRemoved { role_id } => ("Убрана роль", Some(describe_role(role_id, names))),
+        ActionType::WebhookCreated => ("Создан вебхук", None),
+        ActionType::WebhookDeleted => ("Удалён вебхук", None),
+        ActionType::ApiTokenCreated => ("Создан API токен", None),
+        ActionType::ApiTokenDeleted => ("Удалён API токен", None),
+        ActionType::ApiTokenIssued { token_id, label } => {
+            ("Создан API токен", Some(describe_token(token_id, label)))
+        }
+        ActionType::ApiTokenRevoked { token_id, label } => {
+            ("Удалён API токен", Some(describe_token(token_id, label)))
+        }
+        ActionType::ApiTokenSecretRotated { token_id, label } => (
+            "Перевыпущен API токен",
+            Some(describe_token(token_id, label)),
+        ),
+        ActionType::AccountsMerged { source_id } => (
+            "Объединены аккаунты",
+            Some(format!("Дубликат: #{}", source_id.0)),
+        ),
+        ActionType::AccountDeleted => ("Удалён аккаунт", None),
+        ActionType::JournalEntriesRedacted { entry_ids } => (
+            "Обезличены записи журнала",
+            Some(format!("Записей: {}", entry_ids.len())),
+        ),
+        ActionType::UsernameChanged { old, new } => {
+            ("Изменено имя", Some(format!("{old} → {new}")))
+        }
+        ActionType::NoteAdded { note_id } => (
+            "Добавлена заметка",
+            Some(format!("Заметка: #{}", note_id.0)),
+        ),
+        ActionType::NoteDeleted { note_id } => {
+            ("Удалена заметка", Some(format!("Заметка: #{}", note_id.0)))
+        }
+        ActionType::SessionCreated { ip, user_agent } => {
+            ("Выполнен вход", Some(format!("IP: {ip}, {user_agent}")))
+        }
+        ActionType::SessionRevoked { ip, user_agent } => {
+            ("Завершена сессия", Some(format!("IP: {ip}, {user_agent}")))
+        }
+        ActionType::AvatarChanged { old, new } => {
+            ("Изменена аватарка", Some(format!("{old} → {new}")))
+        }
+        ActionType::TFATokenIssued => ("Выдан код входа", None),
+        ActionType::TFAAttemptFailed {
+            ip,
+            lockout_seconds,
+        } => (
+            "Неверный код входа",
+            Some(describe_tfa_attempt(ip, lockout_seconds)),
+        ),
+        ActionType::MfaEnabled => ("Подключён второй фактор", None),
+        ActionType::MfaDisabled => ("Отключён второй фактор", None),
+        ActionType::MfaRecoveryCodeUsed => ("Использован код восстановления", None),
+        ActionType::MfaAttemptFailed { ip } => (
+            "Неверный код второго фактора",
+            ip.as_ref().map(|ip| format!("IP: {ip}")),
+        ),
+        ActionType::FeatureVoteEnded { descriptor } => {
+            let guild_id = DiscordConfig::get().unwrap().guild_id;
+
+            (
+                "Завершено голосование",
+                Some(format!(
+                    "https://discord.com/channels/{}/{}/{}",
+                    guild_id.0, descriptor.1 .0, descriptor.0 .0
+                )),
+            )
+        }
+    }
+}
+
+fn describe_tfa_attempt(ip: &Option<String>, lockout_seconds: &Option<i64>) -> String {
+    let ip = match ip {
+        Some(ip) => format!("IP: {ip}"),
+        None => "Через сервис".to_string(),
+    };
+
+    match lockout_seconds {
+        Some(lockout_seconds) => format!("{ip}, заблокирован на {lockout_seconds} с"),
+        None => ip,
+    }
+}
+
+fn describe_role(role_id: &RoleId, names: &Names) -> String {
+    match names.roles.get(role_id) {
+        Some(role) => format!("Роль: {}", role.name),
+        None => format!("Роль: #{}", role_id.0),
+    }
+}
+
+fn describe_token(token_id: &ApiTokenId, label: &str) -> String {
+    format!("Токен: #{} «{label}»", token_id.0)
+}
@@ -5,6 +5,7 @@ use app_shared::{
     DiscordConfig,
 };
 
+use super::audit_channel;
 use super::handler::Handler;
 
 #[derive(Debug)]
@@ -25,6 +26,8 @@ impl BotClient {
                 .await
                 .unwrap();
 
+            audit_channel::spawn(client.cache_and_http.http.clone());
+
             client.start().await.unwrap();
         });
     }
@@ -14,7 +14,9 @@ use app_shared::{
     },
 };
 
-use crate::commands::feedback::helpers::{get_attachment_url_from_option, get_value_as_string};
+use crate::commands::feedback::helpers::{
+    format_issue_author, get_attachment_url_from_option, get_value_as_string,
+};
 
 #[instrument(skip(ctx))]
 pub async fn handle_bug_report(ctx: &Context, cmd: &ApplicationCommandInteraction) {
@@ -63,10 +65,7 @@ pub async fn handle_bug_report(ctx: &Context, cmd: &ApplicationCommandInteractio
         body += &format!("**[Скриншот]({screenshot_url})**\n");
     }
 
-    let author = format!(
-        "{}#{} ({})",
-        cmd.user.name, cmd.user.discriminator, author_id
-    );
+    let author = format_issue_author(&cmd.user).await;
 
     body += &format!(
         "_Этот иссуй был создан автоматически по сообщению из дискорда. Автор: {author}._"
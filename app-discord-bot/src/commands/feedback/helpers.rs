@@ -1,4 +1,6 @@
-﻿use app_shared::{
+﻿use app_api::Api;
+use app_shared::{
+    models::AnyUserId,
     prelude::*,
     serenity::{
         builder::CreateEmbed,
@@ -69,6 +71,28 @@ pub fn get_attachment_url_from_option(option: &CommandDataOption) -> String {
     }
 }
 
+/// Подпись автора иссуя, с упоминанием на GitHub, если он привязан.
+#[instrument]
+pub async fn format_issue_author(author: &DiscordUser) -> String {
+    trace!("format_issue_author");
+
+    let user_id = author.id;
+    let github_login = Api::lock_async(move |api| {
+        api.find_integrations_by_account_id(AnyUserId::DiscordId(user_id))
+            .ok()
+            .and_then(|integrations| integrations.github_login)
+    })
+    .await
+    .unwrap();
+
+    let author = format!("{}#{} ({})", author.name, author.discriminator, author.id);
+
+    match github_login {
+        Some(login) => format!("{author}, @{login}"),
+        None => author,
+    }
+}
+
 pub async fn is_user_id_mine(user_id: DiscordUserId) -> bool {
     let session = DiscordSession::clone_state();
 
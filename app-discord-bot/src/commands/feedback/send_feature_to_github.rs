@@ -1,12 +1,14 @@
 use app_api::Api;
 use app_shared::{prelude::*, serenity::model::prelude::Message};
 
+use super::helpers::format_issue_author;
+
 #[instrument]
 pub async fn send_feature_to_github(message: &Message, author: &DiscordUser) {
     trace!("send_feature_to_github");
 
     let embed = message.embeds.first().unwrap().clone();
-    let author = format!("{}#{} ({})", author.name, author.discriminator, author.id);
+    let author = format_issue_author(author).await;
     let content = format!("{}\n\n_Этот иссуй был создан автоматически по [сообщению из дискорда]({}). Автор: {author}._", embed.description.unwrap(), message.link());
 
     Api::lock_async(|api| {
@@ -0,0 +1,2 @@
+pub const COMMAND_NAME: &str = "profile";
+pub const USER_OPTION_NAME: &str = "user";
@@ -0,0 +1,7 @@
+mod constants;
+mod register;
+mod run;
+
+pub use constants::COMMAND_NAME;
+pub use register::register;
+pub use run::run;
@@ -0,0 +1,22 @@
+use app_shared::{
+    prelude::*,
+    serenity::{builder::CreateApplicationCommand, model::prelude::command::CommandOptionType},
+};
+
+use super::constants::*;
+
+#[instrument]
+pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name(COMMAND_NAME)
+        .name_localized("ru", "профиль")
+        .description("Показать профиль пользователя")
+        .create_option(|option| {
+            option
+                .name(USER_OPTION_NAME)
+                .name_localized("ru", "пользователь")
+                .description("Чей профиль показать, по умолчанию ваш")
+                .kind(CommandOptionType::User)
+                .required(false)
+        })
+}
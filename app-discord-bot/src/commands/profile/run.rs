@@ -0,0 +1,113 @@
+use app_api::Api;
+use app_shared::{
+    models::{AnyUserId, PublicProfile},
+    prelude::*,
+    serenity::{
+        builder::CreateEmbed,
+        model::{
+            prelude::interaction::{
+                application_command::{ApplicationCommandInteraction, CommandDataOptionValue},
+                InteractionResponseType,
+            },
+            Timestamp,
+        },
+        prelude::Context,
+        utils::Color,
+    },
+    DiscordConfig,
+};
+
+use super::constants::USER_OPTION_NAME;
+
+#[instrument(skip(ctx))]
+pub async fn run(ctx: &Context, cmd: &ApplicationCommandInteraction) {
+    trace!("profile runned");
+
+    let user_id = cmd
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == USER_OPTION_NAME)
+        .and_then(|option| match &option.resolved {
+            Some(CommandDataOptionValue::User(user, _)) => Some(user.id),
+            _ => None,
+        })
+        .unwrap_or(cmd.user.id);
+
+    let profile = Api::lock_async(move |api| api.get_public_profile(AnyUserId::DiscordId(user_id)))
+        .await
+        .unwrap();
+
+    cmd.create_interaction_response(&ctx.http, |response| {
+        response
+            .kind(InteractionResponseType::ChannelMessageWithSource)
+            .interaction_response_data(|message| match profile {
+                Ok(profile) => message.embed(|embed| create_profile_embed(embed, profile)),
+                Err(_) => message
+                    .ephemeral(true)
+                    .content("У пользователя ещё нет аккаунта"),
+            })
+    })
+    .await
+    .unwrap();
+}
+
+#[instrument]
+fn create_profile_embed(embed: &mut CreateEmbed, profile: PublicProfile) -> &mut CreateEmbed {
+    debug!("create_profile_embed");
+
+    let site_url = DiscordConfig::get()
+        .unwrap()
+        .site_url
+        .map(|url| url.trim_end_matches('/').to_string());
+
+    embed.title(&profile.username);
+
+    if let Some(site_url) = &site_url {
+        embed.url(format!("{site_url}/u/{}", profile.username));
+    }
+
+    if profile.avatar_url.starts_with("http") {
+        embed.thumbnail(&profile.avatar_url);
+    } else if let Some(site_url) = &site_url {
+        embed.thumbnail(format!("{site_url}{}", profile.avatar_url));
+    }
+
+    if let Some(main_role) = profile.main_role() {
+        let color = main_role.color;
+
+        embed
+            .color(Color::from_rgb(color.r, color.g, color.b))
+            .field("Роль", main_role.name, true);
+    }
+
+    if let Some(donation_tier) = profile.donation_tier {
+        embed.field("Подписка", donation_tier.name, true);
+    }
+
+    if let Some(created_at) = profile.created_at {
+        embed.field(
+            "Присоединился",
+            format!("<t:{}:D>", Timestamp::from(created_at).unix_timestamp()),
+            true,
+        );
+    }
+
+    if let Some(ckey) = profile.byond_ckey {
+        embed.field("BYOND", ckey.0, true);
+    }
+
+    if let Some(ss14_guid) = profile.ss14_guid {
+        embed.field("SS14", ss14_guid.0, true);
+    }
+
+    if let Some(github_login) = profile.github_login {
+        embed.field(
+            "GitHub",
+            format!("[{github_login}](https://github.com/{github_login})"),
+            true,
+        );
+    }
+
+    embed
+}
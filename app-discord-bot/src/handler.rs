@@ -1,5 +1,5 @@
 use crate::{
-    commands::{self, feedback::COMMAND_NAME},
+    commands::{self, feedback, profile},
     roles_bind, roles_list,
 };
 use app_shared::{
@@ -117,7 +117,9 @@ impl EventHandler for Handler {
         info!("registering application commands");
         let commands = guild
             .set_application_commands(&ctx.http, |commands| {
-                commands.create_application_command(commands::feedback::register)
+                commands
+                    .create_application_command(commands::feedback::register)
+                    .create_application_command(commands::profile::register)
             })
             .await
             .unwrap();
@@ -149,9 +151,9 @@ impl EventHandler for Handler {
             return;
         }
 
-        #[allow(clippy::single_match)]
         match cmd.data.name.as_str() {
-            COMMAND_NAME => commands::feedback::run(&ctx, &cmd).await,
+            feedback::COMMAND_NAME => commands::feedback::run(&ctx, &cmd).await,
+            profile::COMMAND_NAME => commands::profile::run(&ctx, &cmd).await,
             _ => (),
         };
     }
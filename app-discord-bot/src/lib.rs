@@ -1,3 +1,4 @@
+mod audit_channel;
 pub mod bot_client;
 mod commands;
 mod handler;
@@ -1 +1,4 @@
 pub const COOKIES_SESSION_KEY: &str = "session";
+pub const COOKIES_DISCORD_STATE_KEY: &str = "discord_state";
+pub const COOKIES_MFA_LOGIN_KEY: &str = "mfa_login";
+pub const REASON_HEADER: &str = "X-Reason";
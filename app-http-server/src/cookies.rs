@@ -1,13 +1,13 @@
-use crate::constants::COOKIES_SESSION_KEY;
-use actix_web::cookie::time::OffsetDateTime;
+use crate::constants::{COOKIES_DISCORD_STATE_KEY, COOKIES_MFA_LOGIN_KEY, COOKIES_SESSION_KEY};
+use actix_web::cookie::time::{Duration, OffsetDateTime};
 use actix_web::cookie::{Cookie, SameSite};
-use app_shared::models::Session;
+use app_shared::models::{Secret, Session, SessionSecrets};
 
 pub struct SessionCookie;
 
 impl SessionCookie {
-    pub fn from_session(session: Session) -> Cookie<'static> {
-        let mut session_cookie = Cookie::new(COOKIES_SESSION_KEY, session.secret.0);
+    pub fn from_session(session: Session, secrets: SessionSecrets) -> Cookie<'static> {
+        let mut session_cookie = Cookie::new(COOKIES_SESSION_KEY, secrets.secret.0);
         let expiration =
             OffsetDateTime::from_unix_timestamp(session.expiration.timestamp()).unwrap();
 
@@ -20,3 +20,52 @@ impl SessionCookie {
         session_cookie
     }
 }
+
+/// `state` входа через Discord, которым браузер подтверждает, что вход начал он.
+pub struct DiscordStateCookie;
+
+impl DiscordStateCookie {
+    pub fn from_state(state: String) -> Cookie<'static> {
+        let mut state_cookie = Cookie::new(COOKIES_DISCORD_STATE_KEY, state);
+
+        state_cookie.set_max_age(Duration::minutes(10));
+        state_cookie.set_http_only(true);
+        state_cookie.set_secure(true);
+        // Lax, иначе cookie не придёт вместе с редиректом из Discord
+        state_cookie.set_same_site(SameSite::Lax);
+        state_cookie.set_path("/auth/discord");
+
+        state_cookie
+    }
+
+    pub fn removal() -> Cookie<'static> {
+        let mut state_cookie = Self::from_state(String::new());
+        state_cookie.make_removal();
+
+        state_cookie
+    }
+}
+
+/// Вход, который ждёт код второго фактора.
+pub struct MfaLoginCookie;
+
+impl MfaLoginCookie {
+    pub fn from_secret(secret: Secret) -> Cookie<'static> {
+        let mut login_cookie = Cookie::new(COOKIES_MFA_LOGIN_KEY, secret.0);
+
+        login_cookie.set_max_age(Duration::minutes(5));
+        login_cookie.set_http_only(true);
+        login_cookie.set_secure(true);
+        login_cookie.set_same_site(SameSite::Strict);
+        login_cookie.set_path("/api/auth");
+
+        login_cookie
+    }
+
+    pub fn removal() -> Cookie<'static> {
+        let mut login_cookie = Self::from_secret(Secret::default());
+        login_cookie.make_removal();
+
+        login_cookie
+    }
+}
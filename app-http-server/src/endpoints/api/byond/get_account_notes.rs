@@ -0,0 +1,36 @@
+﻿use actix_web::{get, web, HttpRequest, Responder};
+use app_api::Api;
+use serde::Deserialize;
+
+use app_shared::{
+    models::{GameServerId, Secret},
+    prelude::*,
+};
+
+use crate::{RequestHelpers, ResponseHelpers};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Query {
+    pub secret: Secret,
+    pub ckey: ByondUserId,
+    pub server_id: Option<GameServerId>,
+}
+
+#[instrument]
+#[get("/byond/notes")]
+pub async fn endpoint(request: HttpRequest, query: web::Query<Query>) -> impl Responder {
+    trace!("endpoint");
+
+    let Query {
+        secret,
+        ckey,
+        server_id,
+    } = query.0;
+    let caller = RequestHelpers::api_caller(&request, secret);
+
+    let result = Api::lock_async(|api| api.get_account_notes_by_ckey(caller, ckey, server_id))
+        .await
+        .unwrap();
+
+    ResponseHelpers::from_api_result(result)
+}
@@ -1,13 +1,10 @@
-﻿use actix_web::{get, web, Responder};
+﻿use actix_web::{get, web, HttpRequest, Responder};
 use app_api::Api;
 use serde::Deserialize;
 
-use app_shared::{
-    models::{ApiCaller, Secret},
-    prelude::*,
-};
+use app_shared::{models::Secret, prelude::*};
 
-use crate::ResponseHelpers;
+use crate::{RequestHelpers, ResponseHelpers};
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Query {
@@ -18,7 +15,7 @@ pub struct Query {
 
 #[instrument]
 #[get("/byond/connect/byond")]
-pub async fn endpoint(query: web::Query<Query>) -> impl Responder {
+pub async fn endpoint(request: HttpRequest, query: web::Query<Query>) -> impl Responder {
     trace!("endpoint");
 
     let Query {
@@ -26,12 +23,11 @@ pub async fn endpoint(query: web::Query<Query>) -> impl Responder {
         ckey,
         tfa_secret,
     } = query.0;
+    let caller = RequestHelpers::api_caller(&request, secret);
 
-    let result = Api::lock_async(|api| {
-        api.connect_byond_account_by_2fa(ApiCaller::Token(secret), tfa_secret, ckey)
-    })
-    .await
-    .unwrap();
+    let result = Api::lock_async(|api| api.connect_byond_account_by_2fa(caller, tfa_secret, ckey))
+        .await
+        .unwrap();
 
     ResponseHelpers::from_api_result(result.map(|_| "ok"))
 }
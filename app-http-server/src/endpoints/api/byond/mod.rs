@@ -1,2 +1,3 @@
-﻿pub mod get_connect_byond;
+﻿pub mod get_account_notes;
+pub mod get_connect_byond;
 pub mod get_webhook;
@@ -0,0 +1,28 @@
+use actix_web::{delete, web, HttpRequest, Responder};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use app_api::Api;
+
+use crate::{RequestHelpers, ResponseHelpers};
+use app_shared::{
+    models::{AccountId, Secret},
+    prelude::*,
+};
+
+#[instrument]
+#[delete("/account/{account_id}")]
+pub async fn endpoint(
+    request: HttpRequest,
+    path: web::Path<i64>,
+    secret: BearerAuth,
+) -> impl Responder {
+    trace!("endpoint");
+
+    let account_id = AccountId(path.into_inner());
+    let caller = RequestHelpers::api_caller(&request, Secret(secret.token().to_string()));
+
+    let response = Api::lock_async(move |api| api.delete_account(caller, account_id))
+        .await
+        .unwrap();
+
+    ResponseHelpers::from_api_result(response)
+}
@@ -0,0 +1,28 @@
+use actix_web::{delete, web, HttpRequest, Responder};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use app_api::Api;
+
+use crate::{RequestHelpers, ResponseHelpers};
+use app_shared::{
+    models::{AccountNoteId, Secret},
+    prelude::*,
+};
+
+#[instrument]
+#[delete("/notes/{note_id}")]
+pub async fn endpoint(
+    request: HttpRequest,
+    path: web::Path<i64>,
+    secret: BearerAuth,
+) -> impl Responder {
+    trace!("endpoint");
+
+    let note_id = AccountNoteId(path.into_inner());
+    let caller = RequestHelpers::api_caller(&request, Secret(secret.token().to_string()));
+
+    let response = Api::lock_async(move |api| api.delete_account_note(caller, note_id))
+        .await
+        .unwrap();
+
+    ResponseHelpers::from_api_result(response)
+}
@@ -1,14 +1,11 @@
-﻿use actix_web::{delete, web, Responder};
+﻿use actix_web::{delete, web, HttpRequest, Responder};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
 use app_api::Api;
 use serde::Deserialize;
 
-use app_shared::{
-    models::{ApiCaller, Secret},
-    prelude::*,
-};
+use app_shared::{models::Secret, prelude::*};
 
-use crate::ResponseHelpers;
+use crate::{RequestHelpers, ResponseHelpers};
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Body {
@@ -17,16 +14,19 @@ pub struct Body {
 
 #[instrument]
 #[delete("/token")]
-pub async fn endpoint(body: web::Json<Body>, secret: BearerAuth) -> impl Responder {
+pub async fn endpoint(
+    request: HttpRequest,
+    body: web::Json<Body>,
+    secret: BearerAuth,
+) -> impl Responder {
     trace!("endpoint");
 
     let Body { target_secret } = body.0;
-    let secret = Secret(secret.token().to_string());
+    let caller = RequestHelpers::api_caller(&request, Secret(secret.token().to_string()));
 
-    let result =
-        Api::lock_async(|api| api.delete_api_token(ApiCaller::Token(secret), target_secret))
-            .await
-            .unwrap();
+    let result = Api::lock_async(|api| api.delete_api_token(caller, target_secret))
+        .await
+        .unwrap();
 
     ResponseHelpers::from_api_result(result)
 }
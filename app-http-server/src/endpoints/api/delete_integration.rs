@@ -0,0 +1,29 @@
+use actix_web::{delete, web, HttpRequest, Responder};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use app_api::Api;
+
+use crate::{RequestHelpers, ResponseHelpers};
+use app_shared::{
+    models::{AccountId, IntegrationKind, Secret},
+    prelude::*,
+};
+
+#[instrument]
+#[delete("/account/{account_id}/integrations/{kind}")]
+pub async fn endpoint(
+    request: HttpRequest,
+    path: web::Path<(i64, IntegrationKind)>,
+    secret: BearerAuth,
+) -> impl Responder {
+    trace!("endpoint");
+
+    let (account_id, kind) = path.into_inner();
+    let account_id = AccountId(account_id);
+    let caller = RequestHelpers::api_caller(&request, Secret(secret.token().to_string()));
+
+    let response = Api::lock_async(move |api| api.disconnect_integration(caller, account_id, kind))
+        .await
+        .unwrap();
+
+    ResponseHelpers::from_api_result(response)
+}
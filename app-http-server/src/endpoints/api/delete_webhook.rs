@@ -1,15 +1,12 @@
 ﻿use actix_web::web::Json;
-use actix_web::{delete, Responder};
+use actix_web::{delete, HttpRequest, Responder};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
 use serde::Deserialize;
 
 use app_api::Api;
-use app_shared::{
-    models::{ApiCaller, Secret},
-    prelude::*,
-};
+use app_shared::{models::Secret, prelude::*};
 
-use crate::ResponseHelpers;
+use crate::{RequestHelpers, ResponseHelpers};
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Body {
@@ -18,16 +15,19 @@ pub struct Body {
 
 #[instrument]
 #[delete("/webhook")]
-pub async fn endpoint(body: Json<Body>, secret: BearerAuth) -> impl Responder {
+pub async fn endpoint(
+    request: HttpRequest,
+    body: Json<Body>,
+    secret: BearerAuth,
+) -> impl Responder {
     trace!("endpoint");
 
     let Body { webhook_secret } = body.0;
-    let secret = Secret(secret.token().to_string());
+    let caller = RequestHelpers::api_caller(&request, Secret(secret.token().to_string()));
 
-    let result =
-        Api::lock_async(|api| api.delete_webhook(ApiCaller::Token(secret), webhook_secret))
-            .await
-            .unwrap();
+    let result = Api::lock_async(|api| api.delete_webhook(caller, webhook_secret))
+        .await
+        .unwrap();
 
     ResponseHelpers::from_api_result(result)
 }
@@ -0,0 +1,53 @@
+﻿use actix_web::{get, web::Query, HttpRequest, Responder};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use app_api::Api;
+use serde::{Deserialize, Serialize};
+
+use app_shared::{
+    models::{AccountSearch, DonationTierId, RoleId, Secret},
+    prelude::*,
+};
+
+use crate::{RequestHelpers, ResponseHelpers};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Payload {
+    pub query: Option<String>,
+    pub role_id: Option<RoleId>,
+    pub donation_tier_id: Option<DonationTierId>,
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+#[instrument]
+#[get("/accounts")]
+pub async fn endpoint(
+    request: HttpRequest,
+    payload: Query<Payload>,
+    secret: BearerAuth,
+) -> impl Responder {
+    trace!("endpoint");
+
+    let caller = RequestHelpers::api_caller(&request, Secret(secret.token().to_string()));
+    let Payload {
+        query,
+        role_id,
+        donation_tier_id,
+        offset,
+        limit,
+    } = payload.0;
+
+    let search = AccountSearch::new(
+        query,
+        role_id,
+        donation_tier_id,
+        offset.unwrap_or(0),
+        limit.unwrap_or(25),
+    );
+
+    let result = Api::lock_async(move |api| api.search_accounts(caller, search))
+        .await
+        .unwrap();
+
+    ResponseHelpers::from_api_result(result)
+}
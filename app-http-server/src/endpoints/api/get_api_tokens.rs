@@ -0,0 +1,21 @@
+﻿use actix_web::{get, HttpRequest, Responder};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use app_api::Api;
+
+use app_shared::{models::Secret, prelude::*};
+
+use crate::{RequestHelpers, ResponseHelpers};
+
+#[instrument]
+#[get("/tokens")]
+pub async fn endpoint(request: HttpRequest, secret: BearerAuth) -> impl Responder {
+    trace!("endpoint");
+
+    let caller = RequestHelpers::api_caller(&request, Secret(secret.token().to_string()));
+
+    let result = Api::lock_async(|api| api.get_caller_api_tokens(caller))
+        .await
+        .unwrap();
+
+    ResponseHelpers::from_api_result(result)
+}
@@ -0,0 +1,80 @@
+use actix_web::{get, web::Query, HttpRequest, Responder};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use app_api::Api;
+use serde::{Deserialize, Serialize};
+
+use app_shared::{
+    chrono::{DateTime, Utc},
+    models::{Actor, ApiError, JournalEntryId, JournalQuery, Secret},
+    prelude::*,
+};
+
+use crate::{RequestHelpers, ResponseHelpers};
+
+/// Количество записей на странице, если не указано.
+const DEFAULT_LIMIT: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Payload {
+    /// Кто действовал: `System`, `Deleted`, `User:1` или `Webhook:name`.
+    pub object: Option<String>,
+    /// Над кем действовали, в том же виде.
+    pub subject: Option<String>,
+    /// Тип действия, например `RoleAdded`.
+    pub action: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub before_id: Option<i64>,
+    pub limit: Option<usize>,
+}
+
+#[instrument]
+#[get("/journal")]
+pub async fn endpoint(
+    request: HttpRequest,
+    payload: Query<Payload>,
+    secret: BearerAuth,
+) -> impl Responder {
+    trace!("endpoint");
+
+    let caller = RequestHelpers::api_caller(&request, Secret(secret.token().to_string()));
+    let Payload {
+        object,
+        subject,
+        action,
+        from,
+        to,
+        before_id,
+        limit,
+    } = payload.0;
+
+    let (object, subject) = match (parse_actor(object), parse_actor(subject)) {
+        (Ok(object), Ok(subject)) => (object, subject),
+        (Err(err), _) | (_, Err(err)) => return ResponseHelpers::from_api_error(err),
+    };
+
+    let query = JournalQuery {
+        object,
+        subject,
+        action,
+        from,
+        to,
+        before_id: before_id.map(JournalEntryId),
+        ..JournalQuery::new(limit.unwrap_or(DEFAULT_LIMIT))
+    };
+
+    let response = Api::lock_async(move |api| api.query_journal(caller, query))
+        .await
+        .unwrap();
+
+    ResponseHelpers::from_api_result(response)
+}
+
+fn parse_actor(value: Option<String>) -> Result<Option<Actor>, ApiError> {
+    match value.filter(|value| !value.trim().is_empty()) {
+        Some(value) => Actor::parse(&value)
+            .map(Some)
+            .ok_or_else(|| ApiError::Other(format!("Некорректный актор: {value}"))),
+        None => Ok(None),
+    }
+}
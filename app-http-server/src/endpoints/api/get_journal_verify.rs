@@ -0,0 +1,20 @@
+use actix_web::{get, HttpRequest, Responder};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use app_api::Api;
+
+use crate::{RequestHelpers, ResponseHelpers};
+use app_shared::{models::Secret, prelude::*};
+
+#[instrument]
+#[get("/journal/verify")]
+pub async fn endpoint(request: HttpRequest, secret: BearerAuth) -> impl Responder {
+    trace!("endpoint");
+
+    let caller = RequestHelpers::api_caller(&request, Secret(secret.token().to_string()));
+
+    let response = Api::lock_async(move |api| api.verify_journal_chain(caller))
+        .await
+        .unwrap();
+
+    ResponseHelpers::from_api_result(response)
+}
@@ -2,36 +2,67 @@ use actix_web::Scope;
 
 mod account;
 mod byond;
+mod delete_account;
+mod delete_account_note;
 mod delete_api_token;
+mod delete_integration;
 mod delete_webhook;
 mod donations;
+mod get_account_export;
+mod get_account_notes;
+mod get_accounts;
+mod get_api_tokens;
 mod get_identity;
+mod get_journal;
+mod get_journal_verify;
+mod post_add_account_note;
 mod post_add_account_role;
 mod post_auth;
+mod post_auth_mfa;
+mod post_change_username;
 mod post_connect_byond;
 mod post_connect_ss14;
 mod post_create_api_token;
 mod post_create_webhook;
+mod post_github_webhook;
+mod post_merge_accounts;
+mod post_relink_integration;
 mod post_webhook;
 
 pub fn scope() -> Scope {
     actix_web::web::scope("/api")
         // GET
         .service(get_identity::endpoint)
+        .service(get_api_tokens::endpoint)
+        .service(get_accounts::endpoint)
+        .service(get_account_export::endpoint)
+        .service(get_account_notes::endpoint)
+        .service(get_journal::endpoint)
+        .service(get_journal_verify::endpoint)
         // POST
         .service(post_connect_byond::endpoint)
         .service(post_connect_ss14::endpoint)
         .service(post_create_api_token::endpoint)
         .service(post_webhook::endpoint)
+        .service(post_github_webhook::endpoint)
         .service(post_create_webhook::endpoint)
         .service(post_add_account_role::endpoint)
+        .service(post_relink_integration::endpoint)
+        .service(post_merge_accounts::endpoint)
+        .service(post_change_username::endpoint)
+        .service(post_add_account_note::endpoint)
         .service(post_auth::endpoint)
+        .service(post_auth_mfa::endpoint)
         // DELETE
         .service(delete_api_token::endpoint)
         .service(delete_webhook::endpoint)
+        .service(delete_integration::endpoint)
+        .service(delete_account::endpoint)
+        .service(delete_account_note::endpoint)
         // BYOND-friendly (retarded) API
         .service(byond::get_connect_byond::endpoint)
         .service(byond::get_webhook::endpoint)
+        .service(byond::get_account_notes::endpoint)
         // /api/donations
         .service(donations::scope())
         // /api/account
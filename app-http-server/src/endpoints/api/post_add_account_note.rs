@@ -0,0 +1,49 @@
+use actix_web::{post, web, HttpRequest, Responder};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use app_api::Api;
+use serde::{Deserialize, Serialize};
+
+use crate::{RequestHelpers, ResponseHelpers};
+use app_shared::{
+    models::{AccountId, GameServerId, NoteSeverity, Secret},
+    prelude::*,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Body {
+    pub text: String,
+    pub severity: NoteSeverity,
+    /// Сервер, к которому относится заметка, если не указан - ко всем.
+    pub server_id: Option<GameServerId>,
+    /// Показывать ли заметку в выгрузке данных пользователя.
+    #[serde(default)]
+    pub shareable: bool,
+}
+
+#[instrument]
+#[post("/account/{account_id}/notes")]
+pub async fn endpoint(
+    request: HttpRequest,
+    account_id: web::Path<i64>,
+    body: web::Json<Body>,
+    secret: BearerAuth,
+) -> impl Responder {
+    trace!("endpoint");
+
+    let account_id = AccountId(account_id.into_inner());
+    let caller = RequestHelpers::api_caller(&request, Secret(secret.token().to_string()));
+    let Body {
+        text,
+        severity,
+        server_id,
+        shareable,
+    } = body.0;
+
+    let response = Api::lock_async(move |api| {
+        api.add_account_note(caller, account_id, severity, server_id, text, shareable)
+    })
+    .await
+    .unwrap();
+
+    ResponseHelpers::from_api_result(response)
+}
@@ -1,11 +1,11 @@
-﻿use actix_web::{post, web, Responder};
+﻿use actix_web::{post, web, HttpRequest, Responder};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
 use app_api::Api;
 use serde::{Deserialize, Serialize};
 
-use crate::ResponseHelpers;
+use crate::{RequestHelpers, ResponseHelpers};
 use app_shared::{
-    models::{AccountId, ApiCaller, RoleId, Secret},
+    models::{AccountId, RoleId, Secret},
     prelude::*,
 };
 
@@ -17,6 +17,7 @@ pub struct Body {
 #[instrument]
 #[post("/account/{account_id}/roles")]
 pub async fn endpoint(
+    request: HttpRequest,
     account_id: web::Path<i64>,
     body: web::Json<Body>,
     secret: BearerAuth,
@@ -24,14 +25,12 @@ pub async fn endpoint(
     trace!("endpoint");
 
     let account_id = AccountId(account_id.into_inner());
-    let secret = Secret(secret.token().to_string());
+    let caller = RequestHelpers::api_caller(&request, Secret(secret.token().to_string()));
     let Body { role_id } = body.0;
 
-    let response = Api::lock_async(move |api| {
-        api.add_role_to_account(ApiCaller::Token(secret), account_id, role_id)
-    })
-    .await
-    .unwrap();
+    let response = Api::lock_async(move |api| api.add_role_to_account(caller, account_id, role_id))
+        .await
+        .unwrap();
 
     ResponseHelpers::from_api_result(response)
 }
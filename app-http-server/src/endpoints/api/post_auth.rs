@@ -5,12 +5,14 @@ use actix_web::{post, HttpRequest, HttpResponseBuilder, Responder};
 use serde::{Deserialize, Serialize};
 
 use crate::constants::COOKIES_SESSION_KEY;
-use crate::cookies::SessionCookie;
+use crate::cookies::{MfaLoginCookie, SessionCookie};
 use crate::response::ResponseHelpers;
+use crate::RequestHelpers;
 use app_api::Api;
 use app_shared::{
-    models::{ApiError, Secret, Session},
+    models::{ApiError, Login, Secret},
     prelude::*,
+    serde_json::json,
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,9 +32,7 @@ pub async fn endpoint(request: HttpRequest, form: Json<Payload>) -> impl Respond
         .unwrap();
     };
 
-    let Some(user_agent) = request
-        .headers()
-        .get(header::USER_AGENT) else {
+    let Some(user_agent) = request.headers().get(header::USER_AGENT) else {
         return ResponseHelpers::new(StatusCode::BAD_REQUEST, "Пустой User Agent");
     };
 
@@ -40,22 +40,21 @@ pub async fn endpoint(request: HttpRequest, form: Json<Payload>) -> impl Respond
         return ResponseHelpers::new(StatusCode::BAD_REQUEST, "Некорректный User agent");
     };
 
-    let ip = request
-        .connection_info()
-        .realip_remote_addr()
-        .map(|ip| ip.to_string())
-        .unwrap_or_else(String::new);
+    let ip = RequestHelpers::ip(&request);
 
     let tfa = form.0.tfa_secret;
-    let session: Result<Session, ApiError> =
+    let login: Result<Login, ApiError> =
         Api::lock_async(|api| api.create_session_by_tfa(tfa, user_agent, ip))
             .await
             .unwrap();
 
-    match session {
+    match login {
         Err(err) => ResponseHelpers::from_api_error(err),
-        Ok(session) => HttpResponseBuilder::new(StatusCode::OK)
-            .cookie(SessionCookie::from_session(session))
+        Ok(Login::Completed(session, secrets)) => HttpResponseBuilder::new(StatusCode::OK)
+            .cookie(SessionCookie::from_session(*session, secrets))
             .finish(),
+        Ok(Login::MfaRequired(login_secret)) => HttpResponseBuilder::new(StatusCode::OK)
+            .cookie(MfaLoginCookie::from_secret(login_secret))
+            .json(json!({ "mfa_required": true })),
     }
 }
@@ -0,0 +1,68 @@
+use actix_http::StatusCode;
+use actix_web::http::header;
+use actix_web::web::Json;
+use actix_web::{post, HttpRequest, HttpResponseBuilder, Responder};
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{COOKIES_MFA_LOGIN_KEY, COOKIES_SESSION_KEY};
+use crate::cookies::{MfaLoginCookie, SessionCookie};
+use crate::response::ResponseHelpers;
+use crate::RequestHelpers;
+use app_api::Api;
+use app_shared::{models::Secret, prelude::*};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Payload {
+    pub code: String,
+}
+
+/// Завершает вход кодом второго фактора.
+#[post("/auth/mfa")]
+pub async fn endpoint(request: HttpRequest, form: Json<Payload>) -> impl Responder {
+    trace!("endpoint");
+
+    let Some(login_secret) = request
+        .cookie(COOKIES_MFA_LOGIN_KEY)
+        .map(|cookie| Secret(cookie.value().to_string()))
+    else {
+        return ResponseHelpers::new(
+            StatusCode::BAD_REQUEST,
+            "Время на ввод кода истекло, войдите заново",
+        );
+    };
+
+    let Some(user_agent) = request
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|user_agent| user_agent.to_str().ok())
+        .map(|user_agent| user_agent.to_string())
+    else {
+        return ResponseHelpers::new(StatusCode::BAD_REQUEST, "Некорректный User agent");
+    };
+
+    let ip = RequestHelpers::ip(&request);
+    let code = form.0.code;
+
+    let result =
+        Api::lock_async(move |api| api.complete_mfa_login(login_secret, code, user_agent, ip))
+            .await
+            .unwrap();
+
+    let (session, secrets) = match result {
+        Ok(result) => result,
+        Err(err) => return ResponseHelpers::from_api_error(err),
+    };
+
+    if let Some(cookie) = request.cookie(COOKIES_SESSION_KEY) {
+        Api::lock_async(move |api| {
+            api.delete_session(Secret(cookie.value().to_string())).ok();
+        })
+        .await
+        .unwrap();
+    };
+
+    HttpResponseBuilder::new(StatusCode::OK)
+        .cookie(SessionCookie::from_session(session, secrets))
+        .cookie(MfaLoginCookie::removal())
+        .finish()
+}
@@ -1,14 +1,11 @@
-﻿use actix_web::{post, web, Responder};
+﻿use actix_web::{post, web, HttpRequest, Responder};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
 use app_api::Api;
 use serde::Deserialize;
 
-use app_shared::{
-    models::{ApiCaller, Secret},
-    prelude::*,
-};
+use app_shared::{models::Secret, prelude::*};
 
-use crate::ResponseHelpers;
+use crate::{RequestHelpers, ResponseHelpers};
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Body {
@@ -18,17 +15,19 @@ pub struct Body {
 
 #[instrument]
 #[post("/connect/byond")]
-pub async fn endpoint(query: web::Json<Body>, secret: BearerAuth) -> impl Responder {
+pub async fn endpoint(
+    request: HttpRequest,
+    query: web::Json<Body>,
+    secret: BearerAuth,
+) -> impl Responder {
     trace!("endpoint");
 
     let Body { ckey, tfa_secret } = query.0;
-    let secret = Secret(secret.token().to_string());
+    let caller = RequestHelpers::api_caller(&request, Secret(secret.token().to_string()));
 
-    let result = Api::lock_async(|api| {
-        api.connect_byond_account_by_2fa(ApiCaller::Token(secret), tfa_secret, ckey)
-    })
-    .await
-    .unwrap();
+    let result = Api::lock_async(|api| api.connect_byond_account_by_2fa(caller, tfa_secret, ckey))
+        .await
+        .unwrap();
 
     ResponseHelpers::from_api_result(result)
 }
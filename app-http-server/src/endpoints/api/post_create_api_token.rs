@@ -1,18 +1,20 @@
-﻿use actix_web::{post, web, Responder};
+﻿use actix_web::{post, web, HttpRequest, Responder};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
 use app_api::Api;
 use serde::Deserialize;
 
 use app_shared::{
     chrono::{DateTime, Utc},
-    models::{ApiCaller, Rights, Secret},
+    models::{Rights, Secret},
     prelude::*,
 };
 
-use crate::ResponseHelpers;
+use crate::{RequestHelpers, ResponseHelpers};
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Body {
+    #[serde(default)]
+    label: String,
     rights: Rights,
     expiration: Option<DateTime<Utc>>,
     is_service: bool,
@@ -20,20 +22,25 @@ pub struct Body {
 
 #[instrument]
 #[post("/token")]
-pub async fn endpoint(body: web::Json<Body>, secret: BearerAuth) -> impl Responder {
+pub async fn endpoint(
+    request: HttpRequest,
+    body: web::Json<Body>,
+    secret: BearerAuth,
+) -> impl Responder {
     trace!("endpoint");
 
     let Body {
+        label,
         rights,
         expiration,
         is_service,
     } = body.0;
-    let secret = Secret(secret.token().to_string());
+    let caller = RequestHelpers::api_caller(&request, Secret(secret.token().to_string()));
 
     let result = Api::lock_async(move |api| {
         let duration = expiration.map(|expiration| expiration - Utc::now());
 
-        api.create_api_token(ApiCaller::Token(secret), rights, duration, is_service)
+        api.create_api_token(caller, label, rights, duration, is_service)
     })
     .await
     .unwrap();
@@ -1,15 +1,15 @@
 ﻿use actix_web::web::Json;
-use actix_web::{post, Responder};
+use actix_web::{post, HttpRequest, Responder};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
 use app_api::Api;
 use serde::Deserialize;
 
 use app_shared::{
-    models::{ApiCaller, Secret, ServiceId, WebhookConfiguration},
+    models::{Secret, ServiceId, WebhookConfiguration},
     prelude::*,
 };
 
-use crate::ResponseHelpers;
+use crate::{RequestHelpers, ResponseHelpers};
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Body {
@@ -20,7 +20,11 @@ pub struct Body {
 
 #[instrument]
 #[post("/webhook")]
-pub async fn endpoint(body: Json<Body>, secret: BearerAuth) -> impl Responder {
+pub async fn endpoint(
+    request: HttpRequest,
+    body: Json<Body>,
+    secret: BearerAuth,
+) -> impl Responder {
     trace!("endpoint");
 
     let Body {
@@ -28,15 +32,10 @@ pub async fn endpoint(body: Json<Body>, secret: BearerAuth) -> impl Responder {
         name,
         configuration,
     } = body.0;
-    let secret = Secret(secret.token().to_string());
+    let caller = RequestHelpers::api_caller(&request, Secret(secret.token().to_string()));
 
     let result = Api::lock_async(|api| {
-        api.create_webhook(
-            ApiCaller::Token(secret),
-            service_id,
-            name,
-            configuration.unwrap_or_default(),
-        )
+        api.create_webhook(caller, service_id, name, configuration.unwrap_or_default())
     })
     .await
     .unwrap();
@@ -0,0 +1,30 @@
+use actix_web::{post, web::Bytes, HttpRequest, Responder};
+use app_api::Api;
+
+use crate::ResponseHelpers;
+use app_shared::prelude::*;
+
+#[instrument(skip(body))]
+#[post("/github/webhook")]
+pub async fn endpoint(request: HttpRequest, body: Bytes) -> impl Responder {
+    trace!("endpoint");
+
+    let header = |name: &str| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string()
+    };
+
+    let event = header("X-GitHub-Event");
+    let signature = header("X-Hub-Signature-256");
+    let body = body.to_vec();
+
+    let response = Api::lock_async(move |api| api.handle_github_webhook(event, signature, body))
+        .await
+        .unwrap();
+
+    ResponseHelpers::from_api_result(response)
+}
@@ -0,0 +1,37 @@
+use actix_web::{post, web, HttpRequest, Responder};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use app_api::Api;
+use serde::{Deserialize, Serialize};
+
+use crate::{RequestHelpers, ResponseHelpers};
+use app_shared::{
+    models::{AccountId, Secret},
+    prelude::*,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Body {
+    /// Аккаунт-дубликат, который будет объединён в аккаунт из пути.
+    pub source_id: AccountId,
+}
+
+#[instrument]
+#[post("/account/{account_id}/merge")]
+pub async fn endpoint(
+    request: HttpRequest,
+    account_id: web::Path<i64>,
+    body: web::Json<Body>,
+    secret: BearerAuth,
+) -> impl Responder {
+    trace!("endpoint");
+
+    let target_id = AccountId(account_id.into_inner());
+    let caller = RequestHelpers::api_caller(&request, Secret(secret.token().to_string()));
+    let Body { source_id } = body.0;
+
+    let response = Api::lock_async(move |api| api.merge_accounts(caller, source_id, target_id))
+        .await
+        .unwrap();
+
+    ResponseHelpers::from_api_result(response)
+}
@@ -0,0 +1,38 @@
+﻿use actix_web::{post, web, HttpRequest, Responder};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use app_api::Api;
+use serde::{Deserialize, Serialize};
+
+use crate::{RequestHelpers, ResponseHelpers};
+use app_shared::{
+    models::{AccountId, AnyUserId, Secret},
+    prelude::*,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Body {
+    pub integration_id: AnyUserId,
+}
+
+#[instrument]
+#[post("/account/{account_id}/integrations")]
+pub async fn endpoint(
+    request: HttpRequest,
+    account_id: web::Path<i64>,
+    body: web::Json<Body>,
+    secret: BearerAuth,
+) -> impl Responder {
+    trace!("endpoint");
+
+    let account_id = AccountId(account_id.into_inner());
+    let caller = RequestHelpers::api_caller(&request, Secret(secret.token().to_string()));
+    let Body { integration_id } = body.0;
+
+    let response = Api::lock_async(move |api| {
+        api.force_relink_integration(caller, account_id, integration_id)
+    })
+    .await
+    .unwrap();
+
+    ResponseHelpers::from_api_result(response)
+}
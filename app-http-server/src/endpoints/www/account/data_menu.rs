@@ -0,0 +1,151 @@
+use actix_http::{header, Method, StatusCode};
+use actix_web::{
+    cookie::Cookie, get, routes, web::Form, HttpRequest, HttpResponse, HttpResponseBuilder,
+};
+use serde::{Deserialize, Serialize};
+
+use app_api::Api;
+use app_shared::{models::Secret, prelude::*};
+
+use crate::constants::COOKIES_SESSION_KEY;
+use crate::response::ResponseHelpers;
+use crate::{extractors::AuthenticatedUser, html_response::HtmlResponse, RequestHelpers};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataForm {
+    pub csrf_token: Secret,
+    /// Имя пользователя для подтверждения удаления.
+    pub username: String,
+    /// Код второго фактора, если он подключён.
+    #[serde(default)]
+    pub mfa_code: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RenderContext {
+    pub user: AuthenticatedUser,
+    pub form: DataForm,
+    pub mfa_enabled: bool,
+    pub errors: Vec<String>,
+}
+
+/// Удаляет аккаунт, возвращает `true` при успехе.
+#[instrument]
+async fn handle(request: &HttpRequest, context: &mut RenderContext, form: DataForm) -> bool {
+    trace!("handle");
+
+    if form.username.trim() != context.user.account.username {
+        context
+            .errors
+            .push("Имя пользователя не совпадает".to_string());
+        return false;
+    }
+
+    let account_id = context.user.account.id;
+    let caller = RequestHelpers::api_caller(request, context.user.secrets.api_secret.clone());
+    let ip = RequestHelpers::ip(request);
+    let result = Api::lock_async(move |api| {
+        api.check_mfa_code(account_id, form.mfa_code, Some(ip))?;
+        api.delete_account(caller, account_id)
+    })
+    .await
+    .unwrap();
+
+    match result {
+        Ok(_) => true,
+        Err(err) => {
+            context.errors.push(err.to_string());
+            false
+        }
+    }
+}
+
+#[instrument]
+async fn context(user: &AuthenticatedUser) -> RenderContext {
+    trace!("context");
+
+    let account_id = user.account.id;
+    let mfa_enabled = Api::lock_async(move |api| api.is_mfa_enabled(account_id))
+        .await
+        .unwrap();
+
+    RenderContext {
+        user: user.clone(),
+        form: DataForm {
+            csrf_token: user.secrets.csrf_token.clone(),
+            username: String::new(),
+            mfa_code: None,
+        },
+        mfa_enabled,
+        errors: Vec::new(),
+    }
+}
+
+#[instrument]
+async fn render(context: RenderContext) -> HttpResponse {
+    trace!("render");
+
+    HtmlResponse::from_template("account/data.html", Some(context)).await
+}
+
+#[instrument]
+#[routes]
+#[get("/data")]
+#[post("/data")]
+pub async fn endpoint(
+    request: HttpRequest,
+    user: AuthenticatedUser,
+    form: Option<Form<DataForm>>,
+) -> HttpResponse {
+    trace!("endpoint");
+
+    let form = form.map(|map| map.0);
+    let mut ctx = context(&user).await;
+
+    if let Some(form) = form {
+        if request.method() == Method::POST {
+            let csrf_token = form.csrf_token.clone();
+
+            if !Api::lock_async(move |api| api.is_csrf_secret_valid(csrf_token))
+                .await
+                .unwrap()
+            {
+                return ResponseHelpers::new(StatusCode::BAD_REQUEST, "Некорректный CSRF токен.");
+            }
+
+            if handle(&request, &mut ctx, form).await {
+                let mut cookie = Cookie::new(COOKIES_SESSION_KEY, "");
+                cookie.make_removal();
+
+                return HttpResponseBuilder::new(StatusCode::SEE_OTHER)
+                    .insert_header((header::LOCATION, "/"))
+                    .cookie(cookie)
+                    .finish();
+            }
+        }
+    }
+
+    render(ctx).await
+}
+
+#[instrument]
+#[get("/data/export")]
+pub async fn export_endpoint(request: HttpRequest, user: AuthenticatedUser) -> HttpResponse {
+    trace!("export_endpoint");
+
+    let account_id = user.account.id;
+    let caller = RequestHelpers::api_caller(&request, user.secrets.api_secret.clone());
+    let result = Api::lock_async(move |api| api.export_account(caller, account_id))
+        .await
+        .unwrap();
+
+    match result {
+        Ok(export) => HttpResponseBuilder::new(StatusCode::OK)
+            .insert_header((
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"account-{}.json\"", account_id.0),
+            ))
+            .json(export),
+        Err(err) => ResponseHelpers::from_api_error(err),
+    }
+}
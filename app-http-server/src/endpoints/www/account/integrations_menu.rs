@@ -1,30 +1,159 @@
-use crate::{extractors::AuthenticatedUser, html_response::HtmlResponse};
-use actix_web::{get, HttpRequest, Responder};
-use app_shared::prelude::*;
+use actix_http::{Method, StatusCode};
+use actix_web::{routes, web::Form, HttpRequest, HttpResponse};
 use serde::{Deserialize, Serialize};
 
+use app_api::Api;
+use app_shared::{
+    models::{AnyUserId, ApiError, GithubLinkCode, IntegrationKind, IntegrationLink, Secret},
+    prelude::*,
+};
+
+use crate::response::ResponseHelpers;
+use crate::{extractors::AuthenticatedUser, html_response::HtmlResponse, RequestHelpers};
+
+/// Шаг привязки GitHub аккаунта.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum GithubLinkStep {
+    Start,
+    Finish,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrationsForm {
+    pub csrf_token: Secret,
+    pub kind: Option<IntegrationKind>,
+    pub github: Option<GithubLinkStep>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct RenderContext {
     pub user: AuthenticatedUser,
+    pub links: Vec<IntegrationLink>,
+    pub github_code: Option<GithubLinkCode>,
+    pub form: IntegrationsForm,
+    pub errors: Vec<String>,
+}
+
+#[instrument]
+async fn handle(request: &HttpRequest, context: &mut RenderContext, form: IntegrationsForm) {
+    trace!("handle");
+
+    let account_id = context.user.account.id;
+    let caller = RequestHelpers::api_caller(request, context.user.secrets.api_secret.clone());
+
+    let result = match (form.kind, form.github) {
+        (Some(kind), _) => {
+            Api::lock_async(move |api| api.disconnect_integration(caller, account_id, kind))
+                .await
+                .unwrap()
+        }
+        (None, Some(GithubLinkStep::Start)) => {
+            Api::lock_async(move |api| api.start_github_link(caller, account_id))
+                .await
+                .unwrap()
+                .map(|code| context.github_code = Some(code))
+        }
+        (None, Some(GithubLinkStep::Finish)) => {
+            match Api::lock_async(move |api| api.finish_github_link(caller, account_id))
+                .await
+                .unwrap()
+            {
+                Ok(false) => Err(ApiError::Other(
+                    "GitHub ещё не подтвердил код, попробуйте через несколько секунд".to_string(),
+                )),
+                Ok(true) => {
+                    context.github_code = None;
+                    Ok(())
+                }
+                Err(err) => Err(err),
+            }
+        }
+        (None, None) => return,
+    };
+
+    if let Err(err) = result {
+        context.errors.push(err.to_string());
+        return;
+    }
+
+    let (integrations, links) = Api::lock_async(move |api| {
+        (
+            api.find_integrations_by_account_id(AnyUserId::AccountId(account_id)),
+            api.get_integration_links(account_id),
+        )
+    })
+    .await
+    .unwrap();
+
+    if let Ok(integrations) = integrations {
+        context.user.account.integrations = integrations;
+    }
+
+    context.links = links;
 }
 
-fn context(user: &AuthenticatedUser) -> RenderContext {
-    RenderContext { user: user.clone() }
+#[instrument]
+async fn context(user: &AuthenticatedUser) -> RenderContext {
+    trace!("context");
+
+    let account_id = user.account.id;
+    let (links, github_code) = Api::lock_async(move |api| {
+        (
+            api.get_integration_links(account_id),
+            api.find_github_link_code(account_id),
+        )
+    })
+    .await
+    .unwrap();
+
+    RenderContext {
+        user: user.clone(),
+        links,
+        github_code,
+        form: IntegrationsForm {
+            csrf_token: user.secrets.csrf_token.clone(),
+            kind: None,
+            github: None,
+        },
+        errors: Vec::new(),
+    }
 }
 
 #[instrument]
-async fn render(context: RenderContext) -> impl Responder {
+async fn render(context: RenderContext) -> HttpResponse {
     trace!("render");
 
-    return HtmlResponse::from_template("account/integrations.html", Some(context)).await;
+    HtmlResponse::from_template("account/integrations.html", Some(context)).await
 }
 
 #[instrument]
+#[routes]
 #[get("/integrations")]
-pub async fn endpoint(request: HttpRequest, user: AuthenticatedUser) -> impl Responder {
+#[post("/integrations")]
+pub async fn endpoint(
+    request: HttpRequest,
+    user: AuthenticatedUser,
+    form: Option<Form<IntegrationsForm>>,
+) -> HttpResponse {
     trace!("endpoint");
 
-    let ctx = context(&user);
+    let form = form.map(|map| map.0);
+    let mut ctx = context(&user).await;
+
+    if let Some(form) = form {
+        if request.method() == Method::POST {
+            let csrf_token = form.csrf_token.clone();
+
+            if !Api::lock_async(move |api| api.is_csrf_secret_valid(csrf_token))
+                .await
+                .unwrap()
+            {
+                return ResponseHelpers::new(StatusCode::BAD_REQUEST, "Некорректный CSRF токен.");
+            }
+
+            handle(&request, &mut ctx, form).await;
+        }
+    }
 
     render(ctx).await
 }
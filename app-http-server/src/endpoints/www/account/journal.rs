@@ -23,11 +23,14 @@ pub struct PaginationQuery {
 }
 
 async fn context(user: &AuthenticatedUser, pagination: PaginationQuery) -> RenderContext {
-    let empty_cursor = JournalEntryCursor::new(
-        pagination.offset.unwrap_or(0),
-        Some(Actor::User(user.account.id)),
-        10,
-    );
+    let empty_cursor = JournalEntryCursor {
+        shareable_only: true,
+        ..JournalEntryCursor::new(
+            pagination.offset.unwrap_or(0),
+            Some(Actor::User(user.account.id)),
+            10,
+        )
+    };
 
     let cursor = empty_cursor.clone();
     let (cursor, roles, accounts) = Api::lock_async(move |api| {
@@ -39,8 +42,19 @@ async fn context(user: &AuthenticatedUser, pagination: PaginationQuery) -> Rende
             .map(|role| (role.id, role))
             .collect();
 
+        let account_ids = cursor
+            .iter()
+            .flat_map(|cursor| &cursor.entries)
+            .flat_map(|entry| [Some(&entry.object), entry.subject.as_ref()])
+            .filter_map(|actor| match actor {
+                Some(Actor::User(account_id)) => Some(*account_id),
+                _ => None,
+            })
+            .unique()
+            .collect();
+
         let accounts = api
-            .get_accounts()
+            .find_accounts_by_ids(account_ids)
             .into_iter()
             .map(|account| (account.id, account))
             .collect();
@@ -1,10 +1,13 @@
 use actix_http::{header, StatusCode};
 use actix_web::{web, HttpResponseBuilder, Responder, Scope};
 
+mod data_menu;
 mod integrations_menu;
 mod journal;
 mod profile_menu;
+mod security_menu;
 mod sessions_menu;
+mod tokens_menu;
 
 async fn redirect() -> impl Responder {
     HttpResponseBuilder::new(StatusCode::TEMPORARY_REDIRECT)
@@ -15,8 +18,13 @@ async fn redirect() -> impl Responder {
 pub fn endpoint() -> Scope {
     Scope::new("/account")
         .service(profile_menu::endpoint)
+        .service(profile_menu::avatar_endpoint)
         .service(sessions_menu::endpoint)
+        .service(security_menu::endpoint)
+        .service(tokens_menu::endpoint)
         .service(integrations_menu::endpoint)
         .service(journal::endpoint)
+        .service(data_menu::endpoint)
+        .service(data_menu::export_endpoint)
         .default_service(web::to(redirect))
 }
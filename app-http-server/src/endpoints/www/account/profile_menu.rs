@@ -1,8 +1,11 @@
-use actix_http::{Method, StatusCode};
+use actix_http::{header, Method, StatusCode};
 
-use actix_web::{routes, web::Form, HttpRequest, HttpResponse};
+use actix_multipart::Multipart;
+use actix_web::{post, routes, web::Form, HttpRequest, HttpResponse, HttpResponseBuilder};
 use app_shared::{
-    models::{AnyUserId, Secret},
+    chrono::{DateTime, Utc},
+    futures_util::StreamExt,
+    models::{AccountId, AnyUserId, ApiError, ProfileVisibility, Secret, UsernameChange},
     prelude::*,
 };
 
@@ -10,7 +13,7 @@ use crate::{
     extractors::AuthenticatedUser, html_response::HtmlResponse, response::ResponseHelpers,
     FormErrors,
 };
-use app_api::Api;
+use app_api::{Api, PendingAvatar};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,13 +21,50 @@ pub struct ProfileForm {
     pub username: String,
     pub avatar_url: String,
     pub csrf_token: Secret,
+    /// Нажата кнопка синхронизации аватарки с Discord.
+    #[serde(default)]
+    pub sync_discord_avatar: Option<String>,
+    #[serde(default)]
+    pub show_main_role: bool,
+    #[serde(default)]
+    pub show_donation_tier: bool,
+    #[serde(default)]
+    pub show_joined_at: bool,
+    #[serde(default)]
+    pub show_byond: bool,
+    #[serde(default)]
+    pub show_ss14: bool,
+    #[serde(default)]
+    pub show_github: bool,
 }
 
+impl ProfileForm {
+    fn visibility(&self) -> ProfileVisibility {
+        [
+            (self.show_main_role, ProfileVisibility::MAIN_ROLE),
+            (self.show_donation_tier, ProfileVisibility::DONATION_TIER),
+            (self.show_joined_at, ProfileVisibility::JOINED_AT),
+            (self.show_byond, ProfileVisibility::BYOND),
+            (self.show_ss14, ProfileVisibility::SS14),
+            (self.show_github, ProfileVisibility::GITHUB),
+        ]
+        .into_iter()
+        .filter(|(show, _)| *show)
+        .fold(ProfileVisibility::empty(), |acc, (_, field)| acc | field)
+    }
+}
+
+/// Наибольший размер загружаемой формы с аватаркой, остальное проверяет API.
+const MAX_AVATAR_UPLOAD_SIZE: usize = 16 * 1024 * 1024;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct RenderContext {
     pub user: AuthenticatedUser,
     pub form: ProfileForm,
     pub errors: FormErrors,
+    pub username_history: Vec<UsernameChange>,
+    /// Когда можно будет снова сменить имя, `None` - уже можно.
+    pub username_available_at: Option<DateTime<Utc>>,
 }
 
 #[instrument]
@@ -45,6 +85,21 @@ async fn handle(context: &mut RenderContext) {
             .push("Некорректный CSRF токен".to_string())
     }
 
+    let new_visibility = form.visibility();
+    if user.account.profile_visibility != new_visibility {
+        if let Err(err) = Api::lock_async(move |api| {
+            api.change_profile_visibility(AnyUserId::AccountId(user.account.id), new_visibility)
+        })
+        .await
+        .unwrap()
+        {
+            errors
+                .entry("visibility".to_string())
+                .or_default()
+                .push(err.to_string());
+        }
+    }
+
     let new_username = form.username.clone();
     if user.account.username != new_username {
         if let Err(err) = Api::lock_async(move |api| {
@@ -60,14 +115,47 @@ async fn handle(context: &mut RenderContext) {
         }
     }
 
+    let account_id = user.account.id;
+
+    if form.sync_discord_avatar.is_some() {
+        let result = async {
+            let avatar = Api::lock_async(move |api| {
+                api.sync_discord_avatar(AnyUserId::AccountId(account_id))
+            })
+            .await
+            .unwrap()?;
+
+            store_avatar(account_id, avatar).await
+        }
+        .await;
+
+        if let Err(err) = result {
+            errors
+                .entry("avatar_url".to_string())
+                .or_default()
+                .push(err.to_string());
+        }
+
+        return;
+    }
+
     let new_avatar_url = form.avatar_url.clone();
     if user.account.avatar_url != form.avatar_url {
-        if let Err(err) = Api::lock_async(move |api| {
-            api.change_avatar_url(AnyUserId::AccountId(user.account.id), new_avatar_url)
-        })
-        .await
-        .unwrap()
-        {
+        let result = async {
+            let avatar = Api::lock_async(move |api| {
+                api.change_avatar_url(AnyUserId::AccountId(account_id), new_avatar_url)
+            })
+            .await
+            .unwrap()?;
+
+            match avatar {
+                Some(avatar) => store_avatar(account_id, avatar).await,
+                None => Ok(()),
+            }
+        }
+        .await;
+
+        if let Err(err) = result {
             errors
                 .entry("avatar_url".to_string())
                 .or_default()
@@ -76,27 +164,58 @@ async fn handle(context: &mut RenderContext) {
     }
 }
 
+/// Скачивает и обрабатывает аватарку без блокировки Api, затем ставит её аккаунту.
+async fn store_avatar(account_id: AccountId, avatar: PendingAvatar) -> Result<(), ApiError> {
+    let new_avatar_url = avatar.store().await?;
+
+    Api::lock_async(move |api| {
+        api.finish_avatar_change(AnyUserId::AccountId(account_id), new_avatar_url)
+    })
+    .await
+    .unwrap()
+}
+
 #[instrument]
 fn context(user: &AuthenticatedUser, form: Option<&ProfileForm>) -> RenderContext {
     trace!("context");
 
+    let visibility = user.account.profile_visibility;
     let form = form.cloned().unwrap_or_else(|| ProfileForm {
         username: user.account.username.clone(),
         avatar_url: user.account.avatar_url.clone(),
-        csrf_token: user.session.csrf_token.clone(),
+        csrf_token: user.secrets.csrf_token.clone(),
+        sync_discord_avatar: None,
+        show_main_role: visibility.contains(ProfileVisibility::MAIN_ROLE),
+        show_donation_tier: visibility.contains(ProfileVisibility::DONATION_TIER),
+        show_joined_at: visibility.contains(ProfileVisibility::JOINED_AT),
+        show_byond: visibility.contains(ProfileVisibility::BYOND),
+        show_ss14: visibility.contains(ProfileVisibility::SS14),
+        show_github: visibility.contains(ProfileVisibility::GITHUB),
     });
 
     RenderContext {
         form,
         user: user.clone(),
         errors: FormErrors::default(),
+        username_history: Vec::new(),
+        username_available_at: None,
     }
 }
 
 #[instrument]
-async fn render(context: RenderContext) -> HttpResponse {
+async fn render(mut context: RenderContext) -> HttpResponse {
     trace!("render");
 
+    let account_id = context.user.account.id;
+    (context.username_history, context.username_available_at) = Api::lock_async(move |api| {
+        (
+            api.get_username_history(account_id),
+            api.get_username_change_available_at(account_id),
+        )
+    })
+    .await
+    .unwrap();
+
     return HtmlResponse::from_template("account/profile.html", Some(context)).await;
 }
 
@@ -129,3 +248,95 @@ pub async fn endpoint(
 
     render(ctx).await
 }
+
+/// Читает из формы CSRF токен и файл аватарки.
+#[instrument(skip(payload))]
+async fn read_avatar_form(mut payload: Multipart) -> Result<(Secret, Vec<u8>), String> {
+    trace!("read_avatar_form");
+
+    let mut csrf_token = None;
+    let mut image = None;
+    let mut total_size = 0;
+
+    while let Some(field) = payload.next().await {
+        let mut field = field.map_err(|err| err.to_string())?;
+        let name = field.name().unwrap_or_default().to_string();
+
+        if name == "avatar"
+            && field
+                .content_type()
+                .is_none_or(|mime| mime.type_() != "image")
+        {
+            return Err("Файл должен быть изображением".to_string());
+        }
+
+        let mut bytes = Vec::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk.map_err(|err| err.to_string())?;
+            total_size += chunk.len();
+
+            if total_size > MAX_AVATAR_UPLOAD_SIZE {
+                return Err("Файл слишком большой".to_string());
+            }
+
+            bytes.extend_from_slice(&chunk);
+        }
+
+        match name.as_str() {
+            "csrf_token" => csrf_token = Some(Secret(String::from_utf8_lossy(&bytes).to_string())),
+            "avatar" => image = Some(bytes),
+            _ => {}
+        }
+    }
+
+    match (csrf_token, image) {
+        (Some(csrf_token), Some(image)) => Ok((csrf_token, image)),
+        _ => Err("Файл не выбран".to_string()),
+    }
+}
+
+#[instrument(skip(payload))]
+#[post("/profile/avatar")]
+pub async fn avatar_endpoint(user: AuthenticatedUser, payload: Multipart) -> HttpResponse {
+    trace!("avatar_endpoint");
+
+    let mut ctx = context(&user, None);
+
+    let result = match read_avatar_form(payload).await {
+        Ok((csrf_token, image)) => {
+            if !Api::lock_async(move |api| api.is_csrf_secret_valid(csrf_token))
+                .await
+                .unwrap()
+            {
+                return ResponseHelpers::new(StatusCode::BAD_REQUEST, "Некорректный CSRF токен.");
+            }
+
+            let account_id = user.account.id;
+            let result = async {
+                let avatar = Api::lock_async(move |api| {
+                    api.upload_avatar(AnyUserId::AccountId(account_id), image)
+                })
+                .await
+                .unwrap()?;
+
+                store_avatar(account_id, avatar).await
+            }
+            .await;
+
+            result.map_err(|err| err.to_string())
+        }
+        Err(err) => Err(err),
+    };
+
+    if let Err(err) = result {
+        ctx.errors
+            .entry("avatar".to_string())
+            .or_default()
+            .push(err);
+        return render(ctx).await;
+    }
+
+    HttpResponseBuilder::new(StatusCode::SEE_OTHER)
+        .insert_header((header::LOCATION, "/account/profile"))
+        .finish()
+}
@@ -0,0 +1,143 @@
+use actix_http::{Method, StatusCode};
+use actix_web::{routes, web::Form, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use app_api::Api;
+use app_shared::{
+    models::{MfaStatus, Secret, TotpEnrollment},
+    prelude::*,
+};
+
+use crate::response::ResponseHelpers;
+use crate::{extractors::AuthenticatedUser, html_response::HtmlResponse, RequestHelpers};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecurityAction {
+    /// Начать подключение TOTP.
+    Start,
+    /// Подтвердить подключение первым кодом из приложения.
+    Confirm,
+    Disable,
+    /// Выпустить новые коды восстановления.
+    Regenerate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityForm {
+    pub csrf_token: Secret,
+    pub action: Option<SecurityAction>,
+    /// Код из приложения или код восстановления.
+    #[serde(default)]
+    pub code: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RenderContext {
+    pub user: AuthenticatedUser,
+    pub status: MfaStatus,
+    /// Начатое подключение, ждущее первый код.
+    pub enrollment: Option<TotpEnrollment>,
+    /// Новые коды восстановления, показываются один раз.
+    pub recovery_codes: Vec<String>,
+    pub form: SecurityForm,
+    pub errors: Vec<String>,
+}
+
+#[instrument]
+async fn handle(request: &HttpRequest, context: &mut RenderContext, form: SecurityForm) {
+    trace!("handle");
+
+    let Some(action) = form.action else {
+        return;
+    };
+
+    let session = context.user.session.clone();
+    let account_id = session.account_id;
+    let ip = RequestHelpers::ip(request);
+    let code = form.code;
+
+    let result = Api::lock_async(move |api| match action {
+        SecurityAction::Start => api.start_totp_enrollment(account_id).map(|_| Vec::new()),
+        SecurityAction::Confirm => api.confirm_totp_enrollment(session, code, ip),
+        SecurityAction::Disable => api.disable_totp(account_id, code, ip).map(|_| Vec::new()),
+        SecurityAction::Regenerate => api.regenerate_recovery_codes(account_id, code, ip),
+    })
+    .await
+    .unwrap();
+
+    match result {
+        Ok(recovery_codes) => context.recovery_codes = recovery_codes,
+        Err(err) => context.errors.push(err.to_string()),
+    }
+}
+
+#[instrument]
+fn context(user: &AuthenticatedUser) -> RenderContext {
+    trace!("context");
+
+    RenderContext {
+        user: user.clone(),
+        status: MfaStatus::default(),
+        enrollment: None,
+        recovery_codes: Vec::new(),
+        form: SecurityForm {
+            csrf_token: user.secrets.csrf_token.clone(),
+            action: None,
+            code: String::new(),
+        },
+        errors: Vec::new(),
+    }
+}
+
+#[instrument]
+async fn render(mut context: RenderContext) -> HttpResponse {
+    trace!("render");
+
+    let account_id = context.user.account.id;
+    let (status, enrollment) = Api::lock_async(move |api| {
+        (
+            api.get_mfa_status(account_id),
+            api.get_pending_totp_enrollment(account_id),
+        )
+    })
+    .await
+    .unwrap();
+
+    context.status = status;
+    context.enrollment = enrollment;
+
+    HtmlResponse::from_template("account/security.html", Some(context)).await
+}
+
+#[instrument]
+#[routes]
+#[get("/security")]
+#[post("/security")]
+pub async fn endpoint(
+    request: HttpRequest,
+    user: AuthenticatedUser,
+    form: Option<Form<SecurityForm>>,
+) -> HttpResponse {
+    trace!("endpoint");
+
+    let form = form.map(|map| map.0);
+    let mut ctx = context(&user);
+
+    if let Some(form) = form {
+        if request.method() == Method::POST {
+            let csrf_token = form.csrf_token.clone();
+
+            if !Api::lock_async(move |api| api.is_csrf_secret_valid(csrf_token))
+                .await
+                .unwrap()
+            {
+                return ResponseHelpers::new(StatusCode::BAD_REQUEST, "Некорректный CSRF токен.");
+            }
+
+            handle(&request, &mut ctx, form).await;
+        }
+    }
+
+    render(ctx).await
+}
@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 use app_api::Api;
 use app_shared::{
     chrono::{DateTime, Utc},
-    models::{Secret, Session},
+    models::{Secret, Session, SessionId},
     prelude::*,
     UserAgentParser,
 };
@@ -15,6 +15,10 @@ use crate::{extractors::AuthenticatedUser, html_response::HtmlResponse};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct SessionInfo {
+    pub id: SessionId,
+    pub label: Option<String>,
+    /// Сессия, с которой открыта страница.
+    pub is_current: bool,
     pub os: String,
     pub browser: String,
     pub ip: String,
@@ -23,17 +27,24 @@ struct SessionInfo {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "action")]
+#[serde(rename_all = "snake_case")]
 pub enum SessionsAction {
-    #[serde(rename = "all")]
     All,
+    /// Завершить все сессии, кроме текущей.
+    Others,
+    /// Завершить сессию `session_id`.
+    Revoke,
+    /// Задать название устройства сессии `session_id`.
+    Label,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionsForm {
     pub csrf_token: Secret,
-    #[serde(flatten)]
-    pub action: SessionsAction,
+    pub action: Option<SessionsAction>,
+    pub session_id: Option<SessionId>,
+    #[serde(default)]
+    pub label: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,56 +56,103 @@ struct RenderContext {
 }
 
 #[instrument]
-async fn handle(context: &mut RenderContext) -> Option<HttpResponse> {
-    trace!("session_menu");
+async fn handle(context: &mut RenderContext, form: SessionsForm) -> Option<HttpResponse> {
+    trace!("handle");
 
-    let RenderContext { user, .. } = context.clone();
-    let mut sessions: Vec<Session> =
-        Api::lock_async(move |api| api.get_account_sessions(user.account.id))
-            .await
-            .unwrap();
+    let to_auth = || {
+        HttpResponseBuilder::new(StatusCode::SEE_OTHER)
+            .insert_header((header::LOCATION, "/auth"))
+            .finish()
+    };
 
-    return match context.form.action.clone() {
+    let action = form.action?;
+    let session = context.user.session.clone();
+    let account_id = session.account_id;
+
+    let result = match action {
         SessionsAction::All => {
-            {
-                let target_sessions = sessions.clone();
-                Api::lock_async(move |api| {
-                    for session in target_sessions {
-                        api.delete_session(session.secret.clone()).ok();
-                    }
-                })
+            Api::lock_async(move |api| api.delete_account_sessions(account_id))
+                .await
+                .unwrap();
+
+            return Some(to_auth());
+        }
+        SessionsAction::Others => {
+            Api::lock_async(move |api| api.delete_other_account_sessions(&session))
                 .await
                 .unwrap();
+
+            Ok(())
+        }
+        SessionsAction::Revoke => {
+            let session_id = form.session_id?;
+
+            let result = Api::lock_async(move |api| {
+                api.delete_account_session_by_id(account_id, session_id)
+            })
+            .await
+            .unwrap();
+
+            if result.is_ok() && session_id == session.id {
+                return Some(to_auth());
             }
 
-            sessions.clear();
+            result
+        }
+        SessionsAction::Label => {
+            let session_id = form.session_id?;
+
+            let label = form.label;
 
-            Some(
-                HttpResponseBuilder::new(StatusCode::SEE_OTHER)
-                    .insert_header((header::LOCATION, "/auth"))
-                    .finish(),
-            )
+            Api::lock_async(move |api| api.set_session_label(account_id, session_id, label))
+                .await
+                .unwrap()
         }
     };
+
+    if let Err(err) = result {
+        context.errors.push(err.to_string());
+    }
+
+    None
 }
 
 #[instrument]
-async fn context(user: &AuthenticatedUser, form: Option<&SessionsForm>) -> RenderContext {
+fn context(user: &AuthenticatedUser) -> RenderContext {
     trace!("context");
 
-    let mut sessions_info = Vec::new();
-    let user = user.clone();
-    let sessions: Vec<Session> =
-        Api::lock_async(move |api| api.get_account_sessions(user.account.id))
-            .await
-            .unwrap();
+    RenderContext {
+        user: user.clone(),
+        sessions: Vec::new(),
+        form: SessionsForm {
+            csrf_token: user.secrets.csrf_token.clone(),
+            action: None,
+            session_id: None,
+            label: String::new(),
+        },
+        errors: Vec::new(),
+    }
+}
+
+#[instrument]
+async fn render(mut context: RenderContext) -> HttpResponse {
+    trace!("render");
+
+    let account_id = context.user.account.id;
+    let sessions: Vec<Session> = Api::lock_async(move |api| api.get_account_sessions(account_id))
+        .await
+        .unwrap();
 
     let parser: UserAgentParser = UserAgentParser::clone_state();
+    let current_session_id = context.user.session.id;
 
     for session in sessions {
         let session_info = parser.parse(&session.user_agent);
 
-        sessions_info.push(SessionInfo {
+        context.sessions.push(SessionInfo {
+            id: session.id,
+            label: session.label,
+            is_current: session.id == current_session_id,
             os: session_info
                 .os
                 .unwrap_or_else(|| String::from("Неизвестная ОС")),
@@ -107,20 +165,7 @@ async fn context(user: &AuthenticatedUser, form: Option<&SessionsForm>) -> Rende
         })
     }
 
-    RenderContext {
-        form: form.cloned().unwrap_or_else(|| SessionsForm {
-            action: SessionsAction::All,
-            csrf_token: user.session.csrf_token.clone(),
-        }),
-        user: user.clone(),
-        sessions: sessions_info,
-        errors: Vec::new(),
-    }
-}
-
-#[instrument]
-async fn render(context: RenderContext) -> HttpResponse {
-    trace!("render");
+    context.sessions.sort_by_key(|session| !session.is_current);
 
     HtmlResponse::from_template("account/sessions.html", Some(context)).await
 }
@@ -137,20 +182,22 @@ pub async fn endpoint(
     trace!("endpoint");
 
     let form = form.map(|map| map.0);
-    let mut ctx = context(&user, form.as_ref()).await;
+    let mut ctx = context(&user);
 
-    if form.is_some() && request.method() == Method::POST {
-        let csrf_token = form.unwrap().csrf_token;
+    if let Some(form) = form {
+        if request.method() == Method::POST {
+            let csrf_token = form.csrf_token.clone();
 
-        if !Api::lock_async(move |api| api.is_csrf_secret_valid(csrf_token))
-            .await
-            .unwrap()
-        {
-            return ResponseHelpers::new(StatusCode::BAD_REQUEST, "Некорректный CSRF токен.");
-        }
+            if !Api::lock_async(move |api| api.is_csrf_secret_valid(csrf_token))
+                .await
+                .unwrap()
+            {
+                return ResponseHelpers::new(StatusCode::BAD_REQUEST, "Некорректный CSRF токен.");
+            }
 
-        if let Some(response) = handle(&mut ctx).await {
-            return response;
+            if let Some(response) = handle(&mut ctx, form).await {
+                return response;
+            }
         }
     }
 
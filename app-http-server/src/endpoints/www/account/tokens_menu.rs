@@ -0,0 +1,104 @@
+use actix_http::{Method, StatusCode};
+use actix_web::{routes, web::Form, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use app_api::Api;
+use app_shared::{
+    models::{ApiTokenId, ApiTokenInfo, Secret},
+    prelude::*,
+};
+
+use crate::response::ResponseHelpers;
+use crate::{extractors::AuthenticatedUser, html_response::HtmlResponse, RequestHelpers};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokensForm {
+    pub csrf_token: Secret,
+    pub token_id: Option<ApiTokenId>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RenderContext {
+    pub user: AuthenticatedUser,
+    pub tokens: Vec<ApiTokenInfo>,
+    pub form: TokensForm,
+    pub errors: Vec<String>,
+}
+
+#[instrument]
+async fn handle(request: &HttpRequest, context: &mut RenderContext, form: TokensForm) {
+    trace!("handle");
+
+    let Some(token_id) = form.token_id else {
+        return;
+    };
+
+    let caller = RequestHelpers::api_caller(request, context.user.secrets.api_secret.clone());
+    let result = Api::lock_async(move |api| api.delete_api_token_by_id(caller, token_id))
+        .await
+        .unwrap();
+
+    match result {
+        Ok(_) => context.tokens.retain(|token| token.id != token_id),
+        Err(err) => context.errors.push(err.to_string()),
+    }
+}
+
+#[instrument]
+async fn context(user: &AuthenticatedUser) -> RenderContext {
+    trace!("context");
+
+    let account_id = user.account.id;
+    let tokens = Api::lock_async(move |api| api.get_account_api_tokens(account_id))
+        .await
+        .unwrap();
+
+    RenderContext {
+        user: user.clone(),
+        tokens,
+        form: TokensForm {
+            csrf_token: user.secrets.csrf_token.clone(),
+            token_id: None,
+        },
+        errors: Vec::new(),
+    }
+}
+
+#[instrument]
+async fn render(context: RenderContext) -> HttpResponse {
+    trace!("render");
+
+    HtmlResponse::from_template("account/tokens.html", Some(context)).await
+}
+
+#[instrument]
+#[routes]
+#[get("/tokens")]
+#[post("/tokens")]
+pub async fn endpoint(
+    request: HttpRequest,
+    user: AuthenticatedUser,
+    form: Option<Form<TokensForm>>,
+) -> HttpResponse {
+    trace!("endpoint");
+
+    let form = form.map(|map| map.0);
+    let mut ctx = context(&user).await;
+
+    if let Some(form) = form {
+        if request.method() == Method::POST {
+            let csrf_token = form.csrf_token.clone();
+
+            if !Api::lock_async(move |api| api.is_csrf_secret_valid(csrf_token))
+                .await
+                .unwrap()
+            {
+                return ResponseHelpers::new(StatusCode::BAD_REQUEST, "Некорректный CSRF токен.");
+            }
+
+            handle(&request, &mut ctx, form).await;
+        }
+    }
+
+    render(ctx).await
+}
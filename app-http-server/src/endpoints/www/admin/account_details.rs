@@ -0,0 +1,363 @@
+use std::collections::HashMap;
+
+use actix_http::{header, Method, StatusCode};
+use actix_web::{post, routes, web, web::Form, HttpRequest, HttpResponse, HttpResponseBuilder};
+use serde::{Deserialize, Serialize};
+
+use app_api::Api;
+use app_shared::{
+    chrono::{DateTime, Utc},
+    models::{
+        Account, AccountId, AccountNote, AccountNoteId, Actor, ApiError, GameServerId,
+        IntegrationLink, JournalEntryCursor, NoteSeverity, Role, RoleId, Secret, UserRights,
+        UsernameChange,
+    },
+    prelude::*,
+    UserAgentParser,
+};
+
+use crate::response::ResponseHelpers;
+use crate::{
+    extractors::AuthenticatedUser, html_response::HtmlResponse, FormErrors, RequestHelpers,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SessionInfo {
+    pub label: Option<String>,
+    pub os: String,
+    pub browser: String,
+    pub ip: String,
+    pub created_at: DateTime<Utc>,
+    pub expiration: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaginationQuery {
+    pub offset: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenameForm {
+    pub csrf_token: Secret,
+    pub username: String,
+    /// Код второго фактора администратора, если он подключён.
+    #[serde(default)]
+    pub mfa_code: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteForm {
+    pub csrf_token: Secret,
+    pub text: String,
+    pub severity: NoteSeverity,
+    /// Пустая строка - заметка относится ко всем серверам.
+    #[serde(default)]
+    pub server_id: String,
+    #[serde(default)]
+    pub shareable: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CsrfForm {
+    pub csrf_token: Secret,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RenderContext {
+    pub user: AuthenticatedUser,
+    pub account: Account,
+    pub sessions: Vec<SessionInfo>,
+    pub links: Vec<IntegrationLink>,
+    pub cursor: JournalEntryCursor,
+    pub roles: HashMap<RoleId, Role>,
+    pub accounts: HashMap<AccountId, Account>,
+    pub username_history: Vec<UsernameChange>,
+    /// `None` - недостаточно прав для просмотра заметок.
+    pub notes: Option<Vec<AccountNote>>,
+    pub can_edit_notes: bool,
+    /// У администратора подключён второй фактор, переименование требует код.
+    pub mfa_enabled: bool,
+    pub form: RenameForm,
+    pub errors: FormErrors,
+}
+
+#[instrument]
+async fn render(context: RenderContext) -> HttpResponse {
+    trace!("render");
+
+    HtmlResponse::from_template("admin/account.html", Some(context)).await
+}
+
+#[instrument]
+#[routes]
+#[get("/accounts/{account_id}")]
+#[post("/accounts/{account_id}")]
+pub async fn endpoint(
+    request: HttpRequest,
+    user: AuthenticatedUser,
+    account_id: web::Path<i64>,
+    pagination: web::Query<PaginationQuery>,
+    form: Option<Form<RenameForm>>,
+) -> HttpResponse {
+    trace!("endpoint");
+
+    let account_id = AccountId(account_id.into_inner());
+    let mut errors = FormErrors::default();
+
+    if let Some(Form(form)) = form.filter(|_| request.method() == Method::POST) {
+        let csrf_token = form.csrf_token.clone();
+
+        if !Api::lock_async(move |api| api.is_csrf_secret_valid(csrf_token))
+            .await
+            .unwrap()
+        {
+            return ResponseHelpers::new(StatusCode::BAD_REQUEST, "Некорректный CSRF токен.");
+        }
+
+        let caller = RequestHelpers::api_caller(&request, user.secrets.api_secret.clone());
+        let user_id = user.account.id;
+        let ip = RequestHelpers::ip(&request);
+        let result = Api::lock_async(move |api| {
+            api.check_mfa_code(user_id, form.mfa_code, Some(ip))?;
+            api.force_change_username(caller, account_id, form.username)
+        })
+        .await
+        .unwrap();
+
+        if let Err(err) = result {
+            errors
+                .entry("username".to_string())
+                .or_default()
+                .push(err.to_string());
+        }
+    }
+
+    page(&request, user, account_id, pagination.offset, errors).await
+}
+
+/// Оставляет заметку о пользователе.
+#[instrument]
+#[post("/accounts/{account_id}/notes")]
+pub async fn notes_endpoint(
+    request: HttpRequest,
+    user: AuthenticatedUser,
+    account_id: web::Path<i64>,
+    form: Form<NoteForm>,
+) -> HttpResponse {
+    trace!("notes_endpoint");
+
+    let account_id = AccountId(account_id.into_inner());
+    let Form(form) = form;
+
+    if !is_csrf_valid(form.csrf_token.clone()).await {
+        return ResponseHelpers::new(StatusCode::BAD_REQUEST, "Некорректный CSRF токен.");
+    }
+
+    let caller = RequestHelpers::api_caller(&request, user.secrets.api_secret.clone());
+    let server_id = Some(GameServerId(form.server_id.trim().to_string()));
+
+    let result = Api::lock_async(move |api| {
+        api.add_account_note(
+            caller,
+            account_id,
+            form.severity,
+            server_id,
+            form.text,
+            form.shareable,
+        )
+    })
+    .await
+    .unwrap();
+
+    after_note_change(&request, user, account_id, result.map(|_| ())).await
+}
+
+/// Удаляет заметку о пользователе.
+#[instrument]
+#[post("/accounts/{account_id}/notes/{note_id}/delete")]
+pub async fn delete_note_endpoint(
+    request: HttpRequest,
+    user: AuthenticatedUser,
+    path: web::Path<(i64, i64)>,
+    form: Form<CsrfForm>,
+) -> HttpResponse {
+    trace!("delete_note_endpoint");
+
+    let (account_id, note_id) = path.into_inner();
+    let (account_id, note_id) = (AccountId(account_id), AccountNoteId(note_id));
+
+    if !is_csrf_valid(form.0.csrf_token).await {
+        return ResponseHelpers::new(StatusCode::BAD_REQUEST, "Некорректный CSRF токен.");
+    }
+
+    let caller = RequestHelpers::api_caller(&request, user.secrets.api_secret.clone());
+
+    let result = Api::lock_async(move |api| api.delete_account_note(caller, note_id))
+        .await
+        .unwrap();
+
+    after_note_change(&request, user, account_id, result).await
+}
+
+async fn is_csrf_valid(csrf_token: Secret) -> bool {
+    Api::lock_async(move |api| api.is_csrf_secret_valid(csrf_token))
+        .await
+        .unwrap()
+}
+
+/// Возвращает на страницу аккаунта, при ошибке показывает её у заметок.
+async fn after_note_change(
+    request: &HttpRequest,
+    user: AuthenticatedUser,
+    account_id: AccountId,
+    result: Result<(), ApiError>,
+) -> HttpResponse {
+    if let Err(err) = result {
+        let mut errors = FormErrors::default();
+        errors
+            .entry("text".to_string())
+            .or_default()
+            .push(err.to_string());
+
+        return page(request, user, account_id, None, errors).await;
+    }
+
+    HttpResponseBuilder::new(StatusCode::SEE_OTHER)
+        .insert_header((
+            header::LOCATION,
+            format!("/admin/accounts/{}", account_id.0),
+        ))
+        .finish()
+}
+
+async fn page(
+    request: &HttpRequest,
+    user: AuthenticatedUser,
+    account_id: AccountId,
+    offset: Option<usize>,
+    errors: FormErrors,
+) -> HttpResponse {
+    let caller = RequestHelpers::api_caller(request, user.secrets.api_secret.clone());
+
+    let account = Api::lock_async(move |api| api.get_account(caller, account_id))
+        .await
+        .unwrap();
+
+    let account = match account {
+        Ok(account) => account,
+        Err(err) => return ResponseHelpers::from_api_error(err),
+    };
+
+    let caller = RequestHelpers::api_caller(request, user.secrets.api_secret.clone());
+    let user_id = user.account.id;
+    let (notes, can_edit_notes, mfa_enabled) = Api::lock_async(move |api| {
+        let notes = match api.get_account_notes(caller, account_id) {
+            Ok(notes) => Some(notes),
+            Err(ApiError::Forbidden(_)) => None,
+            Err(err) => {
+                warn!("failed to get account notes: {err}");
+                None
+            }
+        };
+
+        let can_edit_notes = api
+            .get_session_rights(user_id)
+            .user
+            .contains(UserRights::EDIT_NOTES);
+
+        (notes, can_edit_notes, api.is_mfa_enabled(user_id))
+    })
+    .await
+    .unwrap();
+
+    let empty_cursor =
+        JournalEntryCursor::new(offset.unwrap_or(0), Some(Actor::User(account.id)), 10);
+
+    let cursor = empty_cursor.clone();
+    let note_authors = notes
+        .iter()
+        .flatten()
+        .filter_map(|note| note.author)
+        .collect_vec();
+    let (sessions, links, cursor, roles, accounts) = Api::lock_async(move |api| {
+        let sessions = api.get_account_sessions(account_id);
+        let links = api.get_integration_links(account_id);
+        let cursor = api.get_journal_entries(cursor);
+
+        let roles = api
+            .get_roles()
+            .into_iter()
+            .map(|role| (role.id, role))
+            .collect();
+
+        let account_ids = cursor
+            .iter()
+            .flat_map(|cursor| &cursor.entries)
+            .flat_map(|entry| [Some(&entry.object), entry.subject.as_ref()])
+            .filter_map(|actor| match actor {
+                Some(Actor::User(account_id)) => Some(*account_id),
+                _ => None,
+            })
+            .chain(note_authors)
+            .unique()
+            .collect();
+
+        let accounts = api
+            .find_accounts_by_ids(account_ids)
+            .into_iter()
+            .map(|account| (account.id, account))
+            .collect();
+
+        (sessions, links, cursor, roles, accounts)
+    })
+    .await
+    .unwrap();
+
+    let username_history = Api::lock_async(move |api| api.get_username_history(account_id))
+        .await
+        .unwrap();
+
+    let parser = UserAgentParser::clone_state();
+    let sessions = sessions
+        .into_iter()
+        .map(|session| {
+            let session_info = parser.parse(&session.user_agent);
+
+            SessionInfo {
+                label: session.label,
+                os: session_info
+                    .os
+                    .unwrap_or_else(|| String::from("Неизвестная ОС")),
+                browser: session_info
+                    .browser
+                    .unwrap_or_else(|| String::from("Неизвестный браузер")),
+                ip: session.ip,
+                created_at: session.created_at,
+                expiration: session.expiration,
+            }
+        })
+        .collect();
+
+    let form = RenameForm {
+        csrf_token: user.secrets.csrf_token.clone(),
+        username: account.username.clone(),
+        mfa_code: None,
+    };
+
+    render(RenderContext {
+        user,
+        account,
+        sessions,
+        links,
+        cursor: cursor.unwrap_or(empty_cursor),
+        roles,
+        accounts,
+        username_history,
+        notes,
+        can_edit_notes,
+        mfa_enabled,
+        form,
+        errors,
+    })
+    .await
+}
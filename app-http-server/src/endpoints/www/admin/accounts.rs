@@ -0,0 +1,86 @@
+use actix_web::{get, web::Query, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use app_api::Api;
+use app_shared::{
+    models::{AccountSearch, DonationTier, DonationTierId, Role, RoleId},
+    prelude::*,
+};
+
+use crate::response::ResponseHelpers;
+use crate::{extractors::AuthenticatedUser, html_response::HtmlResponse, RequestHelpers};
+
+/// Пустые поля формы приходят пустыми строками, поэтому ID разбираются вручную.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchQuery {
+    pub query: Option<String>,
+    pub role_id: Option<String>,
+    pub donation_tier_id: Option<String>,
+    pub offset: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RenderContext {
+    pub user: AuthenticatedUser,
+    pub search: AccountSearch,
+    pub roles: Vec<Role>,
+    pub donation_tiers: Vec<DonationTier>,
+}
+
+#[instrument]
+async fn render(context: RenderContext) -> HttpResponse {
+    trace!("render");
+
+    HtmlResponse::from_template("admin/accounts.html", Some(context)).await
+}
+
+#[instrument]
+#[get("/accounts")]
+pub async fn endpoint(
+    request: HttpRequest,
+    user: AuthenticatedUser,
+    query: Query<SearchQuery>,
+) -> HttpResponse {
+    trace!("endpoint");
+
+    let SearchQuery {
+        query,
+        role_id,
+        donation_tier_id,
+        offset,
+    } = query.0;
+
+    let search = AccountSearch::new(
+        query,
+        role_id.and_then(|id| id.parse().ok()).map(RoleId),
+        donation_tier_id
+            .and_then(|id| id.parse().ok())
+            .map(DonationTierId),
+        offset.unwrap_or(0),
+        25,
+    );
+
+    let caller = RequestHelpers::api_caller(&request, user.secrets.api_secret.clone());
+    let (search, roles, donation_tiers) = Api::lock_async(move |api| {
+        (
+            api.search_accounts(caller, search),
+            api.get_roles(),
+            api.get_donation_tiers(),
+        )
+    })
+    .await
+    .unwrap();
+
+    let search = match search {
+        Ok(search) => search,
+        Err(err) => return ResponseHelpers::from_api_error(err),
+    };
+
+    render(RenderContext {
+        user,
+        search,
+        roles,
+        donation_tiers,
+    })
+    .await
+}
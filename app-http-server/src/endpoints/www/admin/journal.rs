@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+
+use actix_http::{header, StatusCode};
+use actix_web::{get, web::Query, HttpRequest, HttpResponse, HttpResponseBuilder};
+use serde::{Deserialize, Serialize};
+
+use app_api::Api;
+use app_shared::{
+    chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc},
+    models::{
+        Account, AccountId, Actor, ApiCaller, ApiError, JournalEntry, JournalEntryId, JournalQuery,
+        Role, RoleId,
+    },
+    prelude::*,
+    serde_json,
+};
+
+use crate::response::ResponseHelpers;
+use crate::{
+    extractors::AuthenticatedUser, html_response::HtmlResponse, FormErrors, RequestHelpers,
+};
+
+/// Записей на странице журнала.
+const PAGE_SIZE: usize = 50;
+
+/// Наибольшее количество записей в выгрузке.
+const MAX_EXPORT_ENTRIES: usize = 10_000;
+
+/// Пустые поля формы приходят пустыми строками, поэтому фильтры разбираются вручную.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FilterQuery {
+    pub object: Option<String>,
+    pub subject: Option<String>,
+    pub action: Option<String>,
+    /// Дата в виде `2023-01-31`, включительно.
+    pub from: Option<String>,
+    /// Дата в виде `2023-01-31`, включительно.
+    pub to: Option<String>,
+    pub before_id: Option<i64>,
+    /// `csv` или `json` - выгрузить записи вместо страницы.
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RenderContext {
+    pub user: AuthenticatedUser,
+    pub filter: FilterQuery,
+    pub journal: JournalQuery,
+    pub roles: HashMap<RoleId, Role>,
+    pub accounts: HashMap<AccountId, Account>,
+    pub errors: FormErrors,
+}
+
+#[instrument]
+async fn render(context: RenderContext) -> HttpResponse {
+    trace!("render");
+
+    HtmlResponse::from_template("admin/journal.html", Some(context)).await
+}
+
+#[instrument]
+#[get("/journal")]
+pub async fn endpoint(
+    request: HttpRequest,
+    user: AuthenticatedUser,
+    filter: Query<FilterQuery>,
+) -> HttpResponse {
+    trace!("endpoint");
+
+    let filter = filter.0;
+    let mut errors = FormErrors::default();
+    let mut query = JournalQuery::new(PAGE_SIZE);
+
+    query.object = parse_actor(&filter.object, "object", &mut errors);
+    query.subject = parse_actor(&filter.subject, "subject", &mut errors);
+    query.action = filter.action.clone().filter(|action| !action.is_empty());
+    query.from = parse_date(&filter.from, "from", &mut errors);
+    query.to = parse_date(&filter.to, "to", &mut errors).map(|to| to + Duration::days(1));
+    query.before_id = filter.before_id.map(JournalEntryId);
+
+    let caller = RequestHelpers::api_caller(&request, user.secrets.api_secret.clone());
+
+    if let Some(format) = filter.format.as_deref() {
+        if !errors.is_empty() {
+            return ResponseHelpers::new(StatusCode::BAD_REQUEST, errors);
+        }
+
+        return export(caller, query, format).await;
+    }
+
+    let journal = if errors.is_empty() {
+        let page_query = query.clone();
+
+        match Api::lock_async(move |api| api.query_journal(caller, page_query))
+            .await
+            .unwrap()
+        {
+            Ok(journal) => journal,
+            Err(err @ (ApiError::Forbidden(_) | ApiError::Unauthorized(_))) => {
+                return ResponseHelpers::from_api_error(err)
+            }
+            Err(err) => {
+                errors
+                    .entry("filter".to_string())
+                    .or_default()
+                    .push(err.to_string());
+                query
+            }
+        }
+    } else {
+        query
+    };
+
+    let entries = journal.entries.clone();
+    let (roles, accounts) = Api::lock_async(move |api| {
+        let roles = api
+            .get_roles()
+            .into_iter()
+            .map(|role| (role.id, role))
+            .collect();
+
+        let accounts = api
+            .find_accounts_by_ids(entries_account_ids(&entries))
+            .into_iter()
+            .map(|account| (account.id, account))
+            .collect();
+
+        (roles, accounts)
+    })
+    .await
+    .unwrap();
+
+    render(RenderContext {
+        user,
+        filter,
+        journal,
+        roles,
+        accounts,
+        errors,
+    })
+    .await
+}
+
+/// Выгружает все записи по фильтрам, но не больше `MAX_EXPORT_ENTRIES`.
+async fn export(caller: ApiCaller, query: JournalQuery, format: &str) -> HttpResponse {
+    let result = Api::lock_async(move |api| {
+        let mut entries = Vec::new();
+        let mut query = JournalQuery {
+            max_count: MAX_EXPORT_ENTRIES,
+            ..query
+        };
+
+        while entries.len() < MAX_EXPORT_ENTRIES {
+            let page = api.query_journal(caller.clone(), query.clone())?;
+            entries.extend(page.entries);
+
+            match page.next_before_id {
+                Some(before_id) => query.before_id = Some(before_id),
+                None => break,
+            }
+        }
+
+        entries.truncate(MAX_EXPORT_ENTRIES);
+
+        let accounts = api
+            .find_accounts_by_ids(entries_account_ids(&entries))
+            .into_iter()
+            .map(|account| (account.id, account.username))
+            .collect::<HashMap<_, _>>();
+
+        Ok::<_, ApiError>((entries, accounts))
+    })
+    .await
+    .unwrap();
+
+    let (entries, accounts) = match result {
+        Ok(result) => result,
+        Err(err) => return ResponseHelpers::from_api_error(err),
+    };
+
+    match format {
+        "json" => HttpResponseBuilder::new(StatusCode::OK)
+            .insert_header(attachment("journal.json"))
+            .json(entries),
+        "csv" => HttpResponseBuilder::new(StatusCode::OK)
+            .content_type("text/csv; charset=utf-8")
+            .insert_header(attachment("journal.csv"))
+            .body(to_csv(&entries, &accounts)),
+        _ => ResponseHelpers::new(StatusCode::BAD_REQUEST, "Неизвестный формат выгрузки"),
+    }
+}
+
+fn to_csv(entries: &[JournalEntry], accounts: &HashMap<AccountId, String>) -> String {
+    let actor_name = |actor: &Actor| match actor {
+        Actor::System => "System".to_string(),
+        Actor::User(account_id) => match accounts.get(account_id) {
+            Some(username) => format!("User:{} ({username})", account_id.0),
+            None => format!("User:{}", account_id.0),
+        },
+        Actor::Webhook(name) => format!("Webhook:{name}"),
+        Actor::Deleted => "Deleted".to_string(),
+    };
+
+    let mut csv = String::from("id,datetime,object,subject,action,data,reason\n");
+
+    for entry in entries {
+        let action = serde_json::to_value(&entry.action).unwrap_or_default();
+
+        let fields = [
+            entry.id.0.to_string(),
+            entry.datetime.to_rfc3339(),
+            actor_name(&entry.object),
+            entry.subject.as_ref().map(actor_name).unwrap_or_default(),
+            action["type"].as_str().unwrap_or_default().to_string(),
+            action
+                .get("data")
+                .map(|data| data.to_string())
+                .unwrap_or_default(),
+            entry.reason.clone().unwrap_or_default(),
+        ];
+
+        csv += &fields.iter().map(|field| csv_field(field)).join(",");
+        csv.push('\n');
+    }
+
+    csv
+}
+
+/// Экранирует поле CSV по RFC 4180.
+fn csv_field(value: &str) -> String {
+    // Табличные редакторы считают такие ячейки формулами
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{value}")
+    } else {
+        value.to_string()
+    };
+
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+fn attachment(file_name: &str) -> (header::HeaderName, String) {
+    (
+        header::CONTENT_DISPOSITION,
+        format!("attachment; filename=\"{file_name}\""),
+    )
+}
+
+fn entries_account_ids(entries: &[JournalEntry]) -> Vec<AccountId> {
+    entries
+        .iter()
+        .flat_map(|entry| [Some(&entry.object), entry.subject.as_ref()])
+        .filter_map(|actor| match actor {
+            Some(Actor::User(account_id)) => Some(*account_id),
+            _ => None,
+        })
+        .unique()
+        .collect()
+}
+
+fn parse_actor(value: &Option<String>, field: &str, errors: &mut FormErrors) -> Option<Actor> {
+    let value = value
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())?;
+
+    // Число считается ID аккаунта
+    let actor = match value.parse::<i64>() {
+        Ok(account_id) => Some(Actor::User(AccountId(account_id))),
+        Err(_) => Actor::parse(value),
+    };
+
+    if actor.is_none() {
+        errors
+            .entry(field.to_string())
+            .or_default()
+            .push("Ожидается ID аккаунта, System, Deleted или Webhook:имя".to_string());
+    }
+
+    actor
+}
+
+fn parse_date(
+    value: &Option<String>,
+    field: &str,
+    errors: &mut FormErrors,
+) -> Option<DateTime<Utc>> {
+    let value = value.as_deref().filter(|value| !value.is_empty())?;
+
+    match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        Ok(date) => Some(Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap())),
+        Err(_) => {
+            errors
+                .entry(field.to_string())
+                .or_default()
+                .push("Некорректная дата".to_string());
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_field_quotes_special_characters() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("line\nbreak"), "\"line\nbreak\"");
+    }
+
+    #[test]
+    fn csv_field_neutralizes_formulas() {
+        assert_eq!(csv_field("=1+2"), "'=1+2");
+        assert_eq!(csv_field("+1"), "'+1");
+        assert_eq!(csv_field("-1"), "'-1");
+        assert_eq!(csv_field("@SUM(A1)"), "'@SUM(A1)");
+        assert_eq!(csv_field("\tcmd"), "'\tcmd");
+        assert_eq!(csv_field("\rcmd"), "\"'\rcmd\"");
+        assert_eq!(
+            csv_field("=HYPERLINK(\"x\",\"y\")"),
+            "\"'=HYPERLINK(\"\"x\"\",\"\"y\"\")\""
+        );
+        // Знаки не в начале ячейки безопасны
+        assert_eq!(csv_field("a=b"), "a=b");
+    }
+}
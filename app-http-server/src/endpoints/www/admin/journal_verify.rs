@@ -0,0 +1,39 @@
+use actix_web::{get, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use app_api::Api;
+use app_shared::{models::JournalChainReport, prelude::*};
+
+use crate::response::ResponseHelpers;
+use crate::{extractors::AuthenticatedUser, html_response::HtmlResponse, RequestHelpers};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RenderContext {
+    pub user: AuthenticatedUser,
+    pub report: JournalChainReport,
+}
+
+#[instrument]
+async fn render(context: RenderContext) -> HttpResponse {
+    trace!("render");
+
+    HtmlResponse::from_template("admin/journal_verify.html", Some(context)).await
+}
+
+#[instrument]
+#[get("/journal/verify")]
+pub async fn endpoint(request: HttpRequest, user: AuthenticatedUser) -> HttpResponse {
+    trace!("endpoint");
+
+    let caller = RequestHelpers::api_caller(&request, user.secrets.api_secret.clone());
+
+    let report = match Api::lock_async(move |api| api.verify_journal_chain(caller))
+        .await
+        .unwrap()
+    {
+        Ok(report) => report,
+        Err(err) => return ResponseHelpers::from_api_error(err),
+    };
+
+    render(RenderContext { user, report }).await
+}
@@ -0,0 +1,24 @@
+use actix_http::{header, StatusCode};
+use actix_web::{web, HttpResponseBuilder, Responder, Scope};
+
+mod account_details;
+mod accounts;
+mod journal;
+mod journal_verify;
+
+async fn redirect() -> impl Responder {
+    HttpResponseBuilder::new(StatusCode::TEMPORARY_REDIRECT)
+        .insert_header((header::LOCATION, "/admin/accounts"))
+        .finish()
+}
+
+pub fn endpoint() -> Scope {
+    Scope::new("/admin")
+        .service(accounts::endpoint)
+        .service(account_details::endpoint)
+        .service(account_details::notes_endpoint)
+        .service(account_details::delete_note_endpoint)
+        .service(journal::endpoint)
+        .service(journal_verify::endpoint)
+        .default_service(web::to(redirect))
+}
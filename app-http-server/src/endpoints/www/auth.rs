@@ -1,7 +1,8 @@
 use crate::extractors::AuthenticatedUser;
 use actix_http::header;
 use actix_web::http::StatusCode;
-use actix_web::{get, HttpResponseBuilder, Responder};
+use actix_web::{get, HttpResponse, HttpResponseBuilder, Responder};
+use app_api::Api;
 use app_shared::{prelude::*, serde_json::json, DiscordSession};
 
 use crate::HtmlResponse;
@@ -14,7 +15,25 @@ pub async fn endpoint(session: Option<AuthenticatedUser>) -> impl Responder {
             .finish();
     }
 
+    render(None).await
+}
+
+/// Страница входа, `error` показывается над формой.
+pub async fn render(error: Option<String>) -> HttpResponse {
+    render_page(error, false).await
+}
+
+/// Страница входа сразу с формой кода второго фактора.
+pub async fn render_mfa() -> HttpResponse {
+    render_page(None, true).await
+}
+
+async fn render_page(error: Option<String>, mfa_required: bool) -> HttpResponse {
     let session = DiscordSession::clone_state().user.unwrap();
+    let (discord_login, code_length) =
+        Api::lock_async(|api| (api.is_discord_login_enabled(), api.get_tfa_code_length()))
+            .await
+            .unwrap();
 
     HtmlResponse::from_template(
         "auth.html",
@@ -22,7 +41,11 @@ pub async fn endpoint(session: Option<AuthenticatedUser>) -> impl Responder {
             "bot": {
                 "name": session.name,
                 "discriminator": session.discriminator,
-            }
+            },
+            "code_length": code_length,
+            "discord_login": discord_login,
+            "error": error,
+            "mfa_required": mfa_required,
         })),
     )
     .await
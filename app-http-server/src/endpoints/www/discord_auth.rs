@@ -0,0 +1,134 @@
+use actix_http::header;
+use actix_web::http::StatusCode;
+use actix_web::web::Query;
+use actix_web::{get, HttpRequest, HttpResponseBuilder, Responder};
+use serde::Deserialize;
+
+use crate::constants::{COOKIES_DISCORD_STATE_KEY, COOKIES_SESSION_KEY};
+use crate::cookies::{DiscordStateCookie, MfaLoginCookie, SessionCookie};
+use crate::endpoints::www::auth;
+use crate::RequestHelpers;
+use app_api::Api;
+use app_shared::{
+    models::{Login, Secret},
+    prelude::*,
+};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Перенаправляет на подтверждение входа в Discord.
+#[get("/auth/discord")]
+pub async fn endpoint(request: HttpRequest) -> impl Responder {
+    trace!("endpoint");
+
+    let ip = RequestHelpers::ip(&request);
+
+    let result = Api::lock_async(move |api| api.start_discord_login(ip))
+        .await
+        .unwrap();
+
+    match result {
+        Err(err) => auth::render(Some(err.to_string())).await,
+        Ok((url, state)) => HttpResponseBuilder::new(StatusCode::SEE_OTHER)
+            .insert_header((header::LOCATION, url))
+            .cookie(DiscordStateCookie::from_state(state))
+            .finish(),
+    }
+}
+
+/// Сюда Discord возвращает пользователя с кодом.
+#[get("/auth/discord/callback")]
+pub async fn callback_endpoint(
+    request: HttpRequest,
+    query: Query<CallbackQuery>,
+) -> impl Responder {
+    trace!("callback_endpoint");
+
+    let query = query.into_inner();
+
+    if let Some(error) = query.error {
+        debug!("discord returned error: {error}");
+
+        return auth::render(Some("Вход через Discord отменён".to_string())).await;
+    }
+
+    // Вход должен завершить тот же браузер, который его начал
+    let state_cookie = request.cookie(COOKIES_DISCORD_STATE_KEY);
+    let (Some(code), Some(state)) = (query.code, query.state) else {
+        return auth::render(Some("Некорректный ответ Discord".to_string())).await;
+    };
+
+    if state_cookie.map(|cookie| cookie.value().to_string()) != Some(state.clone()) {
+        return auth::render(Some(
+            "Вход начат в другом браузере, попробуйте ещё раз".to_string(),
+        ))
+        .await;
+    }
+
+    let Some(user_agent) = request
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|user_agent| user_agent.to_str().ok())
+        .map(|user_agent| user_agent.to_string())
+    else {
+        return auth::render(Some("Некорректный User agent".to_string())).await;
+    };
+
+    let ip = RequestHelpers::ip(&request);
+
+    // Запросы к Discord идут без блокировки Api
+    let result = async {
+        let grant = Api::lock_async(move |api| api.finish_discord_login(state))
+            .await
+            .unwrap()?;
+
+        let user = grant.fetch_user(code).await?;
+
+        Api::lock_async(move |api| api.create_session_by_discord_user(user, user_agent, ip))
+            .await
+            .unwrap()
+    }
+    .await;
+
+    let (session, secrets) = match result {
+        Ok(Login::Completed(session, secrets)) => (*session, secrets),
+        Ok(Login::MfaRequired(login_secret)) => {
+            let mut response = auth::render_mfa().await;
+            response
+                .add_cookie(&MfaLoginCookie::from_secret(login_secret))
+                .ok();
+            response
+                .add_removal_cookie(&DiscordStateCookie::removal())
+                .ok();
+
+            return response;
+        }
+        Err(err) => {
+            let mut response = auth::render(Some(err.to_string())).await;
+            response
+                .add_removal_cookie(&DiscordStateCookie::removal())
+                .ok();
+
+            return response;
+        }
+    };
+
+    if let Some(cookie) = request.cookie(COOKIES_SESSION_KEY) {
+        Api::lock_async(move |api| {
+            api.delete_session(Secret(cookie.value().to_string())).ok();
+        })
+        .await
+        .unwrap();
+    };
+
+    HttpResponseBuilder::new(StatusCode::SEE_OTHER)
+        .insert_header((header::LOCATION, "/"))
+        .cookie(SessionCookie::from_session(session, secrets))
+        .cookie(DiscordStateCookie::removal())
+        .finish()
+}
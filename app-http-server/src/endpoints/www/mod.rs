@@ -1,13 +1,20 @@
 ﻿use actix_web::Scope;
 
 mod account;
+mod admin;
 mod auth;
+mod discord_auth;
 mod index;
 pub mod not_found;
+mod public_profile;
 
 pub fn scope() -> Scope {
     actix_web::web::scope("")
         .service(index::endpoint)
         .service(auth::endpoint)
+        .service(discord_auth::endpoint)
+        .service(discord_auth::callback_endpoint)
         .service(account::endpoint())
+        .service(admin::endpoint())
+        .service(public_profile::endpoint)
 }
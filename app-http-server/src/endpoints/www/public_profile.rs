@@ -0,0 +1,30 @@
+use actix_web::{get, web, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use app_api::Api;
+use app_shared::{models::PublicProfile, prelude::*};
+
+use crate::html_response::HtmlResponse;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RenderContext {
+    pub profile: PublicProfile,
+}
+
+#[instrument]
+#[get("/u/{username}")]
+pub async fn endpoint(username: web::Path<String>) -> HttpResponse {
+    trace!("endpoint");
+
+    let username = username.into_inner();
+    let profile = Api::lock_async(move |api| api.get_public_profile_by_username(username))
+        .await
+        .unwrap();
+
+    match profile {
+        Ok(profile) => {
+            HtmlResponse::from_template("profile.html", Some(RenderContext { profile })).await
+        }
+        Err(_) => HtmlResponse::from_template("404.html", None::<()>).await,
+    }
+}
@@ -5,7 +5,7 @@ use app_api::Api;
 use app_shared::{
     futures_util::future::LocalBoxFuture,
     futures_util::FutureExt,
-    models::{Account, Secret, Session},
+    models::{Account, Secret, Session, SessionSecrets},
     prelude::*,
 };
 use serde::{Deserialize, Serialize};
@@ -13,6 +13,9 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthenticatedUser {
     pub session: Session,
+    /// Секреты сессии в открытом виде, в шаблоны не попадают.
+    #[serde(skip)]
+    pub secrets: SessionSecrets,
     pub account: Account,
 }
 
@@ -33,10 +36,11 @@ impl FromRequest for AuthenticatedUser {
             let user: Option<AuthenticatedUser> =
                 Api::lock_async(|api| {
                     let session = api.find_session_by_secret(session_secret.clone())?;
-                    let account = api.find_account_by_session(session_secret).ok()?;
+                    let account = api.find_account_by_session(session_secret.clone()).ok()?;
 
                     Some(AuthenticatedUser {
                         session,
+                        secrets: SessionSecrets::new(session_secret),
                         account
                     })
                 }).await.unwrap();
@@ -11,13 +11,8 @@ use tera::try_get_value;
 pub fn main_role_filter(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
     let value = value.clone();
     let roles: Vec<Role> = serde_json::from_value(value)?;
-    let roles: Vec<Role> = roles
-        .into_iter()
-        .sorted_by(|a, b| Ord::cmp(&a.rights.bits(), &b.rights.bits()))
-        .sorted_by(|a, b| Ord::cmp(&b.id, &a.id))
-        .collect();
 
-    serde_json::to_value(roles.get(0).cloned().unwrap_or_default()).map_err(tera::Error::json)
+    serde_json::to_value(Role::main_role(roles)).map_err(tera::Error::json)
 }
 
 pub fn rights_to_bits_filter(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
@@ -7,11 +7,13 @@ mod html_response;
 mod http_config;
 mod manifest;
 mod middleware;
+mod request;
 mod response;
 mod server;
 mod templates;
 
 use html_response::HtmlResponse;
+use request::RequestHelpers;
 use response::ResponseHelpers;
 pub use server::Server;
 
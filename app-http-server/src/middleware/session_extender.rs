@@ -1,4 +1,4 @@
-use crate::{cookies::SessionCookie, extractors::AuthenticatedUser};
+use crate::{cookies::SessionCookie, extractors::AuthenticatedUser, RequestHelpers};
 use actix_http::Payload;
 use actix_web::{
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
@@ -85,23 +85,19 @@ where
                 return service.call(req).await;
             };
 
-            let ip = request
-                .connection_info()
-                .realip_remote_addr()
-                .map(|ip| ip.to_string())
-                .unwrap_or_else(String::new);
+            let ip = RequestHelpers::ip(request);
 
             let mut response = service.call(req).await?;
 
-            let Ok(new_session) = Api::lock_async(|api| {
-                api.extend_session(user.session.secret, user_agent, ip)
+            let Ok((new_session, new_secrets)) = Api::lock_async(|api| {
+                api.extend_session(user.secrets.secret, user_agent, ip)
             }).await.unwrap() else {
                 return Ok(response);
             };
 
             response
                 .response_mut()
-                .add_cookie(&SessionCookie::from_session(new_session))
+                .add_cookie(&SessionCookie::from_session(new_session, new_secrets))
                 .unwrap();
 
             Ok(response)
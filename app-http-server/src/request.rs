@@ -0,0 +1,30 @@
+use actix_web::HttpRequest;
+use app_shared::models::{ApiCaller, Secret};
+
+use crate::constants::REASON_HEADER;
+
+#[derive(Debug, Clone)]
+pub struct RequestHelpers;
+
+impl RequestHelpers {
+    pub fn ip(request: &HttpRequest) -> String {
+        request
+            .connection_info()
+            .realip_remote_addr()
+            .map(|ip| ip.to_string())
+            .unwrap_or_default()
+    }
+
+    /// Причина действия для журнала из заголовка `X-Reason`.
+    pub fn reason(request: &HttpRequest) -> Option<String> {
+        request
+            .headers()
+            .get(REASON_HEADER)
+            .map(|value| String::from_utf8_lossy(value.as_bytes()).to_string())
+            .filter(|reason| !reason.trim().is_empty())
+    }
+
+    pub fn api_caller(request: &HttpRequest, secret: Secret) -> ApiCaller {
+        ApiCaller::Token(secret, Some(Self::ip(request)), Self::reason(request))
+    }
+}
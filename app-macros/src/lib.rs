@@ -21,25 +21,33 @@ fn normalize_crate(name: &str) -> Ident {
 
 struct ValidateApiSecret {
     pub varname: Ident,
+    pub ip: Ident,
 }
 
 impl Parse for ValidateApiSecret {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let varname = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let ip = input.parse()?;
 
-        Ok(Self { varname })
+        Ok(Self { varname, ip })
     }
 }
 
+/// Validates the API secret and records its usage: `validate_api_secret!(secret, ip)`.
 #[proc_macro]
 pub fn validate_api_secret(item: TokenStream) -> TokenStream {
-    let ValidateApiSecret { varname } = syn::parse_macro_input!(item as ValidateApiSecret);
+    let ValidateApiSecret { varname, ip } = syn::parse_macro_input!(item as ValidateApiSecret);
     let shared_crate = normalize_crate("app-shared");
 
     let expanded = quote! {
         {
             use #shared_crate::Database;
 
+            if let Err(err) = #varname.check_format(#shared_crate::models::SecretKind::ApiToken) {
+                return Err(ApiError::Unauthorized(err));
+            }
+
             let token = Database::lock(|database| database.find_api_token_by_secret(#varname));
 
             let Some(token) = token else {
@@ -50,6 +58,10 @@ pub fn validate_api_secret(item: TokenStream) -> TokenStream {
                 return Err(ApiError::Unauthorized("Некорректный токен".to_string()));
             };
 
+            Database::lock(|database| {
+                database.update_api_token_usage(token.id, #shared_crate::chrono::Utc::now(), #ip)
+            });
+
             token
         }
     };
@@ -2,21 +2,26 @@ use crate::{
     database::{
         db_config::DbConfig,
         tables::{
-            AccountIntegrationsTable, AccountTable, BugMessageTable, DonationTierTable,
-            FeatureMessageTable, JournalEntryTable, RoleTable, SessionTable, TokenTable,
-            WebhookTable,
+            AccountIntegrationsTable, AccountMfaTable, AccountNoteTable, AccountSearchFilter,
+            AccountTable, BugMessageTable, DonationTierTable, FeatureMessageTable,
+            IntegrationLinkTable, JournalEntryTable, MigrationTable, RoleTable, SessionTable,
+            TokenTable, UsernameChangeTable, WebhookTable,
         },
     },
     models::{
-        Account, AccountId, AccountIntegrations, ActionType, Actor, AnyUserId, ApiToken, BugReport,
-        DonationTier, FeatureVote, FeatureVoteDescriptor, JournalEntry, JournalEntryCursor, Role,
-        RoleId, Secret, Session, Webhook,
+        Account, AccountId, AccountIntegrations, AccountMfa, AccountNote, AccountNoteId,
+        AccountSearch, ActionType, Actor, AnyUserId, ApiToken, ApiTokenId, BugReport, DonationTier,
+        FeatureVote, FeatureVoteDescriptor, GameServerId, IntegrationKind, IntegrationLink,
+        JournalChainLink, JournalEntry, JournalEntryCursor, JournalEntryId, JournalQuery,
+        NoteSeverity, ProfileVisibility, Rights, Role, RoleId, Secret, SecretHash, SecretKind,
+        Session, SessionId, UsernameChange, Webhook,
     },
     prelude::*,
 };
 use app_macros::global;
 use chrono::{DateTime, Utc};
 use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
+use std::collections::BTreeSet;
 use tokio::runtime::Runtime;
 
 #[derive(Debug)]
@@ -49,6 +54,22 @@ impl Database {
 
         // Add migrations here!
         self.migration_init(&self.pool);
+        self.migration_token_metadata(&self.pool);
+        self.migration_hash_secrets(&self.pool);
+        self.migration_integration_links(&self.pool);
+        self.migration_account_merge(&self.pool);
+        self.migration_account_search(&self.pool);
+        self.migration_byond_ckeys(&self.pool);
+        self.migration_github_integration(&self.pool);
+        self.migration_username_history(&self.pool);
+        self.migration_profile_visibility(&self.pool);
+        self.migration_account_notes(&self.pool);
+        self.migration_journal_indexes(&self.pool);
+        self.migration_journal_reason(&self.pool);
+        self.migration_journal_hash_chain(&self.pool);
+        self.migration_account_mfa(&self.pool);
+        self.migration_session_labels(&self.pool);
+        self.migration_token_root_flag(&self.pool);
 
         info!("migration done");
     }
@@ -68,6 +89,291 @@ impl Database {
             AccountIntegrationsTable::create(pool).await.unwrap();
             DonationTierTable::create(pool).await.unwrap();
             JournalEntryTable::create(pool).await.unwrap();
+            MigrationTable::create(pool).await.unwrap();
+        })
+    }
+
+    #[instrument(skip(pool))]
+    fn migration_token_metadata(&self, pool: &Pool<Postgres>) {
+        info!("migration: migration_token_metadata");
+
+        self.rt.block_on(async {
+            TokenTable::add_metadata(pool).await.unwrap();
+        })
+    }
+
+    #[instrument(skip(pool))]
+    fn migration_hash_secrets(&self, pool: &Pool<Postgres>) {
+        info!("migration: migration_hash_secrets");
+
+        self.rt.block_on(async {
+            if MigrationTable::is_applied(pool, "hash_secrets")
+                .await
+                .unwrap()
+            {
+                return;
+            }
+
+            let mut transaction = pool.begin().await.unwrap();
+
+            SessionTable::hash_secrets(&mut transaction).await.unwrap();
+            TokenTable::hash_secrets(&mut transaction).await.unwrap();
+            WebhookTable::hash_secrets(&mut transaction).await.unwrap();
+
+            MigrationTable::insert(&mut transaction, "hash_secrets")
+                .await
+                .unwrap();
+
+            transaction.commit().await.unwrap();
+        })
+    }
+
+    #[instrument(skip(pool))]
+    fn migration_integration_links(&self, pool: &Pool<Postgres>) {
+        info!("migration: migration_integration_links");
+
+        self.rt.block_on(async {
+            IntegrationLinkTable::create(pool).await.unwrap();
+
+            if MigrationTable::is_applied(pool, "integration_links")
+                .await
+                .unwrap()
+            {
+                return;
+            }
+
+            IntegrationLinkTable::backfill(pool).await.unwrap();
+
+            MigrationTable::insert(pool, "integration_links")
+                .await
+                .unwrap();
+        })
+    }
+
+    #[instrument(skip(pool))]
+    fn migration_account_merge(&self, pool: &Pool<Postgres>) {
+        info!("migration: migration_account_merge");
+
+        self.rt.block_on(async {
+            AccountTable::add_merged_into(pool).await.unwrap();
+        })
+    }
+
+    #[instrument(skip(pool))]
+    fn migration_account_search(&self, pool: &Pool<Postgres>) {
+        info!("migration: migration_account_search");
+
+        self.rt.block_on(async {
+            AccountTable::add_search_indexes(pool).await.unwrap();
+            AccountIntegrationsTable::add_search_indexes(pool)
+                .await
+                .unwrap();
+        })
+    }
+
+    /// Приводит ckey к каноничному виду одной транзакцией. Ckey, которые после приведения
+    /// принадлежат нескольким аккаунтам, не меняются: о них предупреждает лог,
+    /// разбираться с ними должна администрация.
+    #[instrument(skip(pool))]
+    fn migration_byond_ckeys(&self, pool: &Pool<Postgres>) {
+        info!("migration: migration_byond_ckeys");
+
+        self.rt.block_on(async {
+            if MigrationTable::is_applied(pool, "byond_ckeys").await.unwrap() {
+                return;
+            }
+
+            let mut transaction = pool.begin().await.unwrap();
+
+            let normalized = AccountIntegrationsTable::normalize_byond_ckeys(&mut *transaction)
+                .await
+                .unwrap();
+
+            if normalized > 0 {
+                info!("normalized {normalized} byond ckeys");
+            }
+
+            let normalized = IntegrationLinkTable::normalize_byond_ckeys(&mut *transaction)
+                .await
+                .unwrap();
+
+            if normalized > 0 {
+                info!("normalized {normalized} byond ckeys in link history");
+            }
+
+            let collisions = AccountIntegrationsTable::find_byond_ckey_collisions(&mut *transaction)
+                .await
+                .unwrap();
+
+            for (ckey, account_ids) in collisions {
+                let found = account_ids[0].0;
+                let account_ids = account_ids.iter().map(|id| id.0).join(", ");
+                warn!(
+                    "byond ckey {} is linked to several accounts: {account_ids}, lookups find #{found}, merge or unlink the others",
+                    ckey.0
+                );
+            }
+
+            let collisions = IntegrationLinkTable::find_byond_ckey_collisions(&mut *transaction)
+                .await
+                .unwrap();
+
+            for (ckey, account_ids) in collisions {
+                let account_ids = account_ids.iter().map(|id| id.0).join(", ");
+                warn!(
+                    "byond ckey {} has open links to several accounts in link history: {account_ids}",
+                    ckey.0
+                );
+            }
+
+            MigrationTable::insert(&mut *transaction, "byond_ckeys")
+                .await
+                .unwrap();
+
+            transaction.commit().await.unwrap();
+        })
+    }
+
+    #[instrument(skip(pool))]
+    fn migration_github_integration(&self, pool: &Pool<Postgres>) {
+        info!("migration: migration_github_integration");
+
+        self.rt.block_on(async {
+            AccountIntegrationsTable::add_github(pool).await.unwrap();
+        })
+    }
+
+    #[instrument(skip(pool))]
+    fn migration_username_history(&self, pool: &Pool<Postgres>) {
+        info!("migration: migration_username_history");
+
+        self.rt.block_on(async {
+            UsernameChangeTable::create(pool).await.unwrap();
+        })
+    }
+
+    #[instrument(skip(pool))]
+    fn migration_profile_visibility(&self, pool: &Pool<Postgres>) {
+        info!("migration: migration_profile_visibility");
+
+        self.rt.block_on(async {
+            AccountTable::add_profile_visibility(pool).await.unwrap();
+        })
+    }
+
+    #[instrument(skip(pool))]
+    fn migration_account_notes(&self, pool: &Pool<Postgres>) {
+        info!("migration: migration_account_notes");
+
+        self.rt.block_on(async {
+            AccountNoteTable::create(pool).await.unwrap();
+        })
+    }
+
+    #[instrument(skip(pool))]
+    fn migration_journal_indexes(&self, pool: &Pool<Postgres>) {
+        info!("migration: migration_journal_indexes");
+
+        self.rt.block_on(async {
+            JournalEntryTable::add_query_indexes(pool).await.unwrap();
+        })
+    }
+
+    #[instrument(skip(pool))]
+    fn migration_journal_reason(&self, pool: &Pool<Postgres>) {
+        info!("migration: migration_journal_reason");
+
+        self.rt.block_on(async {
+            JournalEntryTable::add_reason(pool).await.unwrap();
+        })
+    }
+
+    /// Связывает существующие записи журнала в цепочку хэшей по порядку ID.
+    #[instrument(skip(pool))]
+    fn migration_journal_hash_chain(&self, pool: &Pool<Postgres>) {
+        info!("migration: migration_journal_hash_chain");
+
+        self.rt.block_on(async {
+            JournalEntryTable::add_hash_chain(pool).await.unwrap();
+
+            if MigrationTable::is_applied(pool, "journal_hash_chain")
+                .await
+                .unwrap()
+            {
+                return;
+            }
+
+            let mut prev_hash = None;
+            let mut after_id = None;
+
+            loop {
+                let entries = JournalEntryTable::find_after(pool, after_id, 1000)
+                    .await
+                    .unwrap();
+
+                let Some(last) = entries.last() else {
+                    break;
+                };
+
+                after_id = Some(last.id);
+
+                for mut entry in entries {
+                    entry.prev_hash = prev_hash.take();
+                    let hash = entry.compute_hash();
+
+                    JournalEntryTable::update_hashes(pool, entry.id, entry.prev_hash, hash.clone())
+                        .await
+                        .unwrap();
+
+                    prev_hash = Some(hash);
+                }
+            }
+
+            MigrationTable::insert(pool, "journal_hash_chain")
+                .await
+                .unwrap();
+        })
+    }
+
+    #[instrument(skip(pool))]
+    fn migration_account_mfa(&self, pool: &Pool<Postgres>) {
+        info!("migration: migration_account_mfa");
+
+        self.rt.block_on(async {
+            AccountMfaTable::create(pool).await.unwrap();
+            RoleTable::add_requires_mfa(pool).await.unwrap();
+        })
+    }
+
+    #[instrument(skip(pool))]
+    fn migration_session_labels(&self, pool: &Pool<Postgres>) {
+        info!("migration: migration_session_labels");
+
+        self.rt.block_on(async {
+            SessionTable::add_id_and_label(pool).await.unwrap();
+        })
+    }
+
+    #[instrument(skip(pool))]
+    fn migration_token_root_flag(&self, pool: &Pool<Postgres>) {
+        info!("migration: migration_token_root_flag");
+
+        self.rt.block_on(async {
+            if MigrationTable::is_applied(pool, "token_root_flag")
+                .await
+                .unwrap()
+            {
+                return;
+            }
+
+            let mut transaction = pool.begin().await.unwrap();
+
+            TokenTable::add_root_flag(&mut transaction).await.unwrap();
+            MigrationTable::insert(&mut *transaction, "token_root_flag")
+                .await
+                .unwrap();
+
+            transaction.commit().await.unwrap();
         })
     }
 
@@ -84,19 +390,25 @@ impl Database {
     pub fn find_webhook_by_secret(&self, secret: Secret) -> Option<Webhook> {
         trace!("find_webhook_by_secret");
 
-        self.rt.block_on(async {
-            WebhookTable::find_by_secret(&self.pool, secret)
+        if !secret.is_valid(SecretKind::Webhook) {
+            return None;
+        }
+
+        let webhook = self.rt.block_on(async {
+            WebhookTable::find_by_secret_hash(&self.pool, secret.hash())
                 .await
                 .unwrap()
-        })
+        });
+
+        webhook.filter(|webhook| webhook.secret_hash.verify(&secret))
     }
 
     #[instrument(skip(self))]
-    pub fn delete_webhook_by_secret(&self, secret: Secret) {
-        trace!("delete_webhook_by_secret");
+    pub fn delete_webhook_by_secret_hash(&self, secret_hash: SecretHash) {
+        trace!("delete_webhook_by_secret_hash");
 
         self.rt.block_on(async {
-            WebhookTable::delete_by_secret(&self.pool, secret)
+            WebhookTable::delete_by_secret_hash(&self.pool, secret_hash)
                 .await
                 .unwrap();
         })
@@ -107,7 +419,7 @@ impl Database {
         trace!("update_root_token");
 
         self.rt.block_on(async {
-            let has_token = TokenTable::find_by_secret(&self.pool, token.secret.clone())
+            let has_token = TokenTable::find_by_secret_hash(&self.pool, token.secret_hash.clone())
                 .await
                 .unwrap()
                 .is_some();
@@ -120,21 +432,36 @@ impl Database {
         });
     }
 
+    /// Выдаёт рутовым токенам указанные права, чтобы они получали права, добавленные после их выпуска.
     #[instrument(skip(self))]
-    pub fn add_api_token(&self, token: ApiToken) {
-        trace!("add_api_token");
+    pub fn update_root_tokens_rights(&self, rights: Rights) {
+        trace!("update_root_tokens_rights");
 
         self.rt.block_on(async {
-            TokenTable::insert(&self.pool, token).await.unwrap();
-        })
+            TokenTable::update_root_rights(&self.pool, rights)
+                .await
+                .unwrap();
+        });
     }
 
     #[instrument(skip(self))]
-    pub fn delete_api_token_by_secret(&self, secret: Secret) {
-        trace!("remove_api_token");
+    pub fn add_api_token(&self, token: ApiToken) -> ApiToken {
+        trace!("add_api_token");
+
+        let secret = token.secret.clone();
+        let token = self
+            .rt
+            .block_on(async { TokenTable::insert(&self.pool, token).await.unwrap() });
+
+        ApiToken { secret, ..token }
+    }
+
+    #[instrument(skip(self))]
+    pub fn delete_api_token_by_secret_hash(&self, secret_hash: SecretHash) {
+        trace!("delete_api_token_by_secret_hash");
 
         self.rt.block_on(async {
-            TokenTable::delete_by_secret(&self.pool, secret)
+            TokenTable::delete_by_secret_hash(&self.pool, secret_hash)
                 .await
                 .unwrap();
         });
@@ -144,13 +471,95 @@ impl Database {
     pub fn find_api_token_by_secret(&self, api_secret: Secret) -> Option<ApiToken> {
         trace!("find_api_token_by_secret");
 
+        if !api_secret.is_valid(SecretKind::ApiToken) {
+            return None;
+        }
+
+        let token = self.rt.block_on(async {
+            TokenTable::find_by_secret_hash(&self.pool, api_secret.hash())
+                .await
+                .unwrap()
+        });
+
+        token.filter(|token| token.secret_hash.verify(&api_secret))
+    }
+
+    #[instrument(skip(self))]
+    pub fn find_api_token_by_id(&self, token_id: ApiTokenId) -> Option<ApiToken> {
+        trace!("find_api_token_by_id");
+
+        self.rt
+            .block_on(async { TokenTable::find_by_id(&self.pool, token_id).await.unwrap() })
+    }
+
+    #[instrument(skip(self))]
+    pub fn delete_api_token_by_id(&self, token_id: ApiTokenId) {
+        trace!("delete_api_token_by_id");
+
         self.rt.block_on(async {
-            TokenTable::find_by_secret(&self.pool, api_secret)
+            TokenTable::delete_by_id(&self.pool, token_id)
+                .await
+                .unwrap();
+        });
+    }
+
+    #[instrument(skip(self))]
+    pub fn get_account_api_tokens(&self, account_id: AccountId) -> Vec<ApiToken> {
+        trace!("get_account_api_tokens");
+
+        self.rt.block_on(async {
+            TokenTable::find_by_creator(&self.pool, account_id)
                 .await
                 .unwrap()
         })
     }
 
+    #[instrument(skip(self))]
+    pub fn get_system_api_tokens(&self) -> Vec<ApiToken> {
+        trace!("get_system_api_tokens");
+
+        self.rt
+            .block_on(async { TokenTable::find_system(&self.pool).await.unwrap() })
+    }
+
+    #[instrument(skip(self))]
+    pub fn update_api_token_secret(&self, token_id: ApiTokenId, new_secret: Secret) {
+        trace!("update_api_token_secret");
+
+        self.rt.block_on(async {
+            TokenTable::update_secret_hash(&self.pool, token_id, new_secret.hash())
+                .await
+                .unwrap();
+        });
+    }
+
+    #[instrument(skip(self))]
+    pub fn update_api_token_rights(&self, secret_hash: SecretHash, rights: Rights) {
+        trace!("update_api_token_rights");
+
+        self.rt.block_on(async {
+            TokenTable::update_rights_by_secret_hash(&self.pool, secret_hash, rights)
+                .await
+                .unwrap();
+        });
+    }
+
+    #[instrument(skip(self))]
+    pub fn update_api_token_usage(
+        &self,
+        token_id: ApiTokenId,
+        used_at: DateTime<Utc>,
+        ip: Option<String>,
+    ) {
+        trace!("update_api_token_usage");
+
+        self.rt.block_on(async {
+            TokenTable::update_usage(&self.pool, token_id, used_at, ip)
+                .await
+                .unwrap();
+        });
+    }
+
     #[instrument(skip(self))]
     pub fn find_account_integrations_by_user_id(
         &self,
@@ -158,18 +567,30 @@ impl Database {
     ) -> Option<AccountIntegrations> {
         trace!("find_account_integrations_by_user_id");
 
-        self.rt
-            .block_on(async {
-                AccountIntegrationsTable::find_by_id(&self.pool, user_id)
-                    .await
-                    .unwrap()
-            })
-            .map(|table| AccountIntegrations {
-                account_id: table.account_id,
-                discord_user_id: table.discord_user_id,
-                byond_ckey: table.byond_ckey,
-                ss14_guid: table.ss14_guid,
-            })
+        let (table, merged_into) = self.rt.block_on(async {
+            let table = AccountIntegrationsTable::find_by_id(&self.pool, user_id)
+                .await
+                .unwrap()?;
+            let account = AccountTable::find_by_id(&self.pool, table.account_id)
+                .await
+                .unwrap();
+
+            Some((table, account.and_then(|account| account.merged_into)))
+        })?;
+
+        // Объединённый аккаунт перенаправляет на тот, в который его объединили
+        if let Some(merged_into) = merged_into {
+            return self.find_account_integrations_by_user_id(AnyUserId::AccountId(merged_into));
+        }
+
+        Some(AccountIntegrations {
+            account_id: table.account_id,
+            discord_user_id: table.discord_user_id,
+            byond_ckey: table.byond_ckey,
+            ss14_guid: table.ss14_guid,
+            github_user_id: table.github_user_id,
+            github_login: table.github_login,
+        })
     }
 
     #[instrument(skip(self))]
@@ -217,27 +638,7 @@ impl Database {
                     .unwrap()
             })
             .into_iter()
-            .map(|account| {
-                let integrations = self
-                    .find_account_integrations_by_user_id(AnyUserId::AccountId(account.id))
-                    .unwrap();
-                let roles = self.get_account_roles(integrations.account_id);
-                let donation_tier = self
-                    .find_donation_tiers_for_roles(&roles)
-                    .into_iter()
-                    .max_by_key(|tier| tier.id)
-                    .take();
-
-                Account {
-                    id: account.id,
-                    integrations,
-                    roles,
-                    username: account.username,
-                    avatar_url: account.avatar_url,
-                    created_at: account.created_at,
-                    donation_tier,
-                }
-            })
+            .map(|account| self.account_from_table(account))
             .collect()
     }
 
@@ -250,8 +651,7 @@ impl Database {
         let donation_tier = self
             .find_donation_tiers_for_roles(&roles)
             .into_iter()
-            .max_by_key(|tier| tier.id)
-            .take();
+            .max_by_key(|tier| tier.id);
 
         self.rt
             .block_on(async {
@@ -267,118 +667,478 @@ impl Database {
                 avatar_url: table.avatar_url,
                 created_at: table.created_at,
                 donation_tier,
+                profile_visibility: table.profile_visibility.unwrap_or_default(),
+            })
+    }
+
+    #[instrument(skip(self))]
+    pub fn get_accounts(&self) -> Vec<Account> {
+        trace!("get_accounts");
+
+        self.rt
+            .block_on(async { AccountTable::all(&self.pool).await.unwrap() })
+            .into_iter()
+            .map(|account| self.account_from_table(account))
+            .collect()
+    }
+
+    /// Ищет аккаунты постранично.
+    #[instrument(skip(self))]
+    pub fn search_accounts(&self, search: AccountSearch) -> AccountSearch {
+        trace!("search_accounts");
+
+        let filter = AccountSearchFilter::new(
+            search.query.as_deref(),
+            search.role_id,
+            search.donation_tier_id,
+        );
+
+        let (accounts, total) = self.rt.block_on(async {
+            let accounts =
+                AccountTable::search(&self.pool, filter.clone(), search.offset, search.max_count)
+                    .await
+                    .unwrap();
+            let total = AccountTable::count_search(&self.pool, filter)
+                .await
+                .unwrap();
+
+            (accounts, total)
+        });
+
+        AccountSearch {
+            accounts: accounts
+                .into_iter()
+                .map(|account| self.account_from_table(account))
+                .collect(),
+            total,
+            ..search
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub fn find_accounts_by_ids(&self, account_ids: &[AccountId]) -> Vec<Account> {
+        trace!("find_accounts_by_ids");
+
+        self.rt
+            .block_on(async {
+                AccountTable::find_many_by_ids(&self.pool, account_ids)
+                    .await
+                    .unwrap()
             })
+            .into_iter()
+            .map(|account| self.account_from_table(account))
+            .collect()
+    }
+
+    /// Собирает аккаунт из строки таблицы, его интеграций и ролей.
+    fn account_from_table(&self, account: AccountTable) -> Account {
+        let integrations = self
+            .find_account_integrations_by_user_id(AnyUserId::AccountId(account.id))
+            .unwrap();
+        let roles = self.get_account_roles(integrations.account_id);
+        let donation_tier = self
+            .find_donation_tiers_for_roles(&roles)
+            .into_iter()
+            .max_by_key(|tier| tier.id);
+
+        Account {
+            id: account.id,
+            integrations,
+            roles,
+            username: account.username,
+            avatar_url: account.avatar_url,
+            created_at: account.created_at,
+            donation_tier,
+            profile_visibility: account.profile_visibility.unwrap_or_default(),
+        }
+    }
+
+    /// Находит аккаунт по имени, объединённые аккаунты не учитываются.
+    #[instrument(skip(self))]
+    pub fn find_account_by_username(&self, username: String) -> Option<Account> {
+        trace!("find_account_by_username");
+
+        self.rt
+            .block_on(async {
+                AccountTable::find_by_username(&self.pool, username)
+                    .await
+                    .unwrap()
+            })
+            .filter(|account| account.merged_into.is_none())
+            .map(|account| self.account_from_table(account))
+    }
+
+    #[instrument(skip(self))]
+    pub fn is_username_free(&self, username: String) -> bool {
+        trace!("is_username_free");
+
+        self.rt.block_on(async {
+            AccountTable::find_by_username(&self.pool, username)
+                .await
+                .unwrap()
+                .is_none()
+        })
+    }
+
+    #[instrument(skip(self))]
+    pub fn change_profile_visibility(&self, account_id: AccountId, visibility: ProfileVisibility) {
+        trace!("change_profile_visibility");
+
+        self.rt.block_on(async {
+            AccountTable::update_profile_visibility(&self.pool, account_id, visibility)
+                .await
+                .unwrap()
+        });
+    }
+
+    #[instrument(skip(self))]
+    pub fn change_avatar_url(&self, account_id: AccountId, new_avatar_url: String) {
+        trace!("change_avatar_url");
+
+        self.rt.block_on(async {
+            AccountTable::update_avatar_url(&self.pool, account_id, new_avatar_url)
+                .await
+                .unwrap()
+        });
+    }
+
+    #[instrument(skip(self))]
+    pub fn add_account(
+        &self,
+        username: String,
+        avatar_url: String,
+        created_at: DateTime<Utc>,
+        roles: &[Role],
+        discord_user_id: DiscordUserId,
+    ) -> AccountId {
+        trace!("add_account");
+
+        self.rt.block_on(async {
+            let account_id =
+                AccountTable::insert(&self.pool, username, avatar_url, created_at, roles)
+                    .await
+                    .unwrap();
+
+            AccountIntegrationsTable::insert(
+                &self.pool,
+                AccountIntegrationsTable {
+                    account_id,
+                    discord_user_id,
+                    byond_ckey: None,
+                    ss14_guid: None,
+                    github_user_id: None,
+                    github_login: None,
+                },
+            )
+            .await
+            .unwrap();
+
+            account_id
+        })
+    }
+
+    /// Меняет имя пользователя и записывает смену в историю.
+    #[instrument(skip(self))]
+    pub fn change_username(&self, account_id: AccountId, new_username: String) {
+        trace!("update_username");
+
+        self.rt.block_on(async {
+            UsernameChangeTable::change(&self.pool, account_id, new_username, Utc::now())
+                .await
+                .unwrap();
+        });
+    }
+
+    /// История смены имени, начиная с последней.
+    #[instrument(skip(self))]
+    pub fn get_username_history(&self, account_id: AccountId) -> Vec<UsernameChange> {
+        trace!("get_username_history");
+
+        self.rt.block_on(async {
+            UsernameChangeTable::find_by_account(&self.pool, account_id)
+                .await
+                .unwrap()
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(skip(self))]
+    pub fn add_account_note(
+        &self,
+        account_id: AccountId,
+        author: Option<AccountId>,
+        created_at: DateTime<Utc>,
+        severity: NoteSeverity,
+        server_id: Option<GameServerId>,
+        text: String,
+        shareable: bool,
+    ) -> AccountNoteId {
+        trace!("add_account_note");
+
+        self.rt.block_on(async {
+            AccountNoteTable::insert(
+                &self.pool, account_id, author, created_at, severity, server_id, text, shareable,
+            )
+            .await
+            .unwrap()
+        })
+    }
+
+    #[instrument(skip(self))]
+    pub fn find_account_note(&self, note_id: AccountNoteId) -> Option<AccountNote> {
+        trace!("find_account_note");
+
+        self.rt.block_on(async {
+            AccountNoteTable::find_by_id(&self.pool, note_id)
+                .await
+                .unwrap()
+        })
+    }
+
+    #[instrument(skip(self))]
+    pub fn get_account_notes(&self, account_id: AccountId) -> Vec<AccountNote> {
+        trace!("get_account_notes");
+
+        self.rt.block_on(async {
+            AccountNoteTable::find_by_account(&self.pool, account_id)
+                .await
+                .unwrap()
+        })
+    }
+
+    #[instrument(skip(self))]
+    pub fn delete_account_note(&self, note_id: AccountNoteId) {
+        trace!("delete_account_note");
+
+        self.rt.block_on(async {
+            AccountNoteTable::delete(&self.pool, note_id).await.unwrap();
+        });
+    }
+
+    #[instrument(skip(self))]
+    pub fn connect_account(&self, user_id: AnyUserId, new_user_id: AnyUserId) {
+        trace!("connect_account");
+
+        self.rt.block_on(async {
+            AccountIntegrationsTable::set_integration(&self.pool, user_id, new_user_id)
+                .await
+                .unwrap();
+        });
+    }
+
+    /// Привязывает BYOND или SS14 аккаунт и записывает это в историю привязок.
+    #[instrument(skip(self))]
+    pub fn link_integration(&self, account_id: AccountId, integration_id: AnyUserId) {
+        trace!("link_integration");
+
+        let (kind, id) = IntegrationKind::of(&integration_id).unwrap();
+
+        self.rt.block_on(async {
+            let mut transaction = self.pool.begin().await.unwrap();
+
+            AccountIntegrationsTable::set_integration(
+                &mut *transaction,
+                AnyUserId::AccountId(account_id),
+                integration_id,
+            )
+            .await
+            .unwrap();
+
+            IntegrationLinkTable::insert(&mut *transaction, account_id, kind, id, Utc::now())
+                .await
+                .unwrap();
+
+            transaction.commit().await.unwrap();
+        });
+    }
+
+    /// Привязывает интеграцию к аккаунту в одной транзакции с отвязкой от прежнего владельца
+    /// `previous_owner` и отвязкой интеграции того же типа у самого аккаунта, если она была.
+    #[instrument(skip(self))]
+    pub fn relink_integration(
+        &self,
+        account_id: AccountId,
+        integration_id: AnyUserId,
+        previous_owner: Option<AccountId>,
+        unlink_current: bool,
+    ) {
+        trace!("relink_integration");
+
+        let (kind, id) = IntegrationKind::of(&integration_id).unwrap();
+
+        self.rt.block_on(async {
+            let now = Utc::now();
+            let mut transaction = self.pool.begin().await.unwrap();
+
+            let unlinked = previous_owner
+                .into_iter()
+                .chain(unlink_current.then_some(account_id));
+
+            for unlinked_id in unlinked {
+                AccountIntegrationsTable::clear_integration(&mut *transaction, unlinked_id, kind)
+                    .await
+                    .unwrap();
+
+                IntegrationLinkTable::close(&mut *transaction, unlinked_id, kind, now)
+                    .await
+                    .unwrap();
+            }
+
+            AccountIntegrationsTable::set_integration(
+                &mut *transaction,
+                AnyUserId::AccountId(account_id),
+                integration_id,
+            )
+            .await
+            .unwrap();
+
+            IntegrationLinkTable::insert(&mut *transaction, account_id, kind, id, now)
+                .await
+                .unwrap();
+
+            transaction.commit().await.unwrap();
+        });
     }
 
+    /// Запоминает логин GitHub для отображения.
     #[instrument(skip(self))]
-    pub fn get_accounts(&self) -> Vec<Account> {
-        trace!("get_accounts");
+    pub fn update_github_login(&self, account_id: AccountId, github_login: String) {
+        trace!("update_github_login");
 
-        self.rt
-            .block_on(async { AccountTable::all(&self.pool).await.unwrap() })
-            .into_iter()
-            .map(|account| {
-                let integrations = self
-                    .find_account_integrations_by_user_id(AnyUserId::AccountId(account.id))
-                    .unwrap();
-                let roles = self.get_account_roles(integrations.account_id);
-                let donation_tier = self
-                    .find_donation_tiers_for_roles(&roles)
-                    .into_iter()
-                    .max_by_key(|tier| tier.id)
-                    .take();
-
-                Account {
-                    id: account.id,
-                    integrations,
-                    roles,
-                    username: account.username,
-                    avatar_url: account.avatar_url,
-                    created_at: account.created_at,
-                    donation_tier,
-                }
-            })
-            .collect()
+        self.rt.block_on(async {
+            AccountIntegrationsTable::update_github_login(&self.pool, account_id, github_login)
+                .await
+                .unwrap();
+        });
     }
 
+    /// Отвязывает интеграцию от аккаунта и закрывает её запись в истории.
     #[instrument(skip(self))]
-    pub fn is_username_free(&self, username: String) -> bool {
-        trace!("is_username_free");
+    pub fn unlink_integration(&self, account_id: AccountId, kind: IntegrationKind) {
+        trace!("unlink_integration");
 
         self.rt.block_on(async {
-            AccountTable::find_by_username(&self.pool, username)
+            let mut transaction = self.pool.begin().await.unwrap();
+
+            AccountIntegrationsTable::clear_integration(&mut *transaction, account_id, kind)
                 .await
-                .unwrap()
-                .is_none()
-        })
+                .unwrap();
+
+            IntegrationLinkTable::close(&mut *transaction, account_id, kind, Utc::now())
+                .await
+                .unwrap();
+
+            transaction.commit().await.unwrap();
+        });
     }
 
     #[instrument(skip(self))]
-    pub fn change_avatar_url(&self, account_id: AccountId, new_avatar_url: String) {
-        trace!("change_avatar_url");
+    pub fn get_integration_links(&self, account_id: AccountId) -> Vec<IntegrationLink> {
+        trace!("get_integration_links");
 
         self.rt.block_on(async {
-            AccountTable::update_avatar_url(&self.pool, account_id, new_avatar_url)
+            IntegrationLinkTable::find_by_account(&self.pool, account_id)
                 .await
                 .unwrap()
-        });
+        })
     }
 
     #[instrument(skip(self))]
-    pub fn add_account(
+    pub fn find_last_integration_unlink(
         &self,
-        username: String,
-        avatar_url: String,
-        created_at: DateTime<Utc>,
-        roles: &[Role],
-        discord_user_id: DiscordUserId,
-    ) -> AccountId {
-        trace!("add_account");
+        account_id: AccountId,
+        kind: IntegrationKind,
+        integration_id: String,
+    ) -> Option<DateTime<Utc>> {
+        trace!("find_last_integration_unlink");
 
         self.rt.block_on(async {
-            let account_id =
-                AccountTable::insert(&self.pool, username, avatar_url, created_at, roles)
-                    .await
-                    .unwrap();
-
-            AccountIntegrationsTable::insert(
+            IntegrationLinkTable::find_last_unlinked_at(
                 &self.pool,
-                AccountIntegrationsTable {
-                    account_id,
-                    discord_user_id,
-                    byond_ckey: None,
-                    ss14_guid: None,
-                },
+                account_id,
+                kind,
+                integration_id,
             )
             .await
-            .unwrap();
-
-            account_id
+            .unwrap()
         })
     }
 
+    /// Объединяет `source` в `target` одной транзакцией.
     #[instrument(skip(self))]
-    pub fn change_username(&self, account_id: AccountId, new_username: String) {
-        trace!("update_username");
+    pub fn merge_accounts(&self, source: AccountId, target: AccountId) {
+        trace!("merge_accounts");
 
         self.rt.block_on(async {
-            AccountTable::update_username(&self.pool, account_id, new_username)
+            AccountTable::merge(&self.pool, source, target)
                 .await
                 .unwrap();
         });
     }
 
-    #[instrument(skip(self))]
-    pub fn connect_account(&self, user_id: AnyUserId, new_user_id: AnyUserId) {
-        trace!("connect_account");
+    /// Удаляет аккаунт и обезличивает связанные с ним записи,
+    /// в записях журнала действия заменяются на `redactions`.
+    #[instrument(skip(self, redactions))]
+    pub fn delete_account(
+        &self,
+        account_id: AccountId,
+        redactions: &[(JournalEntryId, ActionType)],
+    ) {
+        trace!("delete_account");
 
         self.rt.block_on(async {
-            AccountIntegrationsTable::set_integration(&self.pool, user_id, new_user_id)
+            AccountTable::delete(&self.pool, account_id, redactions)
                 .await
                 .unwrap();
         });
     }
 
+    #[instrument(skip(self))]
+    pub fn find_bug_reports_by_user_id(&self, user_id: DiscordUserId) -> Vec<BugReport> {
+        trace!("find_bug_reports_by_user_id");
+
+        self.rt.block_on(async {
+            BugMessageTable::find_by_user_id(&self.pool, user_id)
+                .await
+                .unwrap()
+        })
+    }
+
+    #[instrument(skip(self))]
+    pub fn find_feature_votes_by_user_id(&self, user_id: DiscordUserId) -> Vec<FeatureVote> {
+        trace!("find_feature_votes_by_user_id");
+
+        self.rt.block_on(async {
+            FeatureMessageTable::find_by_user_id(&self.pool, user_id)
+                .await
+                .unwrap()
+        })
+    }
+
+    /// Все записи журнала, где `actor` выступает объектом или субъектом.
+    #[instrument(skip(self))]
+    pub fn find_journal_entries_by_actor(&self, actor: Actor) -> Vec<JournalEntry> {
+        trace!("find_journal_entries_by_actor");
+
+        self.rt.block_on(async {
+            JournalEntryTable::find_by_actor(&self.pool, actor)
+                .await
+                .unwrap()
+        })
+    }
+
+    /// Все записи журнала, где `subject` выступает субъектом.
+    #[instrument(skip(self))]
+    pub fn find_journal_entries_by_subject(&self, subject: Actor) -> Vec<JournalEntry> {
+        trace!("find_journal_entries_by_subject");
+
+        self.rt.block_on(async {
+            JournalEntryTable::find_by_subject(&self.pool, subject)
+                .await
+                .unwrap()
+        })
+    }
+
     #[instrument(skip(self))]
     pub fn add_bug_report(&self, bug_report: BugReport) {
         trace!("add_bug_report");
@@ -488,22 +1248,34 @@ impl Database {
     pub fn find_session_by_csrf_secret(&self, csrf_secret: Secret) -> Option<Session> {
         trace!("find_session_by_csrf_secret");
 
-        self.rt.block_on(async {
-            SessionTable::find_by_csrf_secret(&self.pool, csrf_secret)
+        if !csrf_secret.is_valid(SecretKind::Csrf) {
+            return None;
+        }
+
+        let session = self.rt.block_on(async {
+            SessionTable::find_by_csrf_secret_hash(&self.pool, csrf_secret.hash())
                 .await
                 .unwrap()
-        })
+        });
+
+        session.filter(|session| session.csrf_secret_hash.verify(&csrf_secret))
     }
 
     #[instrument(skip(self))]
     pub fn find_session_by_secret(&self, session_secret: Secret) -> Option<Session> {
         trace!("find_session_by_secret");
 
-        self.rt.block_on(async {
-            SessionTable::find_by_secret(&self.pool, session_secret)
+        if !session_secret.is_valid(SecretKind::Session) {
+            return None;
+        }
+
+        let session = self.rt.block_on(async {
+            SessionTable::find_by_secret_hash(&self.pool, session_secret.hash())
                 .await
                 .unwrap()
-        })
+        });
+
+        session.filter(|session| session.secret_hash.verify(&session_secret))
     }
 
     #[instrument(skip(self))]
@@ -518,12 +1290,34 @@ impl Database {
     }
 
     #[instrument(skip(self))]
-    pub fn add_session(&self, session: Session) {
+    pub fn find_session_by_id(&self, session_id: SessionId) -> Option<Session> {
+        trace!("find_session_by_id");
+
+        self.rt.block_on(async {
+            SessionTable::find_by_id(&self.pool, session_id)
+                .await
+                .unwrap()
+        })
+    }
+
+    /// Возвращает сессию с присвоенным ID.
+    #[instrument(skip(self))]
+    pub fn add_session(&self, session: Session) -> Session {
         trace!("add_session");
 
+        self.rt
+            .block_on(async { SessionTable::insert(&self.pool, session).await.unwrap() })
+    }
+
+    #[instrument(skip(self))]
+    pub fn update_session_label(&self, session_id: SessionId, label: Option<String>) {
+        trace!("update_session_label");
+
         self.rt.block_on(async {
-            SessionTable::insert(&self.pool, session).await.unwrap();
-        });
+            SessionTable::update_label(&self.pool, session_id, label)
+                .await
+                .unwrap();
+        })
     }
 
     #[instrument(skip(self))]
@@ -535,11 +1329,11 @@ impl Database {
 
             for session in sessions {
                 if session.is_expired() {
-                    SessionTable::delete_by_secret(&self.pool, session.secret)
+                    SessionTable::delete_by_secret_hash(&self.pool, session.secret_hash)
                         .await
                         .unwrap();
 
-                    TokenTable::delete_by_secret(&self.pool, session.api_secret)
+                    TokenTable::delete_by_secret_hash(&self.pool, session.api_secret_hash)
                         .await
                         .unwrap();
                 }
@@ -548,11 +1342,11 @@ impl Database {
     }
 
     #[instrument(skip(self))]
-    pub fn delete_session_by_secret(&self, session_secret: Secret) {
-        trace!("delete_session_by_secret");
+    pub fn delete_session_by_secret_hash(&self, secret_hash: SecretHash) {
+        trace!("delete_session_by_secret_hash");
 
         self.rt.block_on(async {
-            SessionTable::delete_by_secret(&self.pool, session_secret)
+            SessionTable::delete_by_secret_hash(&self.pool, secret_hash)
                 .await
                 .unwrap();
         })
@@ -565,11 +1359,12 @@ impl Database {
         datetime: DateTime<Utc>,
         subject: Option<Actor>,
         action: ActionType,
+        reason: Option<String>,
     ) -> JournalEntry {
         trace!("create_journal_entry");
 
         self.rt.block_on(async {
-            JournalEntryTable::insert(&self.pool, object, datetime, subject, action)
+            JournalEntryTable::insert(&self.pool, object, datetime, subject, action, reason)
                 .await
                 .unwrap()
         })
@@ -583,13 +1378,20 @@ impl Database {
             offset,
             subject,
             max_count,
+            shareable_only,
             ..
         } = current;
 
         let entries = self.rt.block_on(async {
-            JournalEntryTable::find_cursor_entries(&self.pool, offset, max_count, subject.clone())
-                .await
-                .unwrap()
+            JournalEntryTable::find_cursor_entries(
+                &self.pool,
+                offset,
+                max_count,
+                subject.clone(),
+                shareable_only,
+            )
+            .await
+            .unwrap()
         });
 
         if entries.is_empty() {
@@ -597,7 +1399,7 @@ impl Database {
         }
 
         let total = self.rt.block_on(async {
-            JournalEntryTable::count_total_entries(&self.pool, subject.clone())
+            JournalEntryTable::count_total_entries(&self.pool, subject.clone(), shareable_only)
                 .await
                 .unwrap()
         });
@@ -606,6 +1408,7 @@ impl Database {
             offset: current.offset,
             subject,
             max_count,
+            shareable_only,
             entries,
             total,
         };
@@ -613,6 +1416,105 @@ impl Database {
         Some(next)
     }
 
+    /// Выбирает страницу журнала, запрашивая на одну запись больше, чтобы узнать о следующей.
+    #[instrument]
+    pub fn query_journal(&self, query: JournalQuery) -> JournalQuery {
+        trace!("query_journal");
+
+        let mut entries = self.rt.block_on(async {
+            JournalEntryTable::query(&self.pool, &query, query.max_count + 1)
+                .await
+                .unwrap()
+        });
+
+        let next_before_id = if entries.len() > query.max_count {
+            entries.truncate(query.max_count);
+            entries.last().map(|entry| entry.id)
+        } else {
+            None
+        };
+
+        JournalQuery {
+            entries,
+            next_before_id,
+            ..query
+        }
+    }
+
+    /// Записи с типом действия старше `before`, по возрастанию ID начиная после `after_id`.
+    #[instrument(skip(self))]
+    pub fn get_expired_journal_entries(
+        &self,
+        action: &str,
+        before: DateTime<Utc>,
+        after_id: Option<JournalEntryId>,
+        max_count: usize,
+    ) -> Vec<JournalEntry> {
+        trace!("get_expired_journal_entries");
+
+        self.rt.block_on(async {
+            JournalEntryTable::find_expired(&self.pool, action, before, after_id, max_count)
+                .await
+                .unwrap()
+        })
+    }
+
+    /// Удаляет записи, перенесённые в архив, их хэши остаются в цепочке.
+    #[instrument(skip(self, ids))]
+    pub fn archive_journal_entries(&self, ids: &[JournalEntryId]) {
+        trace!("archive_journal_entries");
+
+        self.rt.block_on(async {
+            JournalEntryTable::archive_by_ids(&self.pool, ids)
+                .await
+                .unwrap();
+        })
+    }
+
+    /// Звенья цепочки хэшей журнала по возрастанию ID, начиная после `after_id`.
+    #[instrument(skip(self))]
+    pub fn get_journal_chain_links(
+        &self,
+        after_id: Option<JournalEntryId>,
+        max_count: usize,
+    ) -> Vec<JournalChainLink> {
+        trace!("get_journal_chain_links");
+
+        self.rt.block_on(async {
+            JournalEntryTable::find_chain_links(&self.pool, after_id, max_count)
+                .await
+                .unwrap()
+        })
+    }
+
+    /// ID записей, из которых по `JournalEntriesRedacted` убраны личные данные.
+    #[instrument(skip(self))]
+    pub fn get_redacted_journal_entry_ids(&self) -> BTreeSet<JournalEntryId> {
+        trace!("get_redacted_journal_entry_ids");
+
+        self.rt.block_on(async {
+            JournalEntryTable::find_redacted_ids(&self.pool)
+                .await
+                .unwrap()
+                .into_iter()
+                .collect()
+        })
+    }
+
+    /// Возвращает запись из архива, `false` - запись с таким ID уже есть.
+    #[instrument(skip(self))]
+    pub fn restore_journal_entry(&self, entry: &JournalEntry) -> bool {
+        trace!("restore_journal_entry");
+
+        self.rt.block_on(async {
+            JournalEntryTable::restore(&self.pool, entry)
+                .await
+                .unwrap()
+                .rows_affected()
+                > 0
+        })
+    }
+
     #[instrument]
     pub fn get_roles(&self) -> Vec<Role> {
         trace!("get_roles");
@@ -620,6 +1522,105 @@ impl Database {
         self.rt
             .block_on(async { RoleTable::get_all(&self.pool).await.unwrap() })
     }
+
+    /// Возвращает `false`, если роли нет.
+    #[instrument(skip(self))]
+    pub fn update_role_requires_mfa(&self, role_id: RoleId, requires_mfa: bool) -> bool {
+        trace!("update_role_requires_mfa");
+
+        self.rt.block_on(async {
+            RoleTable::update_requires_mfa(&self.pool, role_id, requires_mfa)
+                .await
+                .unwrap()
+        })
+    }
+
+    #[instrument(skip(self))]
+    pub fn find_account_mfa(&self, account_id: AccountId) -> Option<AccountMfa> {
+        trace!("find_account_mfa");
+
+        self.rt
+            .block_on(async { AccountMfaTable::find(&self.pool, account_id).await.unwrap() })
+    }
+
+    /// Возвращает `false`, если второй фактор уже подключён.
+    #[instrument(skip(self))]
+    pub fn start_account_mfa(&self, account_id: AccountId, totp_secret: Secret) -> bool {
+        trace!("start_account_mfa");
+
+        self.rt.block_on(async {
+            AccountMfaTable::upsert_pending(&self.pool, account_id, totp_secret)
+                .await
+                .unwrap()
+        })
+    }
+
+    /// Возвращает `false`, если подключение не начато или уже завершено.
+    #[instrument(skip(self))]
+    pub fn enable_account_mfa(
+        &self,
+        account_id: AccountId,
+        recovery_codes: Vec<SecretHash>,
+    ) -> bool {
+        trace!("enable_account_mfa");
+
+        self.rt.block_on(async {
+            AccountMfaTable::enable(&self.pool, account_id, Utc::now(), recovery_codes)
+                .await
+                .unwrap()
+        })
+    }
+
+    #[instrument(skip(self))]
+    pub fn update_account_mfa_recovery_codes(
+        &self,
+        account_id: AccountId,
+        recovery_codes: Vec<SecretHash>,
+    ) {
+        trace!("update_account_mfa_recovery_codes");
+
+        self.rt.block_on(async {
+            AccountMfaTable::update_recovery_codes(&self.pool, account_id, recovery_codes)
+                .await
+                .unwrap();
+        })
+    }
+
+    /// Возвращает `false`, если код этого шага уже использован.
+    #[instrument(skip(self))]
+    pub fn use_account_mfa_step(&self, account_id: AccountId, step: i64) -> bool {
+        trace!("use_account_mfa_step");
+
+        self.rt.block_on(async {
+            AccountMfaTable::use_step(&self.pool, account_id, step)
+                .await
+                .unwrap()
+        })
+    }
+
+    /// Возвращает `false`, если такого неиспользованного кода нет.
+    #[instrument(skip(self))]
+    pub fn use_account_mfa_recovery_code(&self, account_id: AccountId, code: Secret) -> bool {
+        trace!("use_account_mfa_recovery_code");
+
+        self.rt.block_on(async {
+            AccountMfaTable::use_recovery_code(&self.pool, account_id, code.hash())
+                .await
+                .unwrap()
+        })
+    }
+
+    /// Возвращает `false`, если второй фактор не был начат.
+    #[instrument(skip(self))]
+    pub fn delete_account_mfa(&self, account_id: AccountId) -> bool {
+        trace!("delete_account_mfa");
+
+        self.rt.block_on(async {
+            AccountMfaTable::delete(&self.pool, account_id)
+                .await
+                .unwrap()
+        })
+    }
 }
 
 impl Clone for Database {
@@ -1,7 +1,14 @@
+use crate::models::Secret;
 use app_macros::config;
+use chrono::{DateTime, Utc};
 
 #[config]
 #[derive(Debug)]
 pub struct DbConfig {
     pub connect: String,
+    /// Ключ, которым хэшируются секреты перед сохранением в БД.
+    pub secret_pepper: Secret,
+    /// До какого момента принимаются секреты старого формата, без префикса `ib_`.
+    /// Если не задано, такие секреты принимаются без ограничения срока.
+    pub legacy_secrets_until: Option<DateTime<Utc>>,
 }
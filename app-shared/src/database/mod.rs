@@ -4,3 +4,4 @@ mod db_config;
 pub mod tables;
 
 pub use database::Database;
+pub(crate) use db_config::DbConfig;
@@ -1,6 +1,77 @@
 use super::prelude::*;
-use crate::models::{AccountId, Role, RoleId};
+use super::JournalEntryTable;
+use crate::models::{
+    AccountId, ActionType, ByondCkey, DonationTierId, JournalEntryId, ProfileVisibility, Role,
+    RoleId,
+};
 use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgArguments, query::Query};
+
+/// Условие поиска аккаунтов, общее для выборки и подсчёта, параметры `$1`-`$6`.
+const SEARCH_CONDITION: &str = "
+account.merged_into IS NULL
+AND ($1::text IS NULL
+    OR lower(account.username) LIKE $1
+    OR account_integrations.discord_user_id = $2
+    OR regexp_replace(lower(account_integrations.byond_ckey), '[^a-z0-9]', '', 'g') = $3
+    OR lower(account_integrations.ss14_guid) = $4)
+AND ($5::bigint IS NULL OR account.roles @> ARRAY[$5::bigint])
+AND ($6::bigint IS NULL OR EXISTS (
+    SELECT 1 FROM donation_tier WHERE donation_tier.id = $6 AND donation_tier.role_id = ANY(account.roles)
+))
+";
+
+/// Параметры поиска аккаунтов.
+#[derive(Debug, Clone, Default)]
+pub struct AccountSearchFilter {
+    pub username_prefix: Option<String>,
+    pub discord_user_id: Option<i64>,
+    pub ckey: Option<String>,
+    pub ss14_guid: Option<String>,
+    pub role_id: Option<RoleId>,
+    pub donation_tier_id: Option<DonationTierId>,
+}
+
+impl AccountSearchFilter {
+    pub fn new(
+        query: Option<&str>,
+        role_id: Option<RoleId>,
+        donation_tier_id: Option<DonationTierId>,
+    ) -> Self {
+        let mut filter = Self {
+            role_id,
+            donation_tier_id,
+            ..Default::default()
+        };
+
+        let Some(query) = query.map(str::trim).filter(|query| !query.is_empty()) else {
+            return filter;
+        };
+
+        let escaped = query
+            .to_lowercase()
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+
+        filter.username_prefix = Some(format!("{escaped}%"));
+        filter.discord_user_id = query.parse::<u64>().ok().map(|id| id as i64);
+        filter.ckey = Some(ByondCkey::canonical(query)).filter(|ckey| !ckey.is_empty());
+        filter.ss14_guid = Some(query.to_lowercase());
+
+        filter
+    }
+
+    fn bind(self, query: Query<'_, Postgres, PgArguments>) -> Query<'_, Postgres, PgArguments> {
+        query
+            .bind(self.username_prefix)
+            .bind(self.discord_user_id)
+            .bind(self.ckey)
+            .bind(self.ss14_guid)
+            .bind(self.role_id.map(|role_id| role_id.0))
+            .bind(self.donation_tier_id.map(|tier_id| tier_id.0))
+    }
+}
 
 pub struct AccountTable {
     pub id: AccountId,
@@ -8,6 +79,10 @@ pub struct AccountTable {
     pub avatar_url: String,
     pub created_at: DateTime<Utc>,
     pub roles: Vec<RoleId>,
+    /// Аккаунт, в который был объединён этот.
+    pub merged_into: Option<AccountId>,
+    /// `None` - пользователь не менял видимость профиля.
+    pub profile_visibility: Option<ProfileVisibility>,
 }
 
 impl AccountTable {
@@ -33,6 +108,38 @@ create table if not exists account
         .await
     }
 
+    #[instrument]
+    pub async fn add_merged_into(pool: &Pool<Postgres>) -> Result<PgQueryResult, Error> {
+        trace!("add_merged_into");
+
+        sqlx::query("ALTER TABLE account ADD COLUMN IF NOT EXISTS merged_into bigint")
+            .execute(pool)
+            .await
+    }
+
+    #[instrument]
+    pub async fn add_profile_visibility(pool: &Pool<Postgres>) -> Result<PgQueryResult, Error> {
+        trace!("add_profile_visibility");
+
+        sqlx::query("ALTER TABLE account ADD COLUMN IF NOT EXISTS profile_visibility bigint")
+            .execute(pool)
+            .await
+    }
+
+    #[instrument]
+    pub async fn add_search_indexes(pool: &Pool<Postgres>) -> Result<(), Error> {
+        trace!("add_search_indexes");
+
+        for query in [
+            "CREATE INDEX IF NOT EXISTS account_username_idx ON account (lower(username) text_pattern_ops)",
+            "CREATE INDEX IF NOT EXISTS account_roles_idx ON account USING gin (roles)",
+        ] {
+            sqlx::query(query).execute(pool).await?;
+        }
+
+        Ok(())
+    }
+
     #[instrument]
     pub async fn insert(
         pool: &Pool<Postgres>,
@@ -63,7 +170,7 @@ RETURNING id
     pub async fn all(pool: &Pool<Postgres>) -> Result<Vec<AccountTable>, Error> {
         trace!("all");
 
-        sqlx::query("SELECT * FROM account")
+        sqlx::query("SELECT * FROM account WHERE merged_into IS NULL")
             .map(Self::map)
             .fetch_all(pool)
             .await
@@ -83,6 +190,62 @@ RETURNING id
             .await
     }
 
+    #[instrument]
+    pub async fn find_many_by_ids(
+        pool: &Pool<Postgres>,
+        account_ids: &[AccountId],
+    ) -> Result<Vec<AccountTable>, Error> {
+        trace!("find_many_by_ids");
+
+        let account_ids: Vec<i64> = account_ids.iter().map(|account_id| account_id.0).collect();
+
+        sqlx::query("SELECT * FROM account WHERE id = ANY($1)")
+            .bind(account_ids)
+            .map(Self::map)
+            .fetch_all(pool)
+            .await
+    }
+
+    #[instrument]
+    pub async fn search(
+        pool: &Pool<Postgres>,
+        filter: AccountSearchFilter,
+        offset: usize,
+        max_count: usize,
+    ) -> Result<Vec<AccountTable>, Error> {
+        trace!("search");
+
+        let query_string = format!(
+            "SELECT account.* FROM account JOIN account_integrations ON account_integrations.account_id = account.id WHERE {SEARCH_CONDITION} ORDER BY account.id LIMIT $7 OFFSET $8"
+        );
+
+        filter
+            .bind(sqlx::query(&query_string))
+            .bind(max_count as i64)
+            .bind(offset as i64)
+            .map(Self::map)
+            .fetch_all(pool)
+            .await
+    }
+
+    #[instrument]
+    pub async fn count_search(
+        pool: &Pool<Postgres>,
+        filter: AccountSearchFilter,
+    ) -> Result<usize, Error> {
+        trace!("count_search");
+
+        let query_string = format!(
+            "SELECT COUNT(*) FROM account JOIN account_integrations ON account_integrations.account_id = account.id WHERE {SEARCH_CONDITION}"
+        );
+
+        filter
+            .bind(sqlx::query(&query_string))
+            .map(|row: PgRow| row.get::<i64, _>("count") as usize)
+            .fetch_one(pool)
+            .await
+    }
+
     #[instrument]
     pub async fn find_many_by_role(
         pool: &Pool<Postgres>,
@@ -90,7 +253,7 @@ RETURNING id
     ) -> Result<Vec<AccountTable>, Error> {
         trace!("find_many_by_role");
 
-        sqlx::query("SELECT * FROM account WHERE $1 = ANY(roles)")
+        sqlx::query("SELECT * FROM account WHERE $1 = ANY(roles) AND merged_into IS NULL")
             .bind(role_id.0)
             .map(Self::map)
             .fetch_all(pool)
@@ -161,6 +324,123 @@ RETURNING id
             .await
     }
 
+    pub async fn update_profile_visibility(
+        pool: &Pool<Postgres>,
+        account_id: AccountId,
+        visibility: ProfileVisibility,
+    ) -> Result<PgQueryResult, Error> {
+        sqlx::query("UPDATE account SET profile_visibility = $1 WHERE id = $2")
+            .bind(visibility.bits() as i64)
+            .bind(account_id.0)
+            .execute(pool)
+            .await
+    }
+
+    /// Переносит всё, что принадлежит `source`, на `target` и оставляет
+    /// на месте `source` перенаправление на `target`.
+    #[instrument]
+    pub async fn merge(
+        pool: &Pool<Postgres>,
+        source: AccountId,
+        target: AccountId,
+    ) -> Result<(), Error> {
+        trace!("merge");
+
+        let mut transaction = pool.begin().await?;
+
+        sqlx::query("UPDATE account SET roles = ARRAY(SELECT DISTINCT unnest(roles || (SELECT roles FROM account WHERE id = $1))) WHERE id = $2")
+            .bind(source.0)
+            .bind(target.0)
+            .execute(&mut transaction)
+            .await?;
+
+        sqlx::query("UPDATE account_integrations SET byond_ckey = COALESCE(byond_ckey, (SELECT byond_ckey FROM account_integrations WHERE account_id = $1)), ss14_guid = COALESCE(ss14_guid, (SELECT ss14_guid FROM account_integrations WHERE account_id = $1)) WHERE account_id = $2")
+            .bind(source.0)
+            .bind(target.0)
+            .execute(&mut transaction)
+            .await?;
+
+        sqlx::query("UPDATE account_integrations AS target SET github_user_id = source.github_user_id, github_login = source.github_login FROM account_integrations AS source WHERE source.account_id = $1 AND target.account_id = $2 AND target.github_user_id IS NULL")
+            .bind(source.0)
+            .bind(target.0)
+            .execute(&mut transaction)
+            .await?;
+
+        sqlx::query("UPDATE account_integrations SET byond_ckey = NULL, ss14_guid = NULL, github_user_id = NULL, github_login = NULL WHERE account_id = $1")
+            .bind(source.0)
+            .execute(&mut transaction)
+            .await?;
+
+        for query in [
+            "UPDATE integration_link SET account_id = $2 WHERE account_id = $1",
+            "UPDATE session SET account_id = $2 WHERE account_id = $1",
+            "UPDATE token SET creator = $2 WHERE creator = $1",
+            "UPDATE account SET merged_into = $2 WHERE merged_into = $1",
+            "UPDATE account_note SET account_id = $2 WHERE account_id = $1",
+            "UPDATE account_note SET author = $2 WHERE author = $1",
+        ] {
+            sqlx::query(query)
+                .bind(source.0)
+                .bind(target.0)
+                .execute(&mut transaction)
+                .await?;
+        }
+
+        sqlx::query("UPDATE account SET roles = '{}', merged_into = $2 WHERE id = $1")
+            .bind(source.0)
+            .bind(target.0)
+            .execute(&mut transaction)
+            .await?;
+
+        transaction.commit().await
+    }
+
+    /// Удаляет аккаунт вместе с объединёнными в него дубликатами.
+    /// Сообщения обратной связи обезличиваются. Акторы в записях журнала не меняются:
+    /// без аккаунта его ID ни с кем не связан. Из действий убираются личные данные по `redactions`.
+    #[instrument(skip(redactions))]
+    pub async fn delete(
+        pool: &Pool<Postgres>,
+        account_id: AccountId,
+        redactions: &[(JournalEntryId, ActionType)],
+    ) -> Result<(), Error> {
+        trace!("delete");
+
+        let mut transaction = pool.begin().await?;
+
+        for (entry_id, action) in redactions {
+            JournalEntryTable::update_action(&mut transaction, *entry_id, action).await?;
+        }
+
+        let account_ids: Vec<i64> =
+            sqlx::query("SELECT id FROM account WHERE id = $1 OR merged_into = $1")
+                .bind(account_id.0)
+                .map(|row: PgRow| row.get::<i64, _>("id"))
+                .fetch_all(&mut transaction)
+                .await?;
+
+        for query in [
+            "UPDATE feature_message SET user_id = 0 WHERE user_id IN (SELECT discord_user_id FROM account_integrations WHERE account_id = ANY($1))",
+            "UPDATE bug_message SET user_id = 0 WHERE user_id IN (SELECT discord_user_id FROM account_integrations WHERE account_id = ANY($1))",
+            "DELETE FROM session WHERE account_id = ANY($1)",
+            "DELETE FROM token WHERE creator = ANY($1)",
+            "DELETE FROM integration_link WHERE account_id = ANY($1)",
+            "DELETE FROM username_change WHERE account_id = ANY($1)",
+            "DELETE FROM account_note WHERE account_id = ANY($1)",
+            "DELETE FROM account_mfa WHERE account_id = ANY($1)",
+            "UPDATE account_note SET author = NULL WHERE author = ANY($1)",
+            "DELETE FROM account_integrations WHERE account_id = ANY($1)",
+            "DELETE FROM account WHERE id = ANY($1)",
+        ] {
+            sqlx::query(query)
+                .bind(&account_ids)
+                .execute(&mut transaction)
+                .await?;
+        }
+
+        transaction.commit().await
+    }
+
     #[instrument(skip(row))]
     fn map(row: PgRow) -> Self {
         Self {
@@ -173,6 +453,10 @@ RETURNING id
                 .into_iter()
                 .map(RoleId)
                 .collect(),
+            merged_into: row.get::<Option<i64>, _>("merged_into").map(AccountId),
+            profile_visibility: row
+                .get::<Option<i64>, _>("profile_visibility")
+                .map(|bits| ProfileVisibility::from_bits_truncate(bits as u64)),
         }
     }
 }
@@ -1,5 +1,5 @@
 use super::prelude::*;
-use crate::models::{AccountId, AnyUserId};
+use crate::models::{AccountId, AnyUserId, GithubUserId, IntegrationKind};
 
 #[derive(Debug, Clone)]
 pub struct AccountIntegrationsTable {
@@ -7,6 +7,8 @@ pub struct AccountIntegrationsTable {
     pub discord_user_id: DiscordUserId,
     pub byond_ckey: Option<ByondUserId>,
     pub ss14_guid: Option<SS14UserId>,
+    pub github_user_id: Option<GithubUserId>,
+    pub github_login: Option<String>,
 }
 
 impl AccountIntegrationsTable {
@@ -31,6 +33,86 @@ create table if not exists account_integrations
         .await
     }
 
+    #[instrument]
+    pub async fn add_github(pool: &Pool<Postgres>) -> Result<(), Error> {
+        trace!("add_github");
+
+        for query in [
+            "ALTER TABLE account_integrations ADD COLUMN IF NOT EXISTS github_user_id bigint",
+            "ALTER TABLE account_integrations ADD COLUMN IF NOT EXISTS github_login text",
+            "CREATE INDEX IF NOT EXISTS account_integrations_github_user_id_idx ON account_integrations (github_user_id)",
+        ] {
+            sqlx::query(query).execute(pool).await?;
+        }
+
+        Ok(())
+    }
+
+    #[instrument]
+    pub async fn add_search_indexes(pool: &Pool<Postgres>) -> Result<(), Error> {
+        trace!("add_search_indexes");
+
+        for query in [
+            "CREATE INDEX IF NOT EXISTS account_integrations_discord_user_id_idx ON account_integrations (discord_user_id)",
+            "CREATE INDEX IF NOT EXISTS account_integrations_byond_ckey_idx ON account_integrations (regexp_replace(lower(byond_ckey), '[^a-z0-9]', '', 'g'))",
+            "CREATE INDEX IF NOT EXISTS account_integrations_ss14_guid_idx ON account_integrations (lower(ss14_guid))",
+        ] {
+            sqlx::query(query).execute(pool).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Приводит ckey к каноничному виду там, где это не создаёт дубликатов.
+    /// Возвращает количество изменённых строк.
+    #[instrument(skip(executor))]
+    pub async fn normalize_byond_ckeys<'e>(
+        executor: impl Executor<'e, Database = Postgres>,
+    ) -> Result<u64, Error> {
+        trace!("normalize_byond_ckeys");
+
+        let result = sqlx::query(
+            "
+UPDATE account_integrations
+SET byond_ckey = regexp_replace(lower(byond_ckey), '[^a-z0-9]', '', 'g')
+WHERE byond_ckey <> regexp_replace(lower(byond_ckey), '[^a-z0-9]', '', 'g')
+  AND regexp_replace(lower(byond_ckey), '[^a-z0-9]', '', 'g') IN (
+    SELECT regexp_replace(lower(byond_ckey), '[^a-z0-9]', '', 'g')
+    FROM account_integrations
+    WHERE byond_ckey IS NOT NULL
+    GROUP BY 1
+    HAVING count(*) = 1
+  )
+",
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Находит ckey, которые после приведения принадлежат нескольким аккаунтам.
+    /// Первым идёт аккаунт, который находится поиском по этому ckey.
+    #[instrument(skip(executor))]
+    pub async fn find_byond_ckey_collisions<'e>(
+        executor: impl Executor<'e, Database = Postgres>,
+    ) -> Result<Vec<(ByondUserId, Vec<AccountId>)>, Error> {
+        trace!("find_byond_ckey_collisions");
+
+        sqlx::query("SELECT regexp_replace(lower(byond_ckey), '[^a-z0-9]', '', 'g') AS ckey, array_agg(account_id ORDER BY byond_ckey = regexp_replace(lower(byond_ckey), '[^a-z0-9]', '', 'g') DESC, account_id) AS account_ids FROM account_integrations WHERE byond_ckey IS NOT NULL GROUP BY 1 HAVING count(*) > 1")
+        .map(|row: PgRow| {
+            (
+                ByondUserId(row.get::<String, _>("ckey")),
+                row.get::<Vec<i64>, _>("account_ids")
+                    .into_iter()
+                    .map(AccountId)
+                    .collect(),
+            )
+        })
+        .fetch_all(executor)
+        .await
+    }
+
     #[instrument]
     pub async fn insert(
         pool: &Pool<Postgres>,
@@ -59,12 +141,24 @@ create table if not exists account_integrations
                 sqlx::query("SELECT * FROM account_integrations WHERE discord_user_id = $1")
                     .bind(user_id.0 as i64)
             }
-            AnyUserId::ByondCkey(ckey) => {
-                sqlx::query("SELECT * FROM account_integrations WHERE byond_ckey = $1").bind(ckey.0)
-            }
+            // Выражение совпадает с индексом. Если ckey старого вида остался у нескольких
+            // аккаунтов, находится аккаунт с уже приведённым ckey, иначе самый старый
+            AnyUserId::ByondCkey(ckey) => sqlx::query(
+                "
+SELECT * FROM account_integrations
+WHERE regexp_replace(lower(byond_ckey), '[^a-z0-9]', '', 'g') = $1
+ORDER BY byond_ckey = $1 DESC, account_id
+LIMIT 1
+",
+            )
+            .bind(ByondUserId::canonical(&ckey.0)),
             AnyUserId::SS14Guid(guid) => {
                 sqlx::query("SELECT * FROM account_integrations WHERE ss14_guid = $1").bind(guid.0)
             }
+            AnyUserId::GithubId(user_id) => {
+                sqlx::query("SELECT * FROM account_integrations WHERE github_user_id = $1")
+                    .bind(user_id.0 as i64)
+            }
             AnyUserId::AccountId(id) => {
                 sqlx::query("SELECT * FROM account_integrations WHERE account_id = $1").bind(id.0)
             }
@@ -73,9 +167,9 @@ create table if not exists account_integrations
         query.map(Self::map).fetch_optional(pool).await
     }
 
-    #[instrument]
-    pub async fn set_integration(
-        pool: &Pool<Postgres>,
+    #[instrument(skip(executor))]
+    pub async fn set_integration<'e>(
+        executor: impl Executor<'e, Database = Postgres>,
         user_id: AnyUserId,
         integration_id: AnyUserId,
     ) -> Result<PgQueryResult, Error> {
@@ -85,6 +179,7 @@ create table if not exists account_integrations
             AnyUserId::DiscordId(_) => "discord_user_id = $1",
             AnyUserId::ByondCkey(_) => "byond_ckey = $1",
             AnyUserId::SS14Guid(_) => "ss14_guid = $1",
+            AnyUserId::GithubId(_) => "github_user_id = $1",
             AnyUserId::AccountId(_) => {
                 panic!("can't change account id")
             }
@@ -94,6 +189,7 @@ create table if not exists account_integrations
             AnyUserId::DiscordId(_) => "discord_user_id = $2",
             AnyUserId::ByondCkey(_) => "byond_ckey = $2",
             AnyUserId::SS14Guid(_) => "ss14_guid = $2",
+            AnyUserId::GithubId(_) => "github_user_id = $2",
             AnyUserId::AccountId(_) => "account_id = $2",
         };
 
@@ -104,20 +200,63 @@ create table if not exists account_integrations
         // Bind $1
         let query = match integration_id {
             AnyUserId::DiscordId(discord_user_id) => query.bind(discord_user_id.0 as i64),
-            AnyUserId::ByondCkey(ckey) => query.bind(ckey.0),
+            AnyUserId::ByondCkey(ckey) => query.bind(ByondUserId::canonical(&ckey.0)),
             AnyUserId::SS14Guid(guid) => query.bind(guid.0),
+            AnyUserId::GithubId(user_id) => query.bind(user_id.0 as i64),
             AnyUserId::AccountId(_) => unreachable!(),
         };
 
         // Bind $2
         let query = match user_id {
             AnyUserId::DiscordId(discord_user_id) => query.bind(discord_user_id.0 as i64),
-            AnyUserId::ByondCkey(ckey) => query.bind(ckey.0),
+            AnyUserId::ByondCkey(ckey) => query.bind(ByondUserId::canonical(&ckey.0)),
             AnyUserId::SS14Guid(guid) => query.bind(guid.0),
+            AnyUserId::GithubId(user_id) => query.bind(user_id.0 as i64),
             AnyUserId::AccountId(id) => query.bind(id.0),
         };
 
-        query.execute(pool).await
+        query.execute(executor).await
+    }
+
+    #[instrument(skip(executor))]
+    pub async fn clear_integration<'e>(
+        executor: impl Executor<'e, Database = Postgres>,
+        account_id: AccountId,
+        kind: IntegrationKind,
+    ) -> Result<PgQueryResult, Error> {
+        trace!("clear_integration");
+
+        let query_string = match kind {
+            IntegrationKind::Byond => {
+                "UPDATE account_integrations SET byond_ckey = NULL WHERE account_id = $1"
+            }
+            IntegrationKind::SS14 => {
+                "UPDATE account_integrations SET ss14_guid = NULL WHERE account_id = $1"
+            }
+            IntegrationKind::Github => {
+                "UPDATE account_integrations SET github_user_id = NULL, github_login = NULL WHERE account_id = $1"
+            }
+        };
+
+        sqlx::query(query_string)
+            .bind(account_id.0)
+            .execute(executor)
+            .await
+    }
+
+    #[instrument]
+    pub async fn update_github_login(
+        pool: &Pool<Postgres>,
+        account_id: AccountId,
+        github_login: String,
+    ) -> Result<PgQueryResult, Error> {
+        trace!("update_github_login");
+
+        sqlx::query("UPDATE account_integrations SET github_login = $1 WHERE account_id = $2")
+            .bind(github_login)
+            .bind(account_id.0)
+            .execute(pool)
+            .await
     }
 
     #[instrument(skip(row))]
@@ -127,6 +266,10 @@ create table if not exists account_integrations
             discord_user_id: DiscordUserId(row.get::<i64, _>("discord_user_id") as u64),
             byond_ckey: row.get::<Option<String>, _>("byond_ckey").map(ByondUserId),
             ss14_guid: row.get::<Option<String>, _>("ss14_guid").map(SS14UserId),
+            github_user_id: row
+                .get::<Option<i64>, _>("github_user_id")
+                .map(|user_id| GithubUserId(user_id as u64)),
+            github_login: row.get::<Option<String>, _>("github_login"),
         }
     }
 }
@@ -0,0 +1,164 @@
+use super::prelude::*;
+use crate::models::{AccountId, AccountMfa, Secret, SecretHash};
+use chrono::{DateTime, Utc};
+use serde_json;
+
+/// Второй фактор аккаунтов.
+pub struct AccountMfaTable;
+
+impl AccountMfaTable {
+    #[instrument]
+    pub async fn create(pool: &Pool<Postgres>) -> Result<PgQueryResult, Error> {
+        trace!("create");
+
+        sqlx::query(
+            "
+create table if not exists account_mfa
+(
+    account_id     bigint      not null
+        constraint account_mfa_pk
+            primary key,
+    totp_secret    text        not null,
+    enabled_at     timestamptz,
+    last_used_step bigint      not null default 0,
+    recovery_codes jsonb       not null default '[]'
+);
+",
+        )
+        .execute(pool)
+        .await
+    }
+
+    /// Начинает подключение заново, если второй фактор ещё не подключён.
+    #[instrument]
+    pub async fn upsert_pending(
+        pool: &Pool<Postgres>,
+        account_id: AccountId,
+        totp_secret: Secret,
+    ) -> Result<bool, Error> {
+        trace!("upsert_pending");
+
+        sqlx::query(
+            "
+INSERT INTO account_mfa (account_id, totp_secret)
+VALUES ($1, $2)
+ON CONFLICT (account_id) DO UPDATE
+    SET totp_secret = excluded.totp_secret, last_used_step = 0, recovery_codes = '[]'
+    WHERE account_mfa.enabled_at IS NULL
+",
+        )
+        .bind(account_id.0)
+        .bind(totp_secret.0)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected() > 0)
+    }
+
+    #[instrument]
+    pub async fn find(
+        pool: &Pool<Postgres>,
+        account_id: AccountId,
+    ) -> Result<Option<AccountMfa>, Error> {
+        trace!("find");
+
+        sqlx::query("SELECT * FROM account_mfa WHERE account_id = $1")
+            .bind(account_id.0)
+            .map(Self::map)
+            .fetch_optional(pool)
+            .await
+    }
+
+    #[instrument]
+    pub async fn enable(
+        pool: &Pool<Postgres>,
+        account_id: AccountId,
+        enabled_at: DateTime<Utc>,
+        recovery_codes: Vec<SecretHash>,
+    ) -> Result<bool, Error> {
+        trace!("enable");
+
+        sqlx::query("UPDATE account_mfa SET enabled_at = $2, recovery_codes = $3 WHERE account_id = $1 AND enabled_at IS NULL")
+            .bind(account_id.0)
+            .bind(enabled_at)
+            .bind(serde_json::to_value(recovery_codes).unwrap())
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected() > 0)
+    }
+
+    #[instrument]
+    pub async fn update_recovery_codes(
+        pool: &Pool<Postgres>,
+        account_id: AccountId,
+        recovery_codes: Vec<SecretHash>,
+    ) -> Result<PgQueryResult, Error> {
+        trace!("update_recovery_codes");
+
+        sqlx::query("UPDATE account_mfa SET recovery_codes = $2 WHERE account_id = $1")
+            .bind(account_id.0)
+            .bind(serde_json::to_value(recovery_codes).unwrap())
+            .execute(pool)
+            .await
+    }
+
+    /// Запоминает шаг TOTP. Возвращает `false`, если этот или более поздний шаг уже использован.
+    #[instrument]
+    pub async fn use_step(
+        pool: &Pool<Postgres>,
+        account_id: AccountId,
+        step: i64,
+    ) -> Result<bool, Error> {
+        trace!("use_step");
+
+        sqlx::query(
+            "UPDATE account_mfa SET last_used_step = $2 WHERE account_id = $1 AND last_used_step < $2",
+        )
+        .bind(account_id.0)
+        .bind(step)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected() > 0)
+    }
+
+    /// Убирает код восстановления. Возвращает `false`, если такого кода нет.
+    #[instrument]
+    pub async fn use_recovery_code(
+        pool: &Pool<Postgres>,
+        account_id: AccountId,
+        code_hash: SecretHash,
+    ) -> Result<bool, Error> {
+        trace!("use_recovery_code");
+
+        sqlx::query("UPDATE account_mfa SET recovery_codes = recovery_codes - $2::text WHERE account_id = $1 AND recovery_codes ? $2::text")
+            .bind(account_id.0)
+            .bind(code_hash.0)
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected() > 0)
+    }
+
+    #[instrument]
+    pub async fn delete(pool: &Pool<Postgres>, account_id: AccountId) -> Result<bool, Error> {
+        trace!("delete");
+
+        sqlx::query("DELETE FROM account_mfa WHERE account_id = $1")
+            .bind(account_id.0)
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected() > 0)
+    }
+
+    #[instrument(skip(row))]
+    fn map(row: PgRow) -> AccountMfa {
+        AccountMfa {
+            account_id: AccountId(row.get("account_id")),
+            totp_secret: Secret(row.get("totp_secret")),
+            enabled_at: row.get("enabled_at"),
+            last_used_step: row.get("last_used_step"),
+            recovery_codes: serde_json::from_value(
+                row.get::<serde_json::Value, _>("recovery_codes"),
+            )
+            .unwrap(),
+        }
+    }
+}
@@ -0,0 +1,116 @@
+﻿use super::prelude::*;
+use crate::models::{AccountId, AccountNote, AccountNoteId, GameServerId, NoteSeverity};
+use chrono::{DateTime, Utc};
+
+/// Заметки администрации о пользователях.
+pub struct AccountNoteTable;
+
+impl AccountNoteTable {
+    #[instrument]
+    pub async fn create(pool: &Pool<Postgres>) -> Result<(), Error> {
+        trace!("create");
+
+        for query in [
+            "
+create table if not exists account_note
+(
+    id         bigserial   not null
+        constraint account_note_pk
+            primary key,
+    account_id bigint      not null,
+    author     bigint,
+    created_at timestamptz not null,
+    severity   text        not null,
+    server_id  text,
+    text       text        not null,
+    shareable  boolean     not null
+);
+",
+            "CREATE INDEX IF NOT EXISTS account_note_account_id_idx ON account_note (account_id)",
+        ] {
+            sqlx::query(query).execute(pool).await?;
+        }
+
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[instrument]
+    pub async fn insert(
+        pool: &Pool<Postgres>,
+        account_id: AccountId,
+        author: Option<AccountId>,
+        created_at: DateTime<Utc>,
+        severity: NoteSeverity,
+        server_id: Option<GameServerId>,
+        text: String,
+        shareable: bool,
+    ) -> Result<AccountNoteId, Error> {
+        trace!("insert");
+
+        sqlx::query("INSERT INTO account_note (account_id, author, created_at, severity, server_id, text, shareable) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id")
+            .bind(account_id.0)
+            .bind(author.map(|author| author.0))
+            .bind(created_at)
+            .bind(severity.name())
+            .bind(server_id.map(|server_id| server_id.0))
+            .bind(text)
+            .bind(shareable)
+            .map(|row: PgRow| AccountNoteId(row.get("id")))
+            .fetch_one(pool)
+            .await
+    }
+
+    #[instrument]
+    pub async fn find_by_id(
+        pool: &Pool<Postgres>,
+        note_id: AccountNoteId,
+    ) -> Result<Option<AccountNote>, Error> {
+        trace!("find_by_id");
+
+        sqlx::query("SELECT * FROM account_note WHERE id = $1")
+            .bind(note_id.0)
+            .map(Self::map)
+            .fetch_optional(pool)
+            .await
+    }
+
+    #[instrument]
+    pub async fn find_by_account(
+        pool: &Pool<Postgres>,
+        account_id: AccountId,
+    ) -> Result<Vec<AccountNote>, Error> {
+        trace!("find_by_account");
+
+        sqlx::query("SELECT * FROM account_note WHERE account_id = $1 ORDER BY id DESC")
+            .bind(account_id.0)
+            .map(Self::map)
+            .fetch_all(pool)
+            .await
+    }
+
+    #[instrument]
+    pub async fn delete(pool: &Pool<Postgres>, note_id: AccountNoteId) -> Result<(), Error> {
+        trace!("delete");
+
+        sqlx::query("DELETE FROM account_note WHERE id = $1")
+            .bind(note_id.0)
+            .execute(pool)
+            .await
+            .map(|_| ())
+    }
+
+    #[instrument(skip(row))]
+    fn map(row: PgRow) -> AccountNote {
+        AccountNote {
+            id: AccountNoteId(row.get("id")),
+            account_id: AccountId(row.get("account_id")),
+            author: row.get::<Option<i64>, _>("author").map(AccountId),
+            created_at: row.get("created_at"),
+            severity: NoteSeverity::from_name(row.get("severity")).unwrap_or(NoteSeverity::Info),
+            server_id: row.get::<Option<String>, _>("server_id").map(GameServerId),
+            text: row.get("text"),
+            shareable: row.get("shareable"),
+        }
+    }
+}
@@ -47,4 +47,26 @@ VALUES (DEFAULT, $1, $2);
         .execute(pool)
         .await
     }
+
+    #[instrument]
+    pub async fn find_by_user_id(
+        pool: &Pool<Postgres>,
+        user_id: DiscordUserId,
+    ) -> Result<Vec<BugReport>, Error> {
+        trace!("find_by_user_id");
+
+        sqlx::query("SELECT * FROM bug_message WHERE user_id = $1 ORDER BY id")
+            .bind(user_id.0 as i64)
+            .map(Self::map)
+            .fetch_all(pool)
+            .await
+    }
+
+    #[instrument(skip(row))]
+    fn map(row: PgRow) -> BugReport {
+        BugReport {
+            issue_number: row.get::<i64, _>("issue_number") as u64,
+            author_id: DiscordUserId(row.get::<i64, _>("user_id") as u64),
+        }
+    }
 }
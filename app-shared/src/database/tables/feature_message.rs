@@ -76,6 +76,20 @@ VALUES (DEFAULT, $1, $2, $3, $4, $5);
             .await
     }
 
+    #[instrument]
+    pub async fn find_by_user_id(
+        pool: &Pool<Postgres>,
+        user_id: DiscordUserId,
+    ) -> Result<Vec<FeatureVote>, Error> {
+        trace!("find_by_user_id");
+
+        sqlx::query("SELECT * FROM feature_message WHERE user_id = $1 ORDER BY created_at")
+            .bind(user_id.0 as i64)
+            .map(Self::map)
+            .fetch_all(pool)
+            .await
+    }
+
     #[instrument]
     pub async fn end_vote(
         pool: &Pool<Postgres>,
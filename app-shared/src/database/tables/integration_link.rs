@@ -0,0 +1,185 @@
+﻿use super::prelude::*;
+use crate::models::{AccountId, IntegrationKind, IntegrationLink};
+use chrono::{DateTime, Utc};
+
+/// История привязок BYOND и SS14 аккаунтов.
+pub struct IntegrationLinkTable;
+
+impl IntegrationLinkTable {
+    #[instrument]
+    pub async fn create(pool: &Pool<Postgres>) -> Result<PgQueryResult, Error> {
+        trace!("create");
+
+        sqlx::query(
+            "
+create table if not exists integration_link
+(
+    id             bigserial not null
+        constraint integration_link_pk
+            primary key,
+    account_id     bigint    not null,
+    kind           text      not null,
+    integration_id text      not null,
+    linked_at      timestamptz,
+    unlinked_at    timestamptz
+);
+",
+        )
+        .execute(pool)
+        .await
+    }
+
+    /// Переносит в историю привязки, сделанные до её появления.
+    #[instrument]
+    pub async fn backfill(pool: &Pool<Postgres>) -> Result<(), Error> {
+        trace!("backfill");
+
+        for (kind, column) in [
+            (IntegrationKind::Byond, "byond_ckey"),
+            (IntegrationKind::SS14, "ss14_guid"),
+        ] {
+            sqlx::query(&format!(
+                "INSERT INTO integration_link (account_id, kind, integration_id) SELECT account_id, $1, {column} FROM account_integrations WHERE {column} IS NOT NULL"
+            ))
+            .bind(kind.name())
+            .execute(pool)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Приводит ckey в истории привязок BYOND к каноничному виду, кроме ckey,
+    /// которые после приведения оказываются привязаны сейчас к нескольким аккаунтам.
+    /// Возвращает количество изменённых строк.
+    #[instrument(skip(executor))]
+    pub async fn normalize_byond_ckeys<'e>(
+        executor: impl Executor<'e, Database = Postgres>,
+    ) -> Result<u64, Error> {
+        trace!("normalize_byond_ckeys");
+
+        let result = sqlx::query(
+            "
+UPDATE integration_link
+SET integration_id = regexp_replace(lower(integration_id), '[^a-z0-9]', '', 'g')
+WHERE kind = $1
+  AND integration_id <> regexp_replace(lower(integration_id), '[^a-z0-9]', '', 'g')
+  AND regexp_replace(lower(integration_id), '[^a-z0-9]', '', 'g') NOT IN (
+    SELECT regexp_replace(lower(integration_id), '[^a-z0-9]', '', 'g')
+    FROM integration_link
+    WHERE kind = $1 AND unlinked_at IS NULL
+    GROUP BY 1
+    HAVING count(DISTINCT account_id) > 1
+  )
+",
+        )
+        .bind(IntegrationKind::Byond.name())
+        .execute(executor)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Находит ckey, у которых после приведения есть открытые привязки к нескольким аккаунтам.
+    #[instrument(skip(executor))]
+    pub async fn find_byond_ckey_collisions<'e>(
+        executor: impl Executor<'e, Database = Postgres>,
+    ) -> Result<Vec<(ByondUserId, Vec<AccountId>)>, Error> {
+        trace!("find_byond_ckey_collisions");
+
+        sqlx::query("SELECT regexp_replace(lower(integration_id), '[^a-z0-9]', '', 'g') AS ckey, array_agg(DISTINCT account_id) AS account_ids FROM integration_link WHERE kind = $1 AND unlinked_at IS NULL GROUP BY 1 HAVING count(DISTINCT account_id) > 1")
+            .bind(IntegrationKind::Byond.name())
+            .map(|row: PgRow| {
+                (
+                    ByondUserId(row.get::<String, _>("ckey")),
+                    row.get::<Vec<i64>, _>("account_ids")
+                        .into_iter()
+                        .map(AccountId)
+                        .collect(),
+                )
+            })
+            .fetch_all(executor)
+            .await
+    }
+
+    #[instrument(skip(executor))]
+    pub async fn insert<'e>(
+        executor: impl Executor<'e, Database = Postgres>,
+        account_id: AccountId,
+        kind: IntegrationKind,
+        integration_id: String,
+        linked_at: DateTime<Utc>,
+    ) -> Result<PgQueryResult, Error> {
+        trace!("insert");
+
+        sqlx::query("INSERT INTO integration_link (account_id, kind, integration_id, linked_at) VALUES ($1, $2, $3, $4)")
+            .bind(account_id.0)
+            .bind(kind.name())
+            .bind(integration_id)
+            .bind(linked_at)
+            .execute(executor)
+            .await
+    }
+
+    /// Закрывает текущую привязку аккаунта.
+    #[instrument(skip(executor))]
+    pub async fn close<'e>(
+        executor: impl Executor<'e, Database = Postgres>,
+        account_id: AccountId,
+        kind: IntegrationKind,
+        unlinked_at: DateTime<Utc>,
+    ) -> Result<PgQueryResult, Error> {
+        trace!("close");
+
+        sqlx::query("UPDATE integration_link SET unlinked_at = $3 WHERE account_id = $1 AND kind = $2 AND unlinked_at IS NULL")
+            .bind(account_id.0)
+            .bind(kind.name())
+            .bind(unlinked_at)
+            .execute(executor)
+            .await
+    }
+
+    #[instrument]
+    pub async fn find_by_account(
+        pool: &Pool<Postgres>,
+        account_id: AccountId,
+    ) -> Result<Vec<IntegrationLink>, Error> {
+        trace!("find_by_account");
+
+        sqlx::query("SELECT * FROM integration_link WHERE account_id = $1 ORDER BY id DESC")
+            .bind(account_id.0)
+            .map(Self::map)
+            .fetch_all(pool)
+            .await
+    }
+
+    /// Время последней отвязки этого ID интеграции от любого другого аккаунта.
+    #[instrument]
+    pub async fn find_last_unlinked_at(
+        pool: &Pool<Postgres>,
+        account_id: AccountId,
+        kind: IntegrationKind,
+        integration_id: String,
+    ) -> Result<Option<DateTime<Utc>>, Error> {
+        trace!("find_last_unlinked_at");
+
+        sqlx::query("SELECT max(unlinked_at) AS unlinked_at FROM integration_link WHERE kind = $2 AND integration_id = $3 AND account_id <> $1")
+            .bind(account_id.0)
+            .bind(kind.name())
+            .bind(integration_id)
+            .map(|row: PgRow| row.get::<Option<DateTime<Utc>>, _>("unlinked_at"))
+            .fetch_one(pool)
+            .await
+    }
+
+    #[instrument(skip(row))]
+    fn map(row: PgRow) -> IntegrationLink {
+        IntegrationLink {
+            account_id: AccountId(row.get::<i64, _>("account_id")),
+            kind: IntegrationKind::from_name(row.get::<&str, _>("kind")).unwrap(),
+            integration_id: row.get("integration_id"),
+            linked_at: row.get("linked_at"),
+            unlinked_at: row.get("unlinked_at"),
+        }
+    }
+}
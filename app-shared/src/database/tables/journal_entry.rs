@@ -1,12 +1,51 @@
 use chrono::{DateTime, Utc};
 use serde_json;
 
-use crate::models::{ActionType, Actor, JournalEntry, JournalEntryId};
+use crate::models::{
+    ActionType, Actor, JournalChainLink, JournalEntry, JournalEntryId, JournalQuery,
+};
 
 use super::prelude::*;
 
 pub struct JournalEntryTable;
 
+/// Записи, которые можно показать владельцу аккаунта: заметки администрации
+/// только разрешённые к показу, записи об удалённых заметках скрыты, как и в выгрузке.
+const SHAREABLE_CONDITION: &str = "(action ->> 'type' NOT IN ('NoteAdded', 'NoteDeleted') OR EXISTS (SELECT 1 FROM account_note WHERE account_note.id::text = action -> 'data' ->> 'note_id' AND account_note.shareable))";
+
+/// Фильтры выборки журнала, `NULL` - фильтр не задан.
+fn query_condition() -> String {
+    format!(
+        "
+($1::jsonb IS NULL OR {})
+AND ($2::jsonb IS NULL OR {})
+AND ($3::text IS NULL OR action ->> 'type' = $3)
+AND ($4::timestamptz IS NULL OR datetime >= $4)
+AND ($5::timestamptz IS NULL OR datetime < $5)
+AND ($6::bigint IS NULL OR id < $6)
+AND (NOT $8 OR {SHAREABLE_CONDITION})
+",
+        actor_condition("object", "$1"),
+        actor_condition("subject", "$2"),
+    )
+}
+
+/// Фильтры страниц журнала: субъект `$1` и только записи для владельца аккаунта `$2`.
+fn cursor_condition() -> String {
+    format!(
+        "($1::jsonb IS NULL OR {}) AND (NOT $2 OR {SHAREABLE_CONDITION})",
+        actor_condition("subject", "$1"),
+    )
+}
+
+/// Актор в столбце совпадает с параметром. Записи объединённых аккаунтов не переписываются,
+/// поэтому пользователю соответствуют и аккаунты, объединённые в него.
+fn actor_condition(column: &str, param: &str) -> String {
+    format!(
+        "({column} @> {param} OR ({param} ->> 'type' = 'User' AND {column} ->> 'type' = 'User' AND {column} ->> 'data' IN (SELECT id::text FROM account WHERE merged_into::text = {param} ->> 'data')))"
+    )
+}
+
 impl JournalEntryTable {
     #[instrument]
     pub async fn create(pool: &Pool<Postgres>) -> Result<PgQueryResult, Error> {
@@ -30,6 +69,59 @@ create table if not exists journal_entry
         .await
     }
 
+    /// Индексы для фильтров по акторам, типу действия и времени.
+    #[instrument]
+    pub async fn add_query_indexes(pool: &Pool<Postgres>) -> Result<(), Error> {
+        trace!("add_query_indexes");
+
+        for query in [
+            "CREATE INDEX IF NOT EXISTS journal_entry_object_idx ON journal_entry USING gin (object jsonb_path_ops)",
+            "CREATE INDEX IF NOT EXISTS journal_entry_subject_idx ON journal_entry USING gin (subject jsonb_path_ops)",
+            "CREATE INDEX IF NOT EXISTS journal_entry_action_type_idx ON journal_entry ((action ->> 'type'), id)",
+            "CREATE INDEX IF NOT EXISTS journal_entry_datetime_idx ON journal_entry (datetime)",
+        ] {
+            sqlx::query(query).execute(pool).await?;
+        }
+
+        Ok(())
+    }
+
+    #[instrument]
+    pub async fn add_reason(pool: &Pool<Postgres>) -> Result<PgQueryResult, Error> {
+        trace!("add_reason");
+
+        sqlx::query("ALTER TABLE journal_entry ADD COLUMN IF NOT EXISTS reason text")
+            .execute(pool)
+            .await
+    }
+
+    /// Цепочка хэшей и хэши записей, перенесённых в архив.
+    #[instrument]
+    pub async fn add_hash_chain(pool: &Pool<Postgres>) -> Result<(), Error> {
+        trace!("add_hash_chain");
+
+        for query in [
+            "ALTER TABLE journal_entry ADD COLUMN IF NOT EXISTS prev_hash text",
+            "ALTER TABLE journal_entry ADD COLUMN IF NOT EXISTS hash text",
+            "
+create table if not exists journal_entry_archived
+(
+    id        bigint not null
+        constraint journal_entry_archived_pk
+            primary key,
+    prev_hash text,
+    hash      text
+);
+",
+        ] {
+            sqlx::query(query).execute(pool).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Добавляет запись в конец цепочки. Таблица блокируется до конца транзакции,
+    /// чтобы две записи не сослались на один и тот же предыдущий хэш.
     #[instrument]
     pub async fn insert(
         pool: &Pool<Postgres>,
@@ -37,16 +129,208 @@ create table if not exists journal_entry
         datetime: DateTime<Utc>,
         subject: Option<Actor>,
         action: ActionType,
+        reason: Option<String>,
     ) -> Result<JournalEntry, Error> {
         trace!("insert");
 
-        sqlx::query("INSERT INTO journal_entry (id, object, datetime, subject, action) VALUES(DEFAULT, $1, $2, $3, $4) RETURNING *")
+        let mut transaction = pool.begin().await?;
+
+        sqlx::query("LOCK TABLE journal_entry IN EXCLUSIVE MODE")
+            .execute(&mut transaction)
+            .await?;
+
+        let prev_hash = sqlx::query(
+            "
+SELECT hash FROM (
+    SELECT id, hash FROM journal_entry
+    UNION ALL
+    SELECT id, hash FROM journal_entry_archived
+) links ORDER BY id DESC LIMIT 1
+",
+        )
+        .map(|row: PgRow| row.get::<Option<String>, _>("hash"))
+        .fetch_optional(&mut transaction)
+        .await?
+        .flatten();
+
+        let mut entry = sqlx::query("INSERT INTO journal_entry (id, object, datetime, subject, action, reason, prev_hash) VALUES(DEFAULT, $1, $2, $3, $4, $5, $6) RETURNING *")
             .bind(serde_json::to_value(&object).unwrap())
             .bind(datetime)
             .bind(subject.and_then(|value| serde_json::to_value(value).ok()))
             .bind(serde_json::to_value(&action).unwrap())
+            .bind(reason)
+            .bind(prev_hash)
             .map(Self::map)
-            .fetch_one(pool).await
+            .fetch_one(&mut transaction)
+            .await?;
+
+        // Хэш считается по сохранённой записи, время в БД хранится с точностью до микросекунд
+        let hash = entry.compute_hash();
+
+        sqlx::query("UPDATE journal_entry SET hash = $2 WHERE id = $1")
+            .bind(entry.id.0)
+            .bind(&hash)
+            .execute(&mut transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        entry.hash = Some(hash);
+
+        Ok(entry)
+    }
+
+    /// Записи по возрастанию ID, начиная после `after_id`.
+    #[instrument]
+    pub async fn find_after(
+        pool: &Pool<Postgres>,
+        after_id: Option<JournalEntryId>,
+        max_count: usize,
+    ) -> Result<Vec<JournalEntry>, Error> {
+        trace!("find_after");
+
+        sqlx::query(
+            "SELECT * FROM journal_entry WHERE ($1::bigint IS NULL OR id > $1) ORDER BY id LIMIT $2",
+        )
+        .bind(after_id.map(|id| id.0))
+        .bind(max_count as i64)
+        .map(Self::map)
+        .fetch_all(pool)
+        .await
+    }
+
+    #[instrument]
+    pub async fn update_hashes(
+        pool: &Pool<Postgres>,
+        id: JournalEntryId,
+        prev_hash: Option<String>,
+        hash: String,
+    ) -> Result<PgQueryResult, Error> {
+        trace!("update_hashes");
+
+        sqlx::query("UPDATE journal_entry SET prev_hash = $2, hash = $3 WHERE id = $1")
+            .bind(id.0)
+            .bind(prev_hash)
+            .bind(hash)
+            .execute(pool)
+            .await
+    }
+
+    /// Звенья цепочки по возрастанию ID, начиная после `after_id`.
+    /// Запись, возвращённая из архива, проверяется по содержимому.
+    #[instrument]
+    pub async fn find_chain_links(
+        pool: &Pool<Postgres>,
+        after_id: Option<JournalEntryId>,
+        max_count: usize,
+    ) -> Result<Vec<JournalChainLink>, Error> {
+        trace!("find_chain_links");
+
+        sqlx::query(
+            "
+SELECT id, object, datetime, subject, action, reason, prev_hash, hash, false AS archived
+FROM journal_entry
+WHERE ($1::bigint IS NULL OR id > $1)
+UNION ALL
+SELECT id, NULL, NULL, NULL, NULL, NULL, prev_hash, hash, true
+FROM journal_entry_archived archived
+WHERE ($1::bigint IS NULL OR id > $1)
+    AND NOT EXISTS (SELECT 1 FROM journal_entry WHERE journal_entry.id = archived.id)
+ORDER BY id LIMIT $2
+",
+        )
+        .bind(after_id.map(|id| id.0))
+        .bind(max_count as i64)
+        .map(|row: PgRow| {
+            if row.get::<bool, _>("archived") {
+                return JournalChainLink {
+                    id: JournalEntryId(row.get::<i64, _>("id")),
+                    prev_hash: row.get("prev_hash"),
+                    hash: row.get("hash"),
+                    entry: None,
+                };
+            }
+
+            let entry = Self::map(row);
+
+            JournalChainLink {
+                id: entry.id,
+                prev_hash: entry.prev_hash.clone(),
+                hash: entry.hash.clone(),
+                entry: Some(entry),
+            }
+        })
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Все записи, где актор выступает объектом или субъектом.
+    #[instrument]
+    pub async fn find_by_actor(
+        pool: &Pool<Postgres>,
+        actor: Actor,
+    ) -> Result<Vec<JournalEntry>, Error> {
+        trace!("find_by_actor");
+
+        let query_string = format!(
+            "SELECT * FROM journal_entry WHERE {} OR {} ORDER BY datetime",
+            actor_condition("object", "$1"),
+            actor_condition("subject", "$1"),
+        );
+
+        sqlx::query(&query_string)
+            .bind(serde_json::to_value(actor).unwrap())
+            .map(Self::map)
+            .fetch_all(pool)
+            .await
+    }
+
+    /// Все записи, где актор выступает субъектом.
+    #[instrument]
+    pub async fn find_by_subject(
+        pool: &Pool<Postgres>,
+        subject: Actor,
+    ) -> Result<Vec<JournalEntry>, Error> {
+        trace!("find_by_subject");
+
+        let query_string = format!(
+            "SELECT * FROM journal_entry WHERE {} ORDER BY id",
+            actor_condition("subject", "$1"),
+        );
+
+        sqlx::query(&query_string)
+            .bind(serde_json::to_value(subject).unwrap())
+            .map(Self::map)
+            .fetch_all(pool)
+            .await
+    }
+
+    /// Заменяет действие записи. Хэш не пересчитывается, замена должна быть
+    /// записана в цепочку действием `JournalEntriesRedacted`.
+    #[instrument(skip(executor))]
+    pub async fn update_action<'e>(
+        executor: impl Executor<'e, Database = Postgres>,
+        id: JournalEntryId,
+        action: &ActionType,
+    ) -> Result<PgQueryResult, Error> {
+        trace!("update_action");
+
+        sqlx::query("UPDATE journal_entry SET action = $2 WHERE id = $1")
+            .bind(id.0)
+            .bind(serde_json::to_value(action).unwrap())
+            .execute(executor)
+            .await
+    }
+
+    /// ID записей из всех `JournalEntriesRedacted`.
+    #[instrument]
+    pub async fn find_redacted_ids(pool: &Pool<Postgres>) -> Result<Vec<JournalEntryId>, Error> {
+        trace!("find_redacted_ids");
+
+        sqlx::query("SELECT jsonb_array_elements(action -> 'data' -> 'entry_ids') AS id FROM journal_entry WHERE action ->> 'type' = 'JournalEntriesRedacted'")
+            .map(|row: PgRow| serde_json::from_value(row.get::<serde_json::Value, _>("id")).unwrap())
+            .fetch_all(pool)
+            .await
     }
 
     #[instrument]
@@ -55,45 +339,154 @@ create table if not exists journal_entry
         offset: usize,
         max_count: usize,
         subject: Option<Actor>,
+        shareable_only: bool,
     ) -> Result<Vec<JournalEntry>, Error> {
         trace!("find_cursor_entries");
 
-        let query = if let Some(subject) = subject {
-            sqlx::query("SELECT * FROM journal_entry WHERE $1 @> subject ORDER BY datetime DESC LIMIT $2 OFFSET $3")
-				.bind(serde_json::to_value(subject).unwrap())
-				.bind(max_count as i64)
-                .bind(offset as i64)
-        } else {
-            sqlx::query(
-                "SELECT * FROM journal_entry WHERE ORDER BY datetime DESC LIMIT $1 OFFSET $2",
-            )
+        let query_string = format!(
+            "SELECT * FROM journal_entry WHERE {} ORDER BY datetime DESC LIMIT $3 OFFSET $4",
+            cursor_condition(),
+        );
+
+        sqlx::query(&query_string)
+            .bind(subject.and_then(|value| serde_json::to_value(value).ok()))
+            .bind(shareable_only)
             .bind(max_count as i64)
             .bind(offset as i64)
-        };
-
-        query.map(Self::map).fetch_all(pool).await
+            .map(Self::map)
+            .fetch_all(pool)
+            .await
     }
 
     #[instrument]
     pub async fn count_total_entries(
         pool: &Pool<Postgres>,
         subject: Option<Actor>,
+        shareable_only: bool,
     ) -> Result<usize, Error> {
         trace!("count_total_entries");
 
-        let query = if let Some(subject) = subject {
-            sqlx::query("SELECT COUNT(*) FROM journal_entry WHERE $1 @> subject")
-                .bind(serde_json::to_value(subject).unwrap())
-        } else {
-            sqlx::query("SELECT COUNT(*) FROM journal_entry")
-        };
+        let query_string = format!(
+            "SELECT COUNT(*) FROM journal_entry WHERE {}",
+            cursor_condition(),
+        );
 
-        query
+        sqlx::query(&query_string)
+            .bind(subject.and_then(|value| serde_json::to_value(value).ok()))
+            .bind(shareable_only)
             .map(|row| row.get::<i64, _>("count") as usize)
             .fetch_one(pool)
             .await
     }
 
+    /// Записи по фильтрам выборки, новые первыми.
+    #[instrument]
+    pub async fn query(
+        pool: &Pool<Postgres>,
+        query: &JournalQuery,
+        max_count: usize,
+    ) -> Result<Vec<JournalEntry>, Error> {
+        trace!("query");
+
+        let query_string = format!(
+            "SELECT * FROM journal_entry WHERE {} ORDER BY id DESC LIMIT $7",
+            query_condition(),
+        );
+        let object = query
+            .object
+            .as_ref()
+            .and_then(|value| serde_json::to_value(value).ok());
+        let subject = query
+            .subject
+            .as_ref()
+            .and_then(|value| serde_json::to_value(value).ok());
+
+        sqlx::query(&query_string)
+            .bind(object)
+            .bind(subject)
+            .bind(query.action.as_deref())
+            .bind(query.from)
+            .bind(query.to)
+            .bind(query.before_id.map(|id| id.0))
+            .bind(max_count as i64)
+            .bind(query.shareable_only)
+            .map(Self::map)
+            .fetch_all(pool)
+            .await
+    }
+
+    /// Записи с типом действия, сделанные до `before`, по возрастанию ID.
+    #[instrument]
+    pub async fn find_expired(
+        pool: &Pool<Postgres>,
+        action: &str,
+        before: DateTime<Utc>,
+        after_id: Option<JournalEntryId>,
+        max_count: usize,
+    ) -> Result<Vec<JournalEntry>, Error> {
+        trace!("find_expired");
+
+        sqlx::query(
+            "
+SELECT * FROM journal_entry
+WHERE action ->> 'type' = $1 AND datetime < $2 AND ($3::bigint IS NULL OR id > $3)
+ORDER BY id LIMIT $4
+",
+        )
+        .bind(action)
+        .bind(before)
+        .bind(after_id.map(|id| id.0))
+        .bind(max_count as i64)
+        .map(Self::map)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Удаляет записи, оставляя их хэши, чтобы цепочка не прерывалась.
+    #[instrument(skip(ids))]
+    pub async fn archive_by_ids(
+        pool: &Pool<Postgres>,
+        ids: &[JournalEntryId],
+    ) -> Result<(), Error> {
+        trace!("archive_by_ids");
+
+        let ids = ids.iter().map(|id| id.0).collect::<Vec<_>>();
+        let mut transaction = pool.begin().await?;
+
+        sqlx::query("INSERT INTO journal_entry_archived (id, prev_hash, hash) SELECT id, prev_hash, hash FROM journal_entry WHERE id = ANY($1) ON CONFLICT (id) DO NOTHING")
+            .bind(&ids)
+            .execute(&mut transaction)
+            .await?;
+
+        sqlx::query("DELETE FROM journal_entry WHERE id = ANY($1)")
+            .bind(&ids)
+            .execute(&mut transaction)
+            .await?;
+
+        transaction.commit().await
+    }
+
+    /// Возвращает запись из архива с её прежним ID, существующие записи не трогает.
+    #[instrument]
+    pub async fn restore(
+        pool: &Pool<Postgres>,
+        entry: &JournalEntry,
+    ) -> Result<PgQueryResult, Error> {
+        trace!("restore");
+
+        sqlx::query("INSERT INTO journal_entry (id, object, datetime, subject, action, reason, prev_hash, hash) VALUES($1, $2, $3, $4, $5, $6, $7, $8) ON CONFLICT (id) DO NOTHING")
+            .bind(entry.id.0)
+            .bind(serde_json::to_value(&entry.object).unwrap())
+            .bind(entry.datetime)
+            .bind(entry.subject.as_ref().and_then(|value| serde_json::to_value(value).ok()))
+            .bind(serde_json::to_value(&entry.action).unwrap())
+            .bind(entry.reason.as_deref())
+            .bind(entry.prev_hash.as_deref())
+            .bind(entry.hash.as_deref())
+            .execute(pool)
+            .await
+    }
+
     #[instrument(skip(row))]
     fn map(row: PgRow) -> JournalEntry {
         JournalEntry {
@@ -104,6 +497,9 @@ create table if not exists journal_entry
                 .get::<Option<serde_json::Value>, _>("subject")
                 .map(|value| serde_json::from_value(value).unwrap()),
             action: serde_json::from_value(row.get::<serde_json::Value, _>("action")).unwrap(),
+            reason: row.get("reason"),
+            prev_hash: row.get("prev_hash"),
+            hash: row.get("hash"),
         }
     }
 }
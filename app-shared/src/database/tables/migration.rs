@@ -0,0 +1,70 @@
+﻿use super::prelude::*;
+use chrono::Utc;
+
+/// Учёт одноразовых миграций, которые нельзя безопасно повторять.
+pub struct MigrationTable;
+
+impl MigrationTable {
+    #[instrument]
+    pub async fn create(pool: &Pool<Postgres>) -> Result<PgQueryResult, Error> {
+        trace!("create");
+
+        sqlx::query(
+            "
+create table if not exists migration
+(
+    name       text        not null
+        constraint migration_pk
+            primary key,
+    applied_at timestamptz not null
+);
+",
+        )
+        .execute(pool)
+        .await
+    }
+
+    #[instrument]
+    pub async fn is_applied(pool: &Pool<Postgres>, name: &str) -> Result<bool, Error> {
+        trace!("is_applied");
+
+        sqlx::query("SELECT name FROM migration WHERE name = $1")
+            .bind(name)
+            .fetch_optional(pool)
+            .await
+            .map(|row| row.is_some())
+    }
+
+    #[instrument(skip(executor))]
+    pub async fn insert<'e>(
+        executor: impl Executor<'e, Database = Postgres>,
+        name: &str,
+    ) -> Result<PgQueryResult, Error> {
+        trace!("insert");
+
+        sqlx::query("INSERT INTO migration (name, applied_at) VALUES ($1, $2)")
+            .bind(name)
+            .bind(Utc::now())
+            .execute(executor)
+            .await
+    }
+
+    /// Есть ли в таблице столбец. Нужно, чтобы миграция, прерванная на середине, могла продолжиться.
+    #[instrument(skip(executor))]
+    pub async fn has_column<'e>(
+        executor: impl Executor<'e, Database = Postgres>,
+        table: &str,
+        column: &str,
+    ) -> Result<bool, Error> {
+        trace!("has_column");
+
+        sqlx::query(
+            "SELECT column_name FROM information_schema.columns WHERE table_schema = current_schema() AND table_name = $1 AND column_name = $2",
+        )
+        .bind(table)
+        .bind(column)
+        .fetch_optional(executor)
+        .await
+        .map(|row| row.is_some())
+    }
+}
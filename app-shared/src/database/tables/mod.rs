@@ -1,22 +1,32 @@
 ﻿mod account;
 mod account_integrations;
+mod account_mfa;
+mod account_note;
 mod bug_message;
 mod donation_tier;
 mod feature_message;
+mod integration_link;
 mod journal_entry;
+mod migration;
 mod prelude;
 mod role;
 mod session;
 mod token;
+mod username_change;
 mod webhook;
 
-pub use account::AccountTable;
+pub use account::{AccountSearchFilter, AccountTable};
 pub use account_integrations::AccountIntegrationsTable;
+pub use account_mfa::AccountMfaTable;
+pub use account_note::AccountNoteTable;
 pub use bug_message::BugMessageTable;
 pub use donation_tier::DonationTierTable;
 pub use feature_message::FeatureMessageTable;
+pub use integration_link::IntegrationLinkTable;
 pub use journal_entry::JournalEntryTable;
+pub use migration::MigrationTable;
 pub use role::RoleTable;
 pub use session::SessionTable;
 pub use token::TokenTable;
+pub use username_change::UsernameChangeTable;
 pub use webhook::WebhookTable;
@@ -1,5 +1,5 @@
 ﻿pub use crate::prelude::*;
 pub use sqlx::{
     postgres::{PgQueryResult, PgRow},
-    Error, Pool, Postgres, Row,
+    Error, Executor, Pool, Postgres, Row, Transaction,
 };
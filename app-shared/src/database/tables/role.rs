@@ -28,16 +28,30 @@ create table if not exists role
         .await
     }
 
+    #[instrument]
+    pub async fn add_requires_mfa(pool: &Pool<Postgres>) -> Result<PgQueryResult, Error> {
+        trace!("add_requires_mfa");
+
+        sqlx::query(
+            "ALTER TABLE role ADD COLUMN IF NOT EXISTS requires_mfa boolean NOT NULL DEFAULT false",
+        )
+        .execute(pool)
+        .await
+    }
+
     #[instrument]
     pub async fn insert(pool: &Pool<Postgres>, role: Role) -> Result<PgQueryResult, Error> {
         trace!("insert");
 
-        sqlx::query("INSERT INTO role (id, name, color, rights) VALUES (DEFAULT, $1, $2, $3)")
-            .bind(role.name)
-            .bind(role.color.to_u24() as i32)
-            .bind(serde_json::to_value(&role.rights).unwrap())
-            .execute(pool)
-            .await
+        sqlx::query(
+            "INSERT INTO role (id, name, color, rights, requires_mfa) VALUES (DEFAULT, $1, $2, $3, $4)",
+        )
+        .bind(role.name)
+        .bind(role.color.to_u24() as i32)
+        .bind(serde_json::to_value(&role.rights).unwrap())
+        .bind(role.requires_mfa)
+        .execute(pool)
+        .await
     }
 
     #[instrument]
@@ -72,6 +86,22 @@ create table if not exists role
             .await
     }
 
+    #[instrument]
+    pub async fn update_requires_mfa(
+        pool: &Pool<Postgres>,
+        id: RoleId,
+        requires_mfa: bool,
+    ) -> Result<bool, Error> {
+        trace!("update_requires_mfa");
+
+        sqlx::query("UPDATE role SET requires_mfa = $2 WHERE id = $1")
+            .bind(id.0)
+            .bind(requires_mfa)
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected() > 0)
+    }
+
     #[instrument(skip(row))]
     fn map(row: PgRow) -> Role {
         Role {
@@ -79,6 +109,7 @@ create table if not exists role
             name: row.get::<String, _>("name"),
             color: HexColor::from_u24(row.get::<i64, _>("color") as u32),
             rights: serde_json::from_value(row.get::<serde_json::Value, _>("rights")).unwrap(),
+            requires_mfa: row.get("requires_mfa"),
         }
     }
 }
@@ -1,5 +1,6 @@
 use super::prelude::*;
-use crate::models::{AccountId, Secret, Session};
+use super::MigrationTable;
+use crate::models::{AccountId, Secret, SecretHash, Session, SessionId};
 use chrono::{DateTime, Utc};
 
 pub struct SessionTable;
@@ -30,22 +31,97 @@ create table if not exists session
         .await
     }
 
+    /// Секреты старых сессий нельзя захэшировать с выводом API и CSRF секретов,
+    /// поэтому такие сессии и их токены удаляются.
+    /// Должно выполняться до хэширования токенов, пока их секреты ещё можно сравнить.
     #[instrument]
-    pub async fn insert(pool: &Pool<Postgres>, session: Session) -> Result<PgQueryResult, Error> {
+    pub async fn hash_secrets(transaction: &mut Transaction<'_, Postgres>) -> Result<(), Error> {
+        trace!("hash_secrets");
+
+        if !MigrationTable::has_column(&mut *transaction, "session", "secret").await? {
+            return Ok(());
+        }
+
+        let api_secrets = sqlx::query("SELECT api_secret FROM session")
+            .map(|row: PgRow| Secret(row.get::<String, _>("api_secret")))
+            .fetch_all(&mut *transaction)
+            .await?;
+
+        // Токены могли захэшировать раньше, если прошлая попытка миграции прервалась.
+        let is_token_hashed =
+            !MigrationTable::has_column(&mut *transaction, "token", "secret").await?;
+
+        for api_secret in api_secrets {
+            let (query, secret) = if is_token_hashed {
+                (
+                    "DELETE FROM token WHERE secret_hash = $1",
+                    api_secret.hash().0,
+                )
+            } else {
+                ("DELETE FROM token WHERE secret = $1", api_secret.0)
+            };
+
+            sqlx::query(query)
+                .bind(secret)
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        sqlx::query("DELETE FROM session")
+            .execute(&mut *transaction)
+            .await?;
+
+        for (column, new_column) in [
+            ("secret", "secret_hash"),
+            ("api_secret", "api_secret_hash"),
+            ("csrf_secret", "csrf_secret_hash"),
+        ] {
+            sqlx::query(&format!(
+                "alter table session rename column {column} to {new_column}"
+            ))
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Несекретный ID, по которому сессией можно управлять, и название устройства.
+    #[instrument]
+    pub async fn add_id_and_label(pool: &Pool<Postgres>) -> Result<(), Error> {
+        trace!("add_id_and_label");
+
+        let mut transaction = pool.begin().await?;
+
+        for query in [
+            "ALTER TABLE session ADD COLUMN IF NOT EXISTS id bigserial",
+            "ALTER TABLE session ADD COLUMN IF NOT EXISTS label text",
+            "CREATE UNIQUE INDEX IF NOT EXISTS session_id_uindex ON session (id)",
+        ] {
+            sqlx::query(query).execute(&mut transaction).await?;
+        }
+
+        transaction.commit().await
+    }
+
+    #[instrument]
+    pub async fn insert(pool: &Pool<Postgres>, session: Session) -> Result<Session, Error> {
         trace!("insert");
 
         sqlx::query(
-            "INSERT INTO session (secret, api_secret, csrf_secret, account_id, created_at, expiration, user_agent, ip) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            "INSERT INTO session (id, secret_hash, api_secret_hash, csrf_secret_hash, account_id, created_at, expiration, user_agent, ip, label) VALUES (DEFAULT, $1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING *",
         )
-            .bind(session.secret.0)
-            .bind(session.api_secret.0)
-            .bind(session.csrf_token.0)
+            .bind(session.secret_hash.0)
+            .bind(session.api_secret_hash.0)
+            .bind(session.csrf_secret_hash.0)
             .bind(session.account_id.0)
             .bind(session.created_at)
             .bind(session.expiration)
             .bind(session.user_agent)
             .bind(session.ip)
-            .execute(pool)
+            .bind(session.label)
+            .map(Self::map)
+            .fetch_one(pool)
             .await
     }
 
@@ -60,32 +136,61 @@ create table if not exists session
     }
 
     #[instrument]
-    pub async fn delete_by_secret(
+    pub async fn delete_by_secret_hash(
         pool: &Pool<Postgres>,
-        session_secret: Secret,
+        secret_hash: SecretHash,
     ) -> Result<PgQueryResult, Error> {
-        trace!("delete_by_secret");
+        trace!("delete_by_secret_hash");
 
-        sqlx::query("DELETE FROM session WHERE secret = $1")
-            .bind(session_secret.0)
+        sqlx::query("DELETE FROM session WHERE secret_hash = $1")
+            .bind(secret_hash.0)
             .execute(pool)
             .await
     }
 
     #[instrument]
-    pub async fn find_by_secret(
+    pub async fn find_by_secret_hash(
+        pool: &Pool<Postgres>,
+        secret_hash: SecretHash,
+    ) -> Result<Option<Session>, Error> {
+        trace!("find_by_secret_hash");
+
+        sqlx::query("SELECT * FROM session WHERE secret_hash = $1")
+            .bind(secret_hash.0)
+            .map(Self::map)
+            .fetch_optional(pool)
+            .await
+    }
+
+    #[instrument]
+    pub async fn find_by_id(
         pool: &Pool<Postgres>,
-        session_secret: Secret,
+        id: SessionId,
     ) -> Result<Option<Session>, Error> {
-        trace!("find_by_secret");
+        trace!("find_by_id");
 
-        sqlx::query("SELECT * FROM session WHERE secret = $1")
-            .bind(session_secret.0)
+        sqlx::query("SELECT * FROM session WHERE id = $1")
+            .bind(id.0)
             .map(Self::map)
             .fetch_optional(pool)
             .await
     }
 
+    #[instrument]
+    pub async fn update_label(
+        pool: &Pool<Postgres>,
+        id: SessionId,
+        label: Option<String>,
+    ) -> Result<PgQueryResult, Error> {
+        trace!("update_label");
+
+        sqlx::query("UPDATE session SET label = $2 WHERE id = $1")
+            .bind(id.0)
+            .bind(label)
+            .execute(pool)
+            .await
+    }
+
     pub async fn find_by_account_id(
         pool: &Pool<Postgres>,
         account_id: AccountId,
@@ -100,14 +205,14 @@ create table if not exists session
     }
 
     #[instrument]
-    pub async fn find_by_csrf_secret(
+    pub async fn find_by_csrf_secret_hash(
         pool: &Pool<Postgres>,
-        csrf_secret: Secret,
+        csrf_secret_hash: SecretHash,
     ) -> Result<Option<Session>, Error> {
-        trace!("find_by_csrf_secret");
+        trace!("find_by_csrf_secret_hash");
 
-        sqlx::query("SELECT * FROM session WHERE csrf_secret = $1")
-            .bind(csrf_secret.0)
+        sqlx::query("SELECT * FROM session WHERE csrf_secret_hash = $1")
+            .bind(csrf_secret_hash.0)
             .map(Self::map)
             .fetch_optional(pool)
             .await
@@ -116,14 +221,16 @@ create table if not exists session
     #[instrument(skip(row))]
     fn map(row: PgRow) -> Session {
         Session {
-            secret: Secret(row.get::<String, _>("secret")),
-            api_secret: Secret(row.get::<String, _>("api_secret")),
-            csrf_token: Secret(row.get::<String, _>("csrf_secret")),
+            id: SessionId(row.get::<i64, _>("id")),
+            secret_hash: SecretHash(row.get::<String, _>("secret_hash")),
+            api_secret_hash: SecretHash(row.get::<String, _>("api_secret_hash")),
+            csrf_secret_hash: SecretHash(row.get::<String, _>("csrf_secret_hash")),
             account_id: AccountId(row.get::<i64, _>("account_id")),
             created_at: row.get::<DateTime<Utc>, _>("created_at"),
             expiration: row.get::<DateTime<Utc>, _>("expiration"),
             user_agent: row.get::<String, _>("user_agent"),
             ip: row.get::<String, _>("ip"),
+            label: row.get::<Option<String>, _>("label"),
         }
     }
 }
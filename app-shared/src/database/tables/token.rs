@@ -1,9 +1,10 @@
 ﻿use chrono::Utc;
 
 use super::prelude::*;
+use super::MigrationTable;
 use crate::{
     chrono::DateTime,
-    models::{AccountId, ApiToken, Rights, Secret},
+    models::{AccountId, ApiToken, ApiTokenId, Rights, Secret, SecretHash},
     serde_json,
 };
 
@@ -34,75 +35,265 @@ create table if not exists token
     }
 
     #[instrument]
-    pub async fn insert(pool: &Pool<Postgres>, token: ApiToken) -> Result<PgQueryResult, Error> {
+    pub async fn add_metadata(pool: &Pool<Postgres>) -> Result<PgQueryResult, Error> {
+        trace!("add_metadata");
+
+        sqlx::query(
+            "
+alter table token
+    add column if not exists id           bigserial   not null
+        constraint token_id_key
+            unique,
+    add column if not exists label        text        not null default '',
+    add column if not exists last_used_at timestamptz null,
+    add column if not exists last_used_ip text        null;
+            ",
+        )
+        .execute(pool)
+        .await
+    }
+
+    /// Добавляет отметку рутовых токенов. Рутовыми считаются системные токены из конфига
+    /// и системные токены со всеми правами, которые были до появления отметки.
+    #[instrument]
+    pub async fn add_root_flag(transaction: &mut Transaction<'_, Postgres>) -> Result<(), Error> {
+        trace!("add_root_flag");
+
+        sqlx::query(
+            "alter table token add column if not exists is_root bool not null default false",
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        sqlx::query(
+            "
+update token
+set is_root = true
+where creator is null
+  and (label in ('root', '')
+    or ((rights ->> 'user')::bigint & 15 = 15 and (rights ->> 'token')::bigint & 15 = 15))
+            ",
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Заменяет секреты токенов их хэшами.
+    #[instrument]
+    pub async fn hash_secrets(transaction: &mut Transaction<'_, Postgres>) -> Result<(), Error> {
+        trace!("hash_secrets");
+
+        if !MigrationTable::has_column(&mut *transaction, "token", "secret").await? {
+            return Ok(());
+        }
+
+        let secrets = sqlx::query("SELECT secret FROM token")
+            .map(|row: PgRow| Secret(row.get::<String, _>("secret")))
+            .fetch_all(&mut *transaction)
+            .await?;
+
+        for secret in secrets {
+            sqlx::query("UPDATE token SET secret = $1 WHERE secret = $2")
+                .bind(secret.hash().0)
+                .bind(secret.0)
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        sqlx::query("alter table token rename column secret to secret_hash")
+            .execute(&mut *transaction)
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument]
+    pub async fn insert(pool: &Pool<Postgres>, token: ApiToken) -> Result<ApiToken, Error> {
         trace!("insert");
 
         sqlx::query(
-            "INSERT INTO token (secret, expiration, rights, created_at, creator, is_service) VALUES ($1, $2, $3, $4, $5, $6)",
+            "INSERT INTO token (id, secret_hash, label, expiration, rights, created_at, creator, is_service, is_root) VALUES (DEFAULT, $1, $2, $3, $4, $5, $6, $7, $8) RETURNING *",
         )
-            .bind(token.secret.0)
+            .bind(token.secret_hash.0)
+            .bind(token.label)
             .bind(token.expiration)
             .bind(serde_json::to_value(&token.rights).unwrap())
             .bind(token.created_at)
             .bind(token.creator.map(|account_id| account_id.0))
             .bind(token.is_service)
+            .bind(token.is_root)
+            .map(Self::map)
+            .fetch_one(pool)
+            .await
+    }
+
+    #[instrument]
+    pub async fn delete_by_secret_hash(
+        pool: &Pool<Postgres>,
+        secret_hash: SecretHash,
+    ) -> Result<PgQueryResult, Error> {
+        trace!("delete_by_secret_hash");
+
+        sqlx::query("DELETE FROM token WHERE secret_hash = $1")
+            .bind(secret_hash.0)
+            .execute(pool)
+            .await
+    }
+
+    #[instrument]
+    pub async fn update_secret_hash(
+        pool: &Pool<Postgres>,
+        token_id: ApiTokenId,
+        new_secret_hash: SecretHash,
+    ) -> Result<PgQueryResult, Error> {
+        trace!("update_secret_hash");
+
+        sqlx::query("UPDATE token SET secret_hash = $1 WHERE id = $2")
+            .bind(new_secret_hash.0)
+            .bind(token_id.0)
             .execute(pool)
             .await
     }
 
     #[instrument]
-    pub async fn delete_by_secret(
+    pub async fn update_rights_by_secret_hash(
         pool: &Pool<Postgres>,
-        api_secret: Secret,
+        secret_hash: SecretHash,
+        rights: Rights,
     ) -> Result<PgQueryResult, Error> {
-        trace!("delete_by_secret");
+        trace!("update_rights_by_secret_hash");
 
-        sqlx::query("DELETE FROM token WHERE secret = $1")
-            .bind(api_secret.0)
+        sqlx::query("UPDATE token SET rights = $1 WHERE secret_hash = $2")
+            .bind(serde_json::to_value(&rights).unwrap())
+            .bind(secret_hash.0)
             .execute(pool)
             .await
     }
 
+    /// Выдаёт всем рутовым токенам указанные права.
     #[instrument]
-    pub async fn update(
+    pub async fn update_root_rights(
         pool: &Pool<Postgres>,
-        new_secret: Secret,
-        new_expiration: Option<DateTime<Utc>>,
-        new_rights: Rights,
+        rights: Rights,
     ) -> Result<PgQueryResult, Error> {
-        trace!("update");
+        trace!("update_root_rights");
 
-        sqlx::query("UPDATE token SET secret = $1, expiration = $2, rights = $3 WHERE id = 1")
-            .bind(new_secret.0)
-            .bind(new_expiration.map(|date| date.to_string()))
-            .bind(serde_json::to_value(new_rights).unwrap())
+        sqlx::query("UPDATE token SET rights = $1 WHERE is_root")
+            .bind(serde_json::to_value(&rights).unwrap())
             .execute(pool)
             .await
     }
 
     #[instrument]
-    pub async fn find_by_secret(
+    pub async fn find_by_secret_hash(
+        pool: &Pool<Postgres>,
+        secret_hash: SecretHash,
+    ) -> Result<Option<ApiToken>, Error> {
+        trace!("find_by_secret_hash");
+
+        sqlx::query("SELECT * FROM token WHERE secret_hash = $1")
+            .bind(secret_hash.0)
+            .map(Self::map)
+            .fetch_optional(pool)
+            .await
+    }
+
+    #[instrument]
+    pub async fn find_by_id(
         pool: &Pool<Postgres>,
-        api_secret: Secret,
+        token_id: ApiTokenId,
     ) -> Result<Option<ApiToken>, Error> {
-        trace!("find_by_secret");
+        trace!("find_by_id");
 
-        sqlx::query("SELECT * FROM token WHERE secret = $1")
-            .bind(api_secret.0)
+        sqlx::query("SELECT * FROM token WHERE id = $1")
+            .bind(token_id.0)
             .map(Self::map)
             .fetch_optional(pool)
             .await
     }
 
+    #[instrument]
+    pub async fn delete_by_id(
+        pool: &Pool<Postgres>,
+        token_id: ApiTokenId,
+    ) -> Result<PgQueryResult, Error> {
+        trace!("delete_by_id");
+
+        sqlx::query("DELETE FROM token WHERE id = $1")
+            .bind(token_id.0)
+            .execute(pool)
+            .await
+    }
+
+    /// Возвращает токены аккаунта, не считая токенов его сессий.
+    #[instrument]
+    pub async fn find_by_creator(
+        pool: &Pool<Postgres>,
+        account_id: AccountId,
+    ) -> Result<Vec<ApiToken>, Error> {
+        trace!("find_by_creator");
+
+        sqlx::query(
+            "
+SELECT * FROM token
+WHERE creator = $1
+  AND secret_hash NOT IN (SELECT api_secret_hash FROM session)
+ORDER BY created_at DESC
+",
+        )
+        .bind(account_id.0)
+        .map(Self::map)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Возвращает токены, не принадлежащие ни одному аккаунту.
+    #[instrument]
+    pub async fn find_system(pool: &Pool<Postgres>) -> Result<Vec<ApiToken>, Error> {
+        trace!("find_system");
+
+        sqlx::query("SELECT * FROM token WHERE creator IS NULL ORDER BY created_at")
+            .map(Self::map)
+            .fetch_all(pool)
+            .await
+    }
+
+    #[instrument]
+    pub async fn update_usage(
+        pool: &Pool<Postgres>,
+        token_id: ApiTokenId,
+        used_at: DateTime<Utc>,
+        ip: Option<String>,
+    ) -> Result<PgQueryResult, Error> {
+        trace!("update_usage");
+
+        sqlx::query(
+            "UPDATE token SET last_used_at = $1, last_used_ip = COALESCE($2, last_used_ip) WHERE id = $3",
+        )
+        .bind(used_at)
+        .bind(ip)
+        .bind(token_id.0)
+        .execute(pool)
+        .await
+    }
+
     #[instrument(skip(row))]
     fn map(row: PgRow) -> ApiToken {
         ApiToken {
-            secret: Secret(row.get::<String, _>("secret")),
+            id: ApiTokenId(row.get::<i64, _>("id")),
+            secret: None,
+            secret_hash: SecretHash(row.get::<String, _>("secret_hash")),
+            label: row.get::<String, _>("label"),
             expiration: row.get::<Option<DateTime<Utc>>, _>("expiration"),
             rights: serde_json::from_value(row.get::<serde_json::Value, _>("rights")).unwrap(),
             created_at: row.get::<DateTime<Utc>, _>("created_at"),
             creator: row.get::<Option<i64>, _>("creator").map(AccountId),
             is_service: row.get::<bool, _>("is_service"),
+            is_root: row.get::<bool, _>("is_root"),
+            last_used_at: row.get::<Option<DateTime<Utc>>, _>("last_used_at"),
+            last_used_ip: row.get::<Option<String>, _>("last_used_ip"),
         }
     }
 }
@@ -0,0 +1,85 @@
+﻿use super::prelude::*;
+use crate::models::{AccountId, UsernameChange};
+use chrono::{DateTime, Utc};
+
+/// История смены имён пользователей.
+pub struct UsernameChangeTable;
+
+impl UsernameChangeTable {
+    #[instrument]
+    pub async fn create(pool: &Pool<Postgres>) -> Result<(), Error> {
+        trace!("create");
+
+        for query in [
+            "
+create table if not exists username_change
+(
+    id           bigserial   not null
+        constraint username_change_pk
+            primary key,
+    account_id   bigint      not null,
+    old_username text        not null,
+    new_username text        not null,
+    changed_at   timestamptz not null
+);
+",
+            "CREATE INDEX IF NOT EXISTS username_change_account_id_idx ON username_change (account_id)",
+        ] {
+            sqlx::query(query).execute(pool).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Меняет имя пользователя и записывает смену в историю.
+    #[instrument]
+    pub async fn change(
+        pool: &Pool<Postgres>,
+        account_id: AccountId,
+        new_username: String,
+        changed_at: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        trace!("change");
+
+        let mut transaction = pool.begin().await?;
+
+        sqlx::query("INSERT INTO username_change (account_id, old_username, new_username, changed_at) SELECT id, username, $2, $3 FROM account WHERE id = $1")
+            .bind(account_id.0)
+            .bind(&new_username)
+            .bind(changed_at)
+            .execute(&mut transaction)
+            .await?;
+
+        sqlx::query("UPDATE account SET username = $2 WHERE id = $1")
+            .bind(account_id.0)
+            .bind(new_username)
+            .execute(&mut transaction)
+            .await?;
+
+        transaction.commit().await
+    }
+
+    #[instrument]
+    pub async fn find_by_account(
+        pool: &Pool<Postgres>,
+        account_id: AccountId,
+    ) -> Result<Vec<UsernameChange>, Error> {
+        trace!("find_by_account");
+
+        sqlx::query("SELECT * FROM username_change WHERE account_id = $1 ORDER BY id DESC")
+            .bind(account_id.0)
+            .map(Self::map)
+            .fetch_all(pool)
+            .await
+    }
+
+    #[instrument(skip(row))]
+    fn map(row: PgRow) -> UsernameChange {
+        UsernameChange {
+            account_id: AccountId(row.get::<i64, _>("account_id")),
+            old_username: row.get("old_username"),
+            new_username: row.get("new_username"),
+            changed_at: row.get("changed_at"),
+        }
+    }
+}
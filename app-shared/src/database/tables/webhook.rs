@@ -1,5 +1,6 @@
 ﻿use super::prelude::*;
-use crate::models::{Secret, ServiceId, Webhook, WebhookConfiguration};
+use super::MigrationTable;
+use crate::models::{Secret, SecretHash, ServiceId, Webhook, WebhookConfiguration};
 use chrono::{DateTime, Utc};
 use serde_json;
 
@@ -29,15 +30,44 @@ create table if not exists webhook
         .await
     }
 
+    /// Заменяет секреты вебхуков их хэшами.
+    #[instrument]
+    pub async fn hash_secrets(transaction: &mut Transaction<'_, Postgres>) -> Result<(), Error> {
+        trace!("hash_secrets");
+
+        if !MigrationTable::has_column(&mut *transaction, "webhook", "secret").await? {
+            return Ok(());
+        }
+
+        let secrets = sqlx::query("SELECT secret FROM webhook")
+            .map(|row: PgRow| Secret(row.get::<String, _>("secret")))
+            .fetch_all(&mut *transaction)
+            .await?;
+
+        for secret in secrets {
+            sqlx::query("UPDATE webhook SET secret = $1 WHERE secret = $2")
+                .bind(secret.hash().0)
+                .bind(secret.0)
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        sqlx::query("alter table webhook rename column secret to secret_hash")
+            .execute(&mut *transaction)
+            .await?;
+
+        Ok(())
+    }
+
     #[instrument]
     pub async fn insert(pool: &Pool<Postgres>, webhook: Webhook) -> Result<PgQueryResult, Error> {
         trace!("insert");
 
         sqlx::query(
-            "INSERT INTO webhook (id, name, secret, service_id, created_at, configuration) VALUES (DEFAULT, $1, $2, $3, $4, $5)",
+            "INSERT INTO webhook (id, name, secret_hash, service_id, created_at, configuration) VALUES (DEFAULT, $1, $2, $3, $4, $5)",
         )
             .bind(webhook.name)
-            .bind(webhook.secret.0)
+            .bind(webhook.secret_hash.0)
             .bind(webhook.service_id.0)
             .bind(webhook.created_at)
             .bind(webhook.configuration.0)
@@ -46,28 +76,28 @@ create table if not exists webhook
     }
 
     #[instrument]
-    pub async fn find_by_secret(
+    pub async fn find_by_secret_hash(
         pool: &Pool<Postgres>,
-        secret: Secret,
+        secret_hash: SecretHash,
     ) -> Result<Option<Webhook>, Error> {
-        trace!("find_by_secret");
+        trace!("find_by_secret_hash");
 
-        sqlx::query("SELECT * FROM webhook WHERE secret = $1")
-            .bind(secret.0)
+        sqlx::query("SELECT * FROM webhook WHERE secret_hash = $1")
+            .bind(secret_hash.0)
             .map(Self::map)
             .fetch_optional(pool)
             .await
     }
 
     #[instrument]
-    pub async fn delete_by_secret(
+    pub async fn delete_by_secret_hash(
         pool: &Pool<Postgres>,
-        secret: Secret,
+        secret_hash: SecretHash,
     ) -> Result<PgQueryResult, Error> {
-        trace!("delete_by_secret");
+        trace!("delete_by_secret_hash");
 
-        sqlx::query("DELETE FROM webhook WHERE secret = $1")
-            .bind(secret.0)
+        sqlx::query("DELETE FROM webhook WHERE secret_hash = $1")
+            .bind(secret_hash.0)
             .execute(pool)
             .await
     }
@@ -76,7 +106,8 @@ create table if not exists webhook
     fn map(row: PgRow) -> Webhook {
         Webhook {
             name: row.get::<String, _>("name"),
-            secret: Secret(row.get::<String, _>("secret")),
+            secret: None,
+            secret_hash: SecretHash(row.get::<String, _>("secret_hash")),
             service_id: ServiceId(row.get::<String, _>("service_id")),
             created_at: row.get::<DateTime<Utc>, _>("created_at"),
             configuration: WebhookConfiguration(row.get::<serde_json::Value, _>("configuration")),
@@ -6,4 +6,7 @@ use serenity::model::id::GuildId;
 pub struct DiscordConfig {
     pub guild_id: GuildId,
     pub token: String,
+    /// Адрес сайта, на который бот ссылается в сообщениях.
+    #[serde(default)]
+    pub site_url: Option<String>,
 }
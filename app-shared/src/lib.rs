@@ -25,6 +25,7 @@ pub use hex_color;
 pub use itertools;
 pub use octocrab;
 pub use parking_lot;
+pub use regex;
 pub use serde;
 pub use serde_json;
 pub use serde_yaml;
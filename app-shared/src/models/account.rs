@@ -1,6 +1,6 @@
 ﻿use std::hash::Hash;
 
-use crate::models::{AccountIntegrations, Role};
+use crate::models::{AccountIntegrations, ProfileVisibility, Role};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -19,6 +19,7 @@ pub struct Account {
     pub roles: Vec<Role>,
     pub integrations: AccountIntegrations,
     pub donation_tier: Option<DonationTier>,
+    pub profile_visibility: ProfileVisibility,
 }
 
 impl PartialEq for Account {
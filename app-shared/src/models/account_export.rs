@@ -0,0 +1,45 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    Account, AccountNote, ApiTokenInfo, BugReport, FeatureVote, IntegrationLink, JournalEntry,
+    Session,
+};
+
+/// Все данные, связанные с аккаунтом, для выгрузки владельцу.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountExport {
+    pub exported_at: DateTime<Utc>,
+    pub account: Account,
+    pub integration_links: Vec<IntegrationLink>,
+    pub sessions: Vec<ExportedSession>,
+    pub api_tokens: Vec<ApiTokenInfo>,
+    /// Записи, где аккаунт выступает объектом или субъектом.
+    pub journal: Vec<JournalEntry>,
+    pub feature_votes: Vec<FeatureVote>,
+    pub bug_reports: Vec<BugReport>,
+    /// Только заметки, которые администрация разрешила показывать.
+    pub notes: Vec<AccountNote>,
+}
+
+/// Сессия без хешей секретов.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedSession {
+    pub created_at: DateTime<Utc>,
+    pub expiration: DateTime<Utc>,
+    pub user_agent: String,
+    pub ip: String,
+    pub label: Option<String>,
+}
+
+impl From<Session> for ExportedSession {
+    fn from(session: Session) -> Self {
+        Self {
+            created_at: session.created_at,
+            expiration: session.expiration,
+            user_agent: session.user_agent,
+            ip: session.ip,
+            label: session.label,
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::models::AccountId;
+use crate::models::{AccountId, GithubUserId};
 use crate::prelude::{ByondUserId, DiscordUserId, SS14UserId};
 use serde::{Deserialize, Serialize};
 
@@ -8,6 +8,9 @@ pub struct AccountIntegrations {
     pub discord_user_id: DiscordUserId,
     pub byond_ckey: Option<ByondUserId>,
     pub ss14_guid: Option<SS14UserId>,
+    pub github_user_id: Option<GithubUserId>,
+    /// Логин GitHub на момент привязки, только для отображения.
+    pub github_login: Option<String>,
 }
 
 impl AccountIntegrations {
@@ -16,12 +19,16 @@ impl AccountIntegrations {
         discord_user_id: DiscordUserId,
         byond_ckey: Option<ByondUserId>,
         ss14_guid: Option<SS14UserId>,
+        github_user_id: Option<GithubUserId>,
+        github_login: Option<String>,
     ) -> Self {
         Self {
             account_id,
             discord_user_id,
             byond_ckey,
             ss14_guid,
+            github_user_id,
+            github_login,
         }
     }
 }
@@ -0,0 +1,61 @@
+﻿use crate::models::{AccountId, GameServerId};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Hash, Ord, PartialOrd, Eq, PartialEq)]
+#[serde(transparent)]
+pub struct AccountNoteId(pub i64);
+
+/// Важность заметки о пользователе.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Hash, Ord, PartialOrd, Eq, PartialEq)]
+pub enum NoteSeverity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl NoteSeverity {
+    /// Имя важности, под которым она хранится в базе данных.
+    pub fn name(&self) -> &'static str {
+        match self {
+            NoteSeverity::Info => "Info",
+            NoteSeverity::Warning => "Warning",
+            NoteSeverity::Critical => "Critical",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Info" => Some(NoteSeverity::Info),
+            "Warning" => Some(NoteSeverity::Warning),
+            "Critical" => Some(NoteSeverity::Critical),
+            _ => None,
+        }
+    }
+}
+
+/// Заметка администрации о пользователе.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountNote {
+    pub id: AccountNoteId,
+    pub account_id: AccountId,
+    /// `None` - заметку оставила система или сервисный токен.
+    pub author: Option<AccountId>,
+    pub created_at: DateTime<Utc>,
+    pub severity: NoteSeverity,
+    /// Сервер, к которому относится заметка, `None` - ко всем.
+    pub server_id: Option<GameServerId>,
+    pub text: String,
+    /// Попадает ли заметка в выгрузку данных самого пользователя.
+    pub shareable: bool,
+}
+
+impl AccountNote {
+    /// Относится ли заметка к серверу, заметки без сервера относятся ко всем.
+    pub fn applies_to(&self, server_id: Option<&GameServerId>) -> bool {
+        match (&self.server_id, server_id) {
+            (Some(note_server_id), Some(server_id)) => note_server_id == server_id,
+            _ => true,
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Account, DonationTierId, RoleId};
+
+/// Постраничный поиск аккаунтов.
+/// `query` ищет по началу имени пользователя, Discord ID, ckey и SS14 GUID.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountSearch {
+    pub query: Option<String>,
+    pub role_id: Option<RoleId>,
+    pub donation_tier_id: Option<DonationTierId>,
+    pub offset: usize,
+    pub max_count: usize,
+    pub accounts: Vec<Account>,
+    pub total: usize,
+}
+
+impl AccountSearch {
+    pub fn new(
+        query: Option<String>,
+        role_id: Option<RoleId>,
+        donation_tier_id: Option<DonationTierId>,
+        offset: usize,
+        max: usize,
+    ) -> Self {
+        Self {
+            query: query.filter(|query| !query.trim().is_empty()),
+            role_id,
+            donation_tier_id,
+            offset,
+            max_count: max,
+            accounts: Vec::new(),
+            total: 0,
+        }
+    }
+}
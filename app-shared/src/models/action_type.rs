@@ -1,17 +1,108 @@
 use serde::{Deserialize, Serialize};
 
-use super::{ByondCkey, RoleId, SS14Guid};
+use super::{
+    AccountId, AccountNoteId, ApiTokenId, ByondCkey, FeatureVoteDescriptor, GithubUserId,
+    JournalEntryId, RoleId, SS14Guid,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "data")]
 pub enum ActionType {
     AccountCreated,
-    ByondConnected { ckey: ByondCkey },
+    ByondConnected {
+        #[serde(deserialize_with = "ByondCkey::deserialize_stored")]
+        ckey: ByondCkey,
+    },
     SS14Connected { ss14_guid: SS14Guid },
+    ByondDisconnected {
+        #[serde(deserialize_with = "ByondCkey::deserialize_stored")]
+        ckey: ByondCkey,
+    },
+    SS14Disconnected { ss14_guid: SS14Guid },
+    GithubConnected { github_user_id: GithubUserId },
+    GithubDisconnected { github_user_id: GithubUserId },
     RoleAdded { role_id: RoleId },
     RoleRemoved { role_id: RoleId },
     WebhookDeleted,
     WebhookCreated,
+    /// Старые записи без id и названия токена, новые пишутся как `ApiTokenIssued`.
     ApiTokenCreated,
+    /// Старые записи без id и названия токена, новые пишутся как `ApiTokenRevoked`.
     ApiTokenDeleted,
+    ApiTokenIssued { token_id: ApiTokenId, label: String },
+    ApiTokenRevoked { token_id: ApiTokenId, label: String },
+    ApiTokenSecretRotated { token_id: ApiTokenId, label: String },
+    AccountsMerged { source_id: AccountId },
+    AccountDeleted,
+    UsernameChanged { old: String, new: String },
+    NoteAdded { note_id: AccountNoteId },
+    NoteDeleted { note_id: AccountNoteId },
+    SessionCreated { ip: String, user_agent: String },
+    SessionRevoked { ip: String, user_agent: String },
+    AvatarChanged { old: String, new: String },
+    TFATokenIssued,
+    TFAAttemptFailed { ip: Option<String>, lockout_seconds: Option<i64> },
+    MfaEnabled,
+    MfaDisabled,
+    MfaRecoveryCodeUsed,
+    MfaAttemptFailed { ip: Option<String> },
+    FeatureVoteEnded { descriptor: FeatureVoteDescriptor },
+    /// Из записей удалённого аккаунта убраны личные данные, их хэши больше не сходятся.
+    JournalEntriesRedacted { entry_ids: Vec<JournalEntryId> },
+}
+
+impl ActionType {
+    /// То же действие без ckey, guid, IP и прочих личных данных.
+    /// `None` - личных данных в действии нет.
+    pub fn redacted(&self) -> Option<Self> {
+        let action = match self {
+            Self::ByondConnected { .. } => Self::ByondConnected {
+                ckey: ByondCkey(String::new()),
+            },
+            Self::ByondDisconnected { .. } => Self::ByondDisconnected {
+                ckey: ByondCkey(String::new()),
+            },
+            Self::SS14Connected { .. } => Self::SS14Connected {
+                ss14_guid: SS14Guid(String::new()),
+            },
+            Self::SS14Disconnected { .. } => Self::SS14Disconnected {
+                ss14_guid: SS14Guid(String::new()),
+            },
+            Self::GithubConnected { .. } => Self::GithubConnected {
+                github_user_id: GithubUserId(0),
+            },
+            Self::GithubDisconnected { .. } => Self::GithubDisconnected {
+                github_user_id: GithubUserId(0),
+            },
+            Self::UsernameChanged { .. } => Self::UsernameChanged {
+                old: String::new(),
+                new: String::new(),
+            },
+            Self::AvatarChanged { .. } => Self::AvatarChanged {
+                old: String::new(),
+                new: String::new(),
+            },
+            Self::SessionCreated { .. } => Self::SessionCreated {
+                ip: String::new(),
+                user_agent: String::new(),
+            },
+            Self::SessionRevoked { .. } => Self::SessionRevoked {
+                ip: String::new(),
+                user_agent: String::new(),
+            },
+            Self::TFAAttemptFailed {
+                ip: Some(_),
+                lockout_seconds,
+            } => Self::TFAAttemptFailed {
+                ip: Some(String::new()),
+                lockout_seconds: *lockout_seconds,
+            },
+            Self::MfaAttemptFailed { ip: Some(_) } => Self::MfaAttemptFailed {
+                ip: Some(String::new()),
+            },
+            _ => return None,
+        };
+
+        Some(action)
+    }
 }
@@ -8,4 +8,24 @@ pub enum Actor {
     System,
     User(AccountId),
     Webhook(String),
+    /// Удалённый аккаунт, записи журнала о нём обезличиваются.
+    Deleted,
+}
+
+impl Actor {
+    /// Разбирает актора из строки вида `System`, `Deleted`, `User:1` или `Webhook:name`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let (kind, data) = match value.trim().split_once(':') {
+            Some((kind, data)) => (kind, Some(data.trim())),
+            None => (value.trim(), None),
+        };
+
+        match (kind.to_lowercase().as_str(), data) {
+            ("system", None) => Some(Actor::System),
+            ("deleted", None) => Some(Actor::Deleted),
+            ("user", Some(id)) => id.parse().ok().map(|id| Actor::User(AccountId(id))),
+            ("webhook", Some(name)) if !name.is_empty() => Some(Actor::Webhook(name.to_string())),
+            _ => None,
+        }
+    }
 }
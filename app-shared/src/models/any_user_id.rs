@@ -1,4 +1,7 @@
-﻿use crate::{models::AccountId, prelude::*};
+﻿use crate::{
+    models::{AccountId, GithubUserId},
+    prelude::*,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -6,5 +9,6 @@ pub enum AnyUserId {
     DiscordId(DiscordUserId),
     ByondCkey(ByondUserId),
     SS14Guid(SS14UserId),
+    GithubId(GithubUserId),
     AccountId(AccountId),
 }
@@ -3,5 +3,16 @@ use super::Secret;
 #[derive(Debug, Clone)]
 pub enum ApiCaller {
     System,
-    Token(Secret),
+    /// Секрет токена, IP, с которого пришёл запрос, и причина действия для журнала.
+    Token(Secret, Option<String>, Option<String>),
+}
+
+impl ApiCaller {
+    /// Причина действия, которую вызывающий указал для журнала.
+    pub fn reason(&self) -> Option<String> {
+        match self {
+            ApiCaller::System => None,
+            ApiCaller::Token(_, _, reason) => reason.clone(),
+        }
+    }
 }
@@ -1,22 +1,55 @@
-﻿use crate::models::{AccountId, Rights, Secret};
+﻿use crate::models::{AccountId, Rights, Secret, SecretHash};
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 
-use super::ApiCaller;
+#[derive(
+    Debug, Default, Copy, Clone, Serialize, Deserialize, Hash, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[serde(transparent)]
+pub struct ApiTokenId(pub i64);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiToken {
-    pub secret: Secret,
+    /// ID назначается базой данных, до сохранения равен 0.
+    pub id: ApiTokenId,
+    /// Секрет в открытом виде известен только при создании токена.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<Secret>,
+    #[serde(skip)]
+    pub secret_hash: SecretHash,
+    pub label: String,
     pub expiration: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub rights: Rights,
     pub creator: Option<AccountId>,
     pub is_service: bool,
+    /// Рутовый токен: из конфига или выпущенный `indigo-bot admin mint` без ограничения прав.
+    /// Его права обновляются до полных при каждом запуске.
+    #[serde(default)]
+    pub is_root: bool,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub last_used_ip: Option<String>,
+}
+
+/// Информация о токене без его секрета.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiTokenInfo {
+    pub id: ApiTokenId,
+    pub label: String,
+    pub expiration: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub rights: Rights,
+    pub creator: Option<AccountId>,
+    pub is_service: bool,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub last_used_ip: Option<String>,
 }
 
 impl ApiToken {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         secret: Secret,
+        label: String,
         rights: Rights,
         creator: Option<AccountId>,
         duration: Option<Duration>,
@@ -24,12 +57,18 @@ impl ApiToken {
         custom_creation_time: Option<DateTime<Utc>>,
     ) -> Self {
         Self {
-            secret,
+            id: ApiTokenId::default(),
+            secret_hash: secret.hash(),
+            secret: Some(secret),
+            label,
             rights,
             expiration: duration.map(|duration| Utc::now() + duration),
             created_at: custom_creation_time.unwrap_or_else(Utc::now),
             creator,
             is_service,
+            is_root: false,
+            last_used_at: None,
+            last_used_ip: None,
         }
     }
 
@@ -40,12 +79,20 @@ impl ApiToken {
 
         Utc::now() > expiration
     }
+}
 
-    pub fn to_caller(self) -> ApiCaller {
-        if self.creator.is_some() {
-            ApiCaller::Token(self.secret)
-        } else {
-            ApiCaller::System
+impl From<ApiToken> for ApiTokenInfo {
+    fn from(token: ApiToken) -> Self {
+        Self {
+            id: token.id,
+            label: token.label,
+            expiration: token.expiration,
+            created_at: token.created_at,
+            rights: token.rights,
+            creator: token.creator,
+            is_service: token.is_service,
+            last_used_at: token.last_used_at,
+            last_used_ip: token.last_used_ip,
         }
     }
 }
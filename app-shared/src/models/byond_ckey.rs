@@ -1,4 +1,99 @@
-﻿use serde::{Deserialize, Serialize};
+﻿use serde::{Deserialize, Deserializer, Serialize};
 
+use super::ApiError;
+
+/// Ckey BYOND аккаунта. При десериализации ключ приводится к виду ckey и проверяется.
 #[derive(Debug, Clone, Hash, Ord, PartialOrd, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String")]
 pub struct ByondCkey(pub String);
+
+impl ByondCkey {
+    pub const MIN_LENGTH: usize = 3;
+    pub const MAX_LENGTH: usize = 30;
+
+    /// Приводит ключ BYOND к виду ckey и проверяет его длину.
+    pub fn parse(key: &str) -> Result<Self, ApiError> {
+        let ckey = Self::canonical(key);
+
+        if ckey.is_empty() {
+            return Err(ApiError::Other("Пустой ckey".to_string()));
+        }
+
+        if !(Self::MIN_LENGTH..=Self::MAX_LENGTH).contains(&ckey.len()) {
+            return Err(ApiError::Other(format!(
+                "Ckey должен быть длиной от {} до {} символов",
+                Self::MIN_LENGTH,
+                Self::MAX_LENGTH
+            )));
+        }
+
+        Ok(Self(ckey))
+    }
+
+    /// Приводит ключ BYOND к виду ckey: нижний регистр, только буквы и цифры.
+    pub fn canonical(key: &str) -> String {
+        key.chars()
+            .filter(char::is_ascii_alphanumeric)
+            .map(|char| char.to_ascii_lowercase())
+            .collect()
+    }
+
+    /// Читает уже сохранённый ckey без проверки: в скрытых записях журнала он пустой.
+    pub fn deserialize_stored<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self)
+    }
+}
+
+impl TryFrom<String> for ByondCkey {
+    type Error = ApiError;
+
+    fn try_from(key: String) -> Result<Self, Self::Error> {
+        Self::parse(&key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ActionType;
+
+    #[test]
+    fn canonical_keeps_only_lowercase_alphanumerics() {
+        assert_eq!(ByondCkey::canonical("Some_Player 42"), "someplayer42");
+        assert_eq!(ByondCkey::canonical("ÄbC-d"), "bcd");
+        assert_eq!(ByondCkey::canonical("___"), "");
+    }
+
+    #[test]
+    fn parse_checks_length() {
+        assert_eq!(ByondCkey::parse("Some Player").unwrap().0, "someplayer");
+        assert!(ByondCkey::parse("").is_err());
+        assert!(ByondCkey::parse("-_-").is_err());
+        assert!(ByondCkey::parse("A b").is_err());
+        assert!(ByondCkey::parse("abc").is_ok());
+        assert!(ByondCkey::parse(&"a".repeat(ByondCkey::MAX_LENGTH)).is_ok());
+        assert!(ByondCkey::parse(&"a".repeat(ByondCkey::MAX_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn deserialization_validates() {
+        let ckey: ByondCkey = serde_json::from_str("\"Some Player\"").unwrap();
+        assert_eq!(ckey.0, "someplayer");
+        assert!(serde_json::from_str::<ByondCkey>("\"a!\"").is_err());
+    }
+
+    #[test]
+    fn redacted_journal_actions_deserialize() {
+        let action = ActionType::ByondConnected {
+            ckey: ByondCkey("someplayer".to_string()),
+        };
+        let redacted = serde_json::to_value(action.redacted().unwrap()).unwrap();
+
+        let ActionType::ByondConnected { ckey } = serde_json::from_value(redacted).unwrap() else {
+            panic!("unexpected action");
+        };
+        assert_eq!(ckey.0, "");
+    }
+}
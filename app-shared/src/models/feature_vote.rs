@@ -1,8 +1,9 @@
 use crate::prelude::*;
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use serenity::model::prelude::{ChannelId, Message, MessageId};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct FeatureVoteDescriptor(pub MessageId, pub ChannelId);
 
 impl From<Message> for FeatureVoteDescriptor {
@@ -11,7 +12,7 @@ impl From<Message> for FeatureVoteDescriptor {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct FeatureVote {
     pub descriptor: FeatureVoteDescriptor,
     pub author_id: DiscordUserId,
@@ -0,0 +1,10 @@
+﻿use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Код, который пользователь вводит на GitHub для привязки аккаунта.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GithubLinkCode {
+    pub user_code: String,
+    pub verification_uri: String,
+    pub expires_at: DateTime<Utc>,
+}
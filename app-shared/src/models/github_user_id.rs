@@ -0,0 +1,5 @@
+﻿use serde::{Deserialize, Serialize};
+
+/// Числовой ID пользователя GitHub, в отличие от логина он не меняется.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Hash, Ord, PartialOrd, Eq, PartialEq)]
+pub struct GithubUserId(pub u64);
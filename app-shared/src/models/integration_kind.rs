@@ -0,0 +1,41 @@
+﻿use serde::{Deserialize, Serialize};
+
+use super::AnyUserId;
+
+/// Тип подключаемой к аккаунту интеграции.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Hash, Ord, PartialOrd, Eq, PartialEq)]
+pub enum IntegrationKind {
+    Byond,
+    SS14,
+    Github,
+}
+
+impl IntegrationKind {
+    /// Имя типа интеграции, под которым он хранится в базе данных.
+    pub fn name(&self) -> &'static str {
+        match self {
+            IntegrationKind::Byond => "Byond",
+            IntegrationKind::SS14 => "SS14",
+            IntegrationKind::Github => "Github",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Byond" => Some(IntegrationKind::Byond),
+            "SS14" => Some(IntegrationKind::SS14),
+            "Github" => Some(IntegrationKind::Github),
+            _ => None,
+        }
+    }
+
+    /// Тип интеграции, к которой относится ID, и сам ID в строковом виде.
+    pub fn of(user_id: &AnyUserId) -> Option<(Self, String)> {
+        match user_id {
+            AnyUserId::ByondCkey(ckey) => Some((IntegrationKind::Byond, ckey.0.clone())),
+            AnyUserId::SS14Guid(guid) => Some((IntegrationKind::SS14, guid.0.clone())),
+            AnyUserId::GithubId(user_id) => Some((IntegrationKind::Github, user_id.0.to_string())),
+            AnyUserId::DiscordId(_) | AnyUserId::AccountId(_) => None,
+        }
+    }
+}
@@ -0,0 +1,15 @@
+﻿use crate::models::{AccountId, IntegrationKind};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Запись истории привязок интеграции к аккаунту.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrationLink {
+    pub account_id: AccountId,
+    pub kind: IntegrationKind,
+    /// Ckey или GUID, в зависимости от типа интеграции.
+    pub integration_id: String,
+    /// Неизвестно для привязок, сделанных до появления истории.
+    pub linked_at: Option<DateTime<Utc>>,
+    pub unlinked_at: Option<DateTime<Utc>>,
+}
@@ -1,7 +1,8 @@
 use chrono::{DateTime, Utc};
+use hmac::Mac;
 use serde::{Deserialize, Serialize};
 
-use super::{ActionType, Actor};
+use super::{ActionType, Actor, Secret};
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Hash, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(transparent)]
@@ -14,6 +15,80 @@ pub struct JournalEntry {
     pub datetime: DateTime<Utc>,
     pub subject: Option<Actor>,
     pub action: ActionType,
+    /// Причина, которую указал совершивший действие.
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// Хэш предыдущей записи цепочки, `None` у первой записи.
+    #[serde(default)]
+    pub prev_hash: Option<String>,
+    /// Хэш записи вместе с `prev_hash`, `None` - запись ещё не подписана.
+    #[serde(default)]
+    pub hash: Option<String>,
+}
+
+impl JournalEntry {
+    /// Наибольшая длина причины в символах.
+    pub const MAX_REASON_LENGTH: usize = 500;
+
+    /// Хэширует содержимое записи и `prev_hash` ключом из конфига БД,
+    /// без ключа цепочку не пересчитать после правки записи.
+    pub fn compute_hash(&self) -> String {
+        // Ключи объектов serde_json::Value упорядочены, поэтому строка не зависит от порядка полей
+        let content = serde_json::json!({
+            "id": self.id,
+            "object": self.object,
+            "datetime": self.datetime,
+            "subject": self.subject,
+            "action": self.action,
+            "reason": self.reason,
+            "prev_hash": self.prev_hash,
+        });
+
+        let mut mac = Secret::pepper_mac();
+        mac.update(content.to_string().as_bytes());
+
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
+
+/// Звено цепочки хэшей журнала. У перенесённых в архив записей в БД остаются только хэши.
+#[derive(Debug, Clone)]
+pub struct JournalChainLink {
+    pub id: JournalEntryId,
+    pub prev_hash: Option<String>,
+    pub hash: Option<String>,
+    /// `None` - запись перенесена в архив.
+    pub entry: Option<JournalEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JournalChainProblem {
+    /// У записи нет хэша.
+    Unsigned,
+    /// Содержимое записи не совпадает с её хэшем.
+    ContentChanged,
+    /// `prev_hash` не совпадает с хэшем предыдущей записи.
+    LinkBroken,
+}
+
+/// Первое найденное нарушение цепочки.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalChainBreak {
+    pub id: JournalEntryId,
+    pub problem: JournalChainProblem,
+}
+
+/// Результат проверки цепочки хэшей журнала.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JournalChainReport {
+    /// Проверено записей, включая перенесённые в архив.
+    pub checked: usize,
+    /// Из них перенесено в архив, их содержимое не проверялось.
+    pub archived: usize,
+    /// Из них обезличено по `JournalEntriesRedacted`, их содержимое не проверялось.
+    pub redacted: usize,
+    /// `None` - цепочка цела.
+    pub broken: Option<JournalChainBreak>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,6 +96,9 @@ pub struct JournalEntryCursor {
     pub offset: usize,
     pub subject: Option<Actor>,
     pub max_count: usize,
+    /// Без заметок администрации, которые не разрешили показывать владельцу аккаунта.
+    #[serde(default)]
+    pub shareable_only: bool,
     pub entries: Vec<JournalEntry>,
     pub total: usize,
 }
@@ -31,8 +109,47 @@ impl JournalEntryCursor {
             offset,
             subject,
             max_count: max,
+            shareable_only: false,
             entries: Vec::new(),
             total: 0,
         }
     }
 }
+
+/// Выборка записей журнала с фильтрами, новые первыми.
+/// Страницы идут по id записи, следующая начинается с `next_before_id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalQuery {
+    pub object: Option<Actor>,
+    pub subject: Option<Actor>,
+    /// Тип действия, например `RoleAdded`.
+    pub action: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// Только записи с id меньше указанного.
+    pub before_id: Option<JournalEntryId>,
+    pub max_count: usize,
+    /// Без заметок администрации, которые не разрешили показывать владельцу аккаунта.
+    #[serde(default)]
+    pub shareable_only: bool,
+    pub entries: Vec<JournalEntry>,
+    /// `None` - записей больше нет.
+    pub next_before_id: Option<JournalEntryId>,
+}
+
+impl JournalQuery {
+    pub fn new(max: usize) -> Self {
+        Self {
+            object: None,
+            subject: None,
+            action: None,
+            from: None,
+            to: None,
+            before_id: None,
+            max_count: max,
+            shareable_only: false,
+            entries: Vec::new(),
+            next_before_id: None,
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{AccountId, Secret, SecretHash, Session, SessionSecrets};
+
+/// Второй фактор аккаунта: TOTP и коды восстановления.
+#[derive(Debug, Clone)]
+pub struct AccountMfa {
+    pub account_id: AccountId,
+    /// Ключ TOTP в hex.
+    pub totp_secret: Secret,
+    /// `None` - подключение начато, но ещё не подтверждено кодом.
+    pub enabled_at: Option<DateTime<Utc>>,
+    /// Последний принятый шаг TOTP, один и тот же код не принимается дважды.
+    pub last_used_step: i64,
+    /// Хэши неиспользованных кодов восстановления.
+    pub recovery_codes: Vec<SecretHash>,
+}
+
+impl AccountMfa {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled_at.is_some()
+    }
+}
+
+/// Состояние второго фактора аккаунта.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MfaStatus {
+    pub enabled: bool,
+    /// Одна из ролей аккаунта требует второй фактор.
+    pub required: bool,
+    pub recovery_codes_left: usize,
+}
+
+/// Начатое подключение TOTP: ключ для ввода вручную и ссылка `otpauth://` для приложения.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub uri: String,
+}
+
+/// Результат входа.
+#[derive(Debug, Clone)]
+pub enum Login {
+    Completed(Box<Session>, SessionSecrets),
+    /// Нужен код второго фактора, секрет продолжает начатый вход.
+    MfaRequired(Secret),
+}
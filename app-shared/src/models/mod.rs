@@ -1,5 +1,8 @@
 mod account;
+mod account_export;
 mod account_integrations;
+mod account_note;
+mod account_search;
 mod action_type;
 mod actor;
 mod any_user_id;
@@ -11,7 +14,14 @@ mod byond_ckey;
 mod donation_tier;
 mod feature_vote;
 mod game_server;
+mod github_link_code;
+mod github_user_id;
+mod integration_kind;
+mod integration_link;
 mod journal_entry;
+mod mfa;
+mod profile_visibility;
+mod public_profile;
 mod rights;
 mod role;
 mod secret;
@@ -19,27 +29,45 @@ mod service;
 mod session;
 mod ss14_guid;
 mod tfa_token;
+mod username_change;
 mod webhook;
 
 pub use account::{Account, AccountId};
+pub use account_export::{AccountExport, ExportedSession};
 pub use account_integrations::AccountIntegrations;
+pub use account_note::{AccountNote, AccountNoteId, NoteSeverity};
+pub use account_search::AccountSearch;
 pub use action_type::ActionType;
 pub use actor::Actor;
 pub use any_user_id::AnyUserId;
 pub use api_caller::ApiCaller;
 pub use api_error::ApiError;
-pub use api_token::ApiToken;
+pub use api_token::{ApiToken, ApiTokenId, ApiTokenInfo};
 pub use bug_report::BugReport;
 pub use byond_ckey::ByondCkey;
 pub use donation_tier::{DonationTier, DonationTierId};
 pub use feature_vote::{FeatureVote, FeatureVoteDescriptor};
 pub use game_server::{AnyGameServer, ByondServer, GameServerId, SS14Server};
-pub use journal_entry::{JournalEntry, JournalEntryCursor, JournalEntryId};
-pub use rights::{Rights, RightsScope, ScopedServiceRights, TokenRights, UserRights};
+pub use github_link_code::GithubLinkCode;
+pub use github_user_id::GithubUserId;
+pub use integration_kind::IntegrationKind;
+pub use integration_link::IntegrationLink;
+pub use journal_entry::{
+    JournalChainBreak, JournalChainLink, JournalChainProblem, JournalChainReport, JournalEntry,
+    JournalEntryCursor, JournalEntryId, JournalQuery,
+};
+pub use mfa::{AccountMfa, Login, MfaStatus, TotpEnrollment};
+pub use profile_visibility::ProfileVisibility;
+pub use public_profile::PublicProfile;
+pub use rights::{
+    Rights, RightsScope, ScopedServiceRights, ServiceRights, TokenRights, UserRights,
+    UserRightsScope,
+};
 pub use role::{Role, RoleId};
-pub use secret::Secret;
+pub use secret::{Secret, SecretHash, SecretKind};
 pub use service::{ServiceError, ServiceId};
-pub use session::Session;
+pub use session::{Session, SessionId, SessionSecrets};
 pub use ss14_guid::SS14Guid;
 pub use tfa_token::TFAToken;
+pub use username_change::UsernameChange;
 pub use webhook::{Webhook, WebhookConfiguration, WebhookPayload, WebhookResponse};
@@ -0,0 +1,23 @@
+﻿use bitflags::bitflags;
+use serde::{Deserialize, Serialize};
+
+bitflags! {
+    /// Поля профиля, которые видны другим пользователям.
+    /// Имя и аватарка видны всегда.
+    #[derive(Serialize, Deserialize)]
+    #[serde(transparent)]
+    pub struct ProfileVisibility: u64 {
+        const MAIN_ROLE = (1 << 0);
+        const DONATION_TIER = (1 << 1);
+        const JOINED_AT = (1 << 2);
+        const BYOND = (1 << 3);
+        const SS14 = (1 << 4);
+        const GITHUB = (1 << 5);
+    }
+}
+
+impl Default for ProfileVisibility {
+    fn default() -> Self {
+        Self::MAIN_ROLE | Self::DONATION_TIER | Self::JOINED_AT
+    }
+}
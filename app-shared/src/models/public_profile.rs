@@ -0,0 +1,53 @@
+﻿use crate::models::{Account, DonationTier, ProfileVisibility, Role};
+use crate::prelude::{ByondUserId, SS14UserId};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Профиль пользователя, каким его видят другие.
+/// Скрытые пользователем поля равны `None`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicProfile {
+    pub username: String,
+    pub avatar_url: String,
+    /// Роли для определения основной, см. `Role::main_role`.
+    pub roles: Option<Vec<Role>>,
+    pub donation_tier: Option<DonationTier>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub byond_ckey: Option<ByondUserId>,
+    pub ss14_guid: Option<SS14UserId>,
+    pub github_login: Option<String>,
+}
+
+impl PublicProfile {
+    pub fn new(account: Account) -> Self {
+        let visibility = account.profile_visibility;
+        let visible = |field| visibility.contains(field);
+
+        Self {
+            roles: Some(account.roles).filter(|_| visible(ProfileVisibility::MAIN_ROLE)),
+            donation_tier: account
+                .donation_tier
+                .filter(|_| visible(ProfileVisibility::DONATION_TIER)),
+            created_at: Some(account.created_at).filter(|_| visible(ProfileVisibility::JOINED_AT)),
+            byond_ckey: account
+                .integrations
+                .byond_ckey
+                .filter(|_| visible(ProfileVisibility::BYOND)),
+            ss14_guid: account
+                .integrations
+                .ss14_guid
+                .filter(|_| visible(ProfileVisibility::SS14)),
+            github_login: account
+                .integrations
+                .github_login
+                .filter(|_| visible(ProfileVisibility::GITHUB)),
+            username: account.username,
+            avatar_url: account.avatar_url,
+        }
+    }
+
+    /// Основная роль, если пользователь её не скрыл.
+    pub fn main_role(&self) -> Option<Role> {
+        self.roles.clone().map(Role::main_role)
+    }
+}
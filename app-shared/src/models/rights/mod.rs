@@ -9,4 +9,4 @@ pub use rights::Rights;
 pub use scoped_rights::RightsScope;
 pub use service_rights::{ScopedServiceRights, ServiceRights};
 pub use token_rights::TokenRights;
-pub use user_rights::UserRights;
+pub use user_rights::{UserRights, UserRightsScope};
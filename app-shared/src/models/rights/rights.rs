@@ -1,4 +1,6 @@
-﻿use crate::models::{ScopedServiceRights, TokenRights, UserRights};
+﻿use crate::models::{
+    IntegrationKind, RoleId, ScopedServiceRights, TokenRights, UserRights, UserRightsScope,
+};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::ops::BitOr;
@@ -8,6 +10,8 @@ pub struct Rights {
     #[serde(default)]
     pub user: UserRights,
     #[serde(default)]
+    pub user_scope: UserRightsScope,
+    #[serde(default)]
     pub token: TokenRights,
     #[serde(default)]
     pub service: ScopedServiceRights,
@@ -17,6 +21,7 @@ impl Rights {
     pub fn full() -> Self {
         Self {
             user: UserRights::all(),
+            user_scope: UserRightsScope::default(),
             token: TokenRights::all(),
             service: ScopedServiceRights::all(),
         }
@@ -25,11 +30,22 @@ impl Rights {
     pub fn none() -> Self {
         Self {
             user: UserRights::empty(),
+            user_scope: UserRightsScope::default(),
             token: TokenRights::empty(),
             service: ScopedServiceRights::empty(),
         }
     }
 
+    /// Может ли работать с интеграцией указанного типа.
+    pub fn can_manage_integration(&self, right: UserRights, kind: IntegrationKind) -> bool {
+        self.user.contains(right) && self.user_scope.allows_integration(right, kind)
+    }
+
+    /// Может ли выдавать или снимать указанную роль.
+    pub fn can_manage_role(&self, right: UserRights, role_id: RoleId) -> bool {
+        self.user.contains(right) && self.user_scope.allows_role(right, role_id)
+    }
+
     pub fn bits(&self) -> u64 {
         self.user.bits() + self.token.bits() + self.service.sum().bits()
     }
@@ -40,6 +56,7 @@ impl BitOr for Rights {
 
     fn bitor(self, rhs: Self) -> Self::Output {
         Self {
+            user_scope: self.user_scope.union(self.user, rhs.user_scope, rhs.user),
             user: self.user | rhs.user,
             service: self.service | rhs.service,
             token: self.token | rhs.token,
@@ -49,7 +66,10 @@ impl BitOr for Rights {
 
 impl PartialEq for Rights {
     fn eq(&self, other: &Self) -> bool {
-        self.token == other.token && self.user == other.user && self.service == other.service
+        self.token == other.token
+            && self.user == other.user
+            && self.user_scope == other.user_scope
+            && self.service == other.service
     }
 }
 
@@ -73,10 +93,16 @@ impl PartialOrd for Rights {
     }
 
     fn gt(&self, other: &Self) -> bool {
-        self.token > other.token && self.service > other.service && self.user > other.user
+        self.token > other.token
+            && self.service > other.service
+            && self.user > other.user
+            && self.user_scope.covers(&other.user_scope, other.user)
     }
 
     fn ge(&self, other: &Self) -> bool {
-        self.token >= other.token && self.service >= other.service && self.user >= other.user
+        self.token >= other.token
+            && self.service >= other.service
+            && self.user >= other.user
+            && self.user_scope.covers(&other.user_scope, other.user)
     }
 }
@@ -1,5 +1,7 @@
-﻿use bitflags::bitflags;
+﻿use crate::models::{IntegrationKind, RoleId};
+use bitflags::bitflags;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 
 bitflags! {
     #[derive(Serialize, Deserialize)]
@@ -15,6 +17,22 @@ bitflags! {
         const REMOVE_ROLES = (1 << 2);
         /// Может создавать аккаунты.
         const CREATE_ACCOUNTS = (1 << 3);
+        /// Может отвязывать и принудительно перепривязывать интеграции.
+        const REMOVE_CONNECTED_ACCOUNTS = (1 << 4);
+        /// Может объединять аккаунты-дубликаты.
+        const MERGE_ACCOUNTS = (1 << 5);
+        /// Может искать аккаунты и просматривать их данные.
+        const VIEW_ACCOUNTS = (1 << 6);
+        /// Может удалять аккаунты пользователей с меньшими правами.
+        const DELETE_ACCOUNTS = (1 << 7);
+        /// Может менять имя пользователям с меньшими правами.
+        const RENAME_ACCOUNTS = (1 << 8);
+        /// Может читать заметки о пользователях.
+        const VIEW_NOTES = (1 << 9);
+        /// Может оставлять и удалять заметки о пользователях с меньшими правами.
+        const EDIT_NOTES = (1 << 10);
+        /// Может просматривать весь журнал действий.
+        const VIEW_JOURNAL = (1 << 11);
     }
 }
 
@@ -23,3 +41,341 @@ impl Default for UserRights {
         Self::empty()
     }
 }
+
+impl UserRights {
+    /// Права, которые ограничиваются типами интеграций.
+    /// Получение интеграций нигде не проверяет тип, поэтому не ограничивается.
+    pub fn integration_rights() -> Self {
+        Self::ADD_CONNECTED_ACCOUNTS | Self::REMOVE_CONNECTED_ACCOUNTS
+    }
+
+    /// Права, которые ограничиваются ролями.
+    pub fn role_rights() -> Self {
+        Self::ADD_ROLES | Self::REMOVE_ROLES
+    }
+
+    /// Отдельные флаги прав.
+    fn flags(self) -> impl Iterator<Item = Self> {
+        (0..u64::BITS)
+            .map(|bit| Self::from_bits_truncate(1 << bit))
+            .filter(move |flag| !flag.is_empty() && self.contains(*flag))
+    }
+}
+
+/// Ограничивает отдельные пользовательские права интеграциями и ролями.
+/// Право без записи действует для любых интеграций и ролей.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "UserRightsScopeData")]
+pub struct UserRightsScope {
+    /// Типы интеграций для прав из `UserRights::integration_rights`.
+    #[serde(default)]
+    pub integrations: BTreeMap<UserRights, BTreeSet<IntegrationKind>>,
+    /// Роли для прав из `UserRights::role_rights`.
+    #[serde(default)]
+    pub roles: BTreeMap<UserRights, BTreeSet<RoleId>>,
+}
+
+impl UserRightsScope {
+    pub fn allows_integration(&self, right: UserRights, kind: IntegrationKind) -> bool {
+        self.integrations
+            .get(&right)
+            .is_none_or(|kinds| kinds.contains(&kind))
+    }
+
+    pub fn allows_role(&self, right: UserRights, role_id: RoleId) -> bool {
+        self.roles
+            .get(&right)
+            .is_none_or(|roles| roles.contains(&role_id))
+    }
+
+    /// Покрывает ли ограничение другое для каждого права из `other_rights`.
+    pub fn covers(&self, other: &Self, other_rights: UserRights) -> bool {
+        (other_rights & UserRights::integration_rights())
+            .flags()
+            .all(|right| {
+                covers_scope(
+                    self.integrations.get(&right),
+                    other.integrations.get(&right),
+                )
+            })
+            && (other_rights & UserRights::role_rights())
+                .flags()
+                .all(|right| covers_scope(self.roles.get(&right), other.roles.get(&right)))
+    }
+
+    /// Объединяет ограничения каждого права по отдельности: право, выданное двумя сторонами,
+    /// действует там, где его разрешает хотя бы одна из них.
+    pub fn union(self, rights: UserRights, rhs: Self, rhs_rights: UserRights) -> Self {
+        Self {
+            integrations: union_scope(
+                UserRights::integration_rights(),
+                (self.integrations, rights),
+                (rhs.integrations, rhs_rights),
+            ),
+            roles: union_scope(
+                UserRights::role_rights(),
+                (self.roles, rights),
+                (rhs.roles, rhs_rights),
+            ),
+        }
+    }
+}
+
+fn covers_scope<T: Ord>(scope: Option<&BTreeSet<T>>, other: Option<&BTreeSet<T>>) -> bool {
+    match (scope, other) {
+        (None, _) => true,
+        (Some(_), None) => false,
+        (Some(scope), Some(other)) => scope.is_superset(other),
+    }
+}
+
+fn union_scope<T: Ord>(
+    scoped_rights: UserRights,
+    (mut lhs, lhs_rights): (BTreeMap<UserRights, BTreeSet<T>>, UserRights),
+    (mut rhs, rhs_rights): (BTreeMap<UserRights, BTreeSet<T>>, UserRights),
+) -> BTreeMap<UserRights, BTreeSet<T>> {
+    let mut result = BTreeMap::new();
+
+    for right in ((lhs_rights | rhs_rights) & scoped_rights).flags() {
+        let lhs = lhs_rights.contains(right).then(|| lhs.remove(&right));
+        let rhs = rhs_rights.contains(right).then(|| rhs.remove(&right));
+
+        let scope = match (lhs, rhs) {
+            (Some(Some(lhs)), Some(Some(rhs))) => Some(lhs.into_iter().chain(rhs).collect()),
+            (Some(Some(scope)), None) | (None, Some(Some(scope))) => Some(scope),
+            _ => None,
+        };
+
+        if let Some(scope) = scope {
+            result.insert(right, scope);
+        }
+    }
+
+    result
+}
+
+/// Ограничения в БД и в запросах: по отдельным правам или в старом виде,
+/// когда одно ограничение действовало на все права своего вида.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum UserRightsScopeData {
+    // Ключи - биты прав строкой: в untagged числа из ключей не разбираются
+    PerRight {
+        #[serde(default)]
+        integrations: BTreeMap<String, BTreeSet<IntegrationKind>>,
+        #[serde(default)]
+        roles: BTreeMap<String, BTreeSet<RoleId>>,
+    },
+    Legacy {
+        #[serde(default)]
+        integrations: Option<BTreeSet<IntegrationKind>>,
+        #[serde(default)]
+        roles: Option<BTreeSet<RoleId>>,
+    },
+}
+
+impl TryFrom<UserRightsScopeData> for UserRightsScope {
+    type Error = String;
+
+    fn try_from(data: UserRightsScopeData) -> Result<Self, Self::Error> {
+        let (integrations, roles) = match data {
+            UserRightsScopeData::PerRight {
+                integrations,
+                roles,
+            } => (parse_keys(integrations)?, parse_keys(roles)?),
+            UserRightsScopeData::Legacy {
+                integrations,
+                roles,
+            } => (
+                per_right(UserRights::integration_rights(), integrations),
+                per_right(UserRights::role_rights(), roles),
+            ),
+        };
+
+        // Ключ - ровно одно право, которое можно ограничить
+        let is_valid = |right: &UserRights, allowed: UserRights| {
+            right.flags().count() == 1 && allowed.contains(*right)
+        };
+
+        if !integrations
+            .keys()
+            .all(|right| is_valid(right, UserRights::integration_rights()))
+        {
+            return Err("Интеграциями можно ограничить только права на привязку и отвязку".into());
+        }
+
+        if !roles
+            .keys()
+            .all(|right| is_valid(right, UserRights::role_rights()))
+        {
+            return Err("Ролями можно ограничить только права на выдачу и снятие ролей".into());
+        }
+
+        Ok(Self {
+            integrations,
+            roles,
+        })
+    }
+}
+
+fn parse_keys<T>(
+    scope: BTreeMap<String, BTreeSet<T>>,
+) -> Result<BTreeMap<UserRights, BTreeSet<T>>, String> {
+    scope
+        .into_iter()
+        .map(|(key, value)| {
+            key.parse()
+                .ok()
+                .and_then(UserRights::from_bits)
+                .map(|right| (right, value))
+                .ok_or_else(|| format!("Неизвестное право в ограничении: {key}"))
+        })
+        .collect()
+}
+
+fn per_right<T: Ord + Clone>(
+    rights: UserRights,
+    scope: Option<BTreeSet<T>>,
+) -> BTreeMap<UserRights, BTreeSet<T>> {
+    let Some(scope) = scope else {
+        return BTreeMap::new();
+    };
+
+    rights.flags().map(|right| (right, scope.clone())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(json: &str) -> Result<UserRightsScope, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    fn kinds(kinds: &[IntegrationKind]) -> BTreeSet<IntegrationKind> {
+        kinds.iter().copied().collect()
+    }
+
+    #[test]
+    fn parses_per_right_scope() {
+        let parsed =
+            scope(r#"{"integrations": {"2": ["Byond"]}, "roles": {"4": [1, 2]}}"#).unwrap();
+
+        assert_eq!(
+            parsed.integrations,
+            BTreeMap::from([(
+                UserRights::ADD_CONNECTED_ACCOUNTS,
+                kinds(&[IntegrationKind::Byond])
+            )])
+        );
+        assert_eq!(
+            parsed.roles,
+            BTreeMap::from([(
+                UserRights::ADD_ROLES,
+                BTreeSet::from([RoleId(1), RoleId(2)])
+            )])
+        );
+        assert_eq!(scope("{}").unwrap(), UserRightsScope::default());
+    }
+
+    #[test]
+    fn parses_legacy_scope_for_every_right_of_its_kind() {
+        let parsed = scope(r#"{"integrations": ["SS14"], "roles": null}"#).unwrap();
+
+        assert_eq!(
+            parsed.integrations,
+            BTreeMap::from([
+                (
+                    UserRights::ADD_CONNECTED_ACCOUNTS,
+                    kinds(&[IntegrationKind::SS14])
+                ),
+                (
+                    UserRights::REMOVE_CONNECTED_ACCOUNTS,
+                    kinds(&[IntegrationKind::SS14])
+                ),
+            ])
+        );
+        assert!(parsed.roles.is_empty());
+    }
+
+    #[test]
+    fn rejects_keys_that_cannot_be_scoped() {
+        // Не ограничиваемое интеграциями право
+        assert!(scope(r#"{"integrations": {"1": ["Byond"]}}"#).is_err());
+        // Несколько прав в одном ключе
+        assert!(scope(r#"{"integrations": {"18": ["Byond"]}}"#).is_err());
+        // Право на интеграции в ограничении ролей
+        assert!(scope(r#"{"roles": {"2": [1]}}"#).is_err());
+        assert!(scope(r#"{"roles": {"abc": [1]}}"#).is_err());
+        assert!(scope(r#"{"roles": {"9223372036854775808": [1]}}"#).is_err());
+    }
+
+    #[test]
+    fn covers_only_the_given_rights() {
+        let add = UserRights::ADD_CONNECTED_ACCOUNTS;
+        let remove = UserRights::REMOVE_CONNECTED_ACCOUNTS;
+
+        let unrestricted = UserRightsScope::default();
+        let byond = UserRightsScope {
+            integrations: BTreeMap::from([(add, kinds(&[IntegrationKind::Byond]))]),
+            ..Default::default()
+        };
+        let byond_and_ss14 = UserRightsScope {
+            integrations: BTreeMap::from([(
+                add,
+                kinds(&[IntegrationKind::Byond, IntegrationKind::SS14]),
+            )]),
+            ..Default::default()
+        };
+
+        assert!(unrestricted.covers(&byond, add));
+        assert!(!byond.covers(&unrestricted, add));
+        assert!(byond_and_ss14.covers(&byond, add));
+        assert!(!byond.covers(&byond_and_ss14, add));
+        // Ограничение другого права не мешает
+        assert!(byond.covers(&unrestricted, remove));
+        assert!(byond.covers(&unrestricted, UserRights::VIEW_ACCOUNTS));
+    }
+
+    #[test]
+    fn union_merges_each_right_separately() {
+        let add = UserRights::ADD_CONNECTED_ACCOUNTS;
+        let remove = UserRights::REMOVE_CONNECTED_ACCOUNTS;
+
+        let byond = UserRightsScope {
+            integrations: BTreeMap::from([
+                (add, kinds(&[IntegrationKind::Byond])),
+                (remove, kinds(&[IntegrationKind::Byond])),
+            ]),
+            ..Default::default()
+        };
+        let ss14 = UserRightsScope {
+            integrations: BTreeMap::from([(add, kinds(&[IntegrationKind::SS14]))]),
+            ..Default::default()
+        };
+
+        // Оба ограничены: объединение типов
+        let merged = byond.clone().union(add | remove, ss14.clone(), add);
+        assert_eq!(
+            merged.integrations,
+            BTreeMap::from([
+                (add, kinds(&[IntegrationKind::Byond, IntegrationKind::SS14])),
+                (remove, kinds(&[IntegrationKind::Byond])),
+            ])
+        );
+
+        // Право без ограничения у одной из сторон действует везде
+        let merged = byond.clone().union(add | remove, ss14, add | remove);
+        assert_eq!(
+            merged.integrations,
+            BTreeMap::from([(add, kinds(&[IntegrationKind::Byond, IntegrationKind::SS14]))])
+        );
+
+        // Ограничения невыданных прав отбрасываются
+        let merged = byond.union(add, UserRightsScope::default(), UserRights::empty());
+        assert_eq!(
+            merged.integrations,
+            BTreeMap::from([(add, kinds(&[IntegrationKind::Byond]))])
+        );
+    }
+}
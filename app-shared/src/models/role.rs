@@ -2,6 +2,7 @@
 
 use crate::models::Rights;
 use hex_color::HexColor;
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, Hash, Ord, PartialOrd, Eq, PartialEq)]
@@ -14,15 +15,38 @@ pub struct Role {
     pub name: String,
     pub color: HexColor,
     pub rights: Rights,
+    /// Права роли действуют только в сессиях с подключённым вторым фактором.
+    #[serde(default)]
+    pub requires_mfa: bool,
 }
 
 impl Role {
+    /// Роль, которая отображается рядом с именем пользователя.
+    pub fn main_role(roles: Vec<Role>) -> Role {
+        roles
+            .into_iter()
+            .sorted_by(|a, b| Ord::cmp(&a.rights.bits(), &b.rights.bits()))
+            .sorted_by(|a, b| Ord::cmp(&b.id, &a.id))
+            .next()
+            .unwrap_or_default()
+    }
+
     pub fn sum_roles_rights(roles: Vec<Role>) -> Rights {
         roles
             .into_iter()
             .map(|role| role.rights)
             .fold(Rights::none(), |acc, rights| acc | rights)
     }
+
+    /// Права ролей в сессии: роли, требующие второй фактор, без него не действуют.
+    pub fn sum_session_rights(roles: Vec<Role>, mfa_enabled: bool) -> Rights {
+        Self::sum_roles_rights(
+            roles
+                .into_iter()
+                .filter(|role| mfa_enabled || !role.requires_mfa)
+                .collect(),
+        )
+    }
 }
 
 impl Default for Role {
@@ -32,6 +56,7 @@ impl Default for Role {
             name: String::from("Гость"),
             color: HexColor::from(u32::MAX),
             rights: Rights::none(),
+            requires_mfa: false,
         }
     }
 }
@@ -1,38 +1,179 @@
-﻿use rand::distributions::Alphanumeric;
+﻿use crate::config::Config;
+use crate::database::DbConfig;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use rand::distributions::Alphanumeric;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::fmt::{Debug, Display, Formatter};
+use std::sync::OnceLock;
+
+pub(crate) type HmacSha256 = Hmac<Sha256>;
 
 const CHARSET: &[u8] = b"ABCDEFGHKLMNOPQRSTUVWXYZ0123456789";
 
-#[derive(Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+/// Общее начало префиксов всех типизированных секретов.
+const PREFIX: &str = "ib_";
+const BODY_LENGTH: usize = 64;
+const CHECKSUM_LENGTH: usize = 8;
+
+/// Конфиг БД читается один раз, а не при каждой проверке секрета.
+static DB_CONFIG: OnceLock<DbConfig> = OnceLock::new();
+
+/// Тип секрета, определяет его префикс: `ib_tok_<тело>_<контрольная сумма>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SecretKind {
+    ApiToken,
+    Webhook,
+    Session,
+    Csrf,
+}
+
+impl SecretKind {
+    pub const ALL: [SecretKind; 4] = [
+        SecretKind::ApiToken,
+        SecretKind::Webhook,
+        SecretKind::Session,
+        SecretKind::Csrf,
+    ];
+
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            SecretKind::ApiToken => "ib_tok_",
+            SecretKind::Webhook => "ib_whk_",
+            SecretKind::Session => "ib_ses_",
+            SecretKind::Csrf => "ib_csrf_",
+        }
+    }
+}
+
+impl Display for SecretKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SecretKind::ApiToken => "API токен",
+            SecretKind::Webhook => "секрет вебхука",
+            SecretKind::Session => "секрет сессии",
+            SecretKind::Csrf => "CSRF токен",
+        })
+    }
+}
+
+#[derive(Clone, Default, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Secret(pub String);
 
+/// Хэш секрета, в таком виде секреты хранятся в БД.
+#[derive(Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SecretHash(pub String);
+
 impl Secret {
-    pub fn new_random_webhook_secret() -> Self {
-        let secret: String = rand::thread_rng()
-            .sample_iter(&Alphanumeric)
-            .take(256)
-            .map(char::from)
-            .collect();
+    /// Хэширует секрет ключом из конфига БД.
+    pub fn hash(&self) -> SecretHash {
+        let mut mac = Self::pepper_mac();
+        mac.update(self.0.as_bytes());
 
-        Self(secret)
+        SecretHash(hex::encode(mac.finalize().into_bytes()))
     }
 
-    pub fn new_random_api_secret() -> Self {
-        let secret: String = rand::thread_rng()
+    /// Выводит из секрета дочерний секрет указанного типа.
+    pub fn derive(&self, kind: SecretKind) -> Secret {
+        let mut mac = HmacSha256::new_from_slice(self.0.as_bytes()).unwrap();
+        mac.update(kind.prefix().as_bytes());
+
+        Self::from_body(kind, &hex::encode(mac.finalize().into_bytes()))
+    }
+
+    /// Тип секрета по его префиксу, у старых секретов типа нет.
+    pub fn kind(&self) -> Option<SecretKind> {
+        SecretKind::ALL
+            .into_iter()
+            .find(|kind| self.0.starts_with(kind.prefix()))
+    }
+
+    /// Проверяет формат секрета до обращения к БД.
+    /// Секреты без префикса принимаются как есть, пока не наступил `DbConfig::legacy_secrets_until`.
+    pub fn check_format(&self, expected: SecretKind) -> Result<(), String> {
+        if !self.0.starts_with(PREFIX) {
+            if !Self::is_legacy_format_allowed() {
+                return Err("Секрет старого формата больше не принимается".to_string());
+            }
+
+            return Ok(());
+        }
+
+        let Some(kind) = self.kind() else {
+            return Err("Неизвестный тип секрета".to_string());
+        };
+
+        if kind != expected {
+            return Err(format!("Ожидался {expected}, а передан {kind}"));
+        }
+
+        let Some((payload, checksum)) = self.0.rsplit_once('_') else {
+            return Err("Некорректный формат секрета".to_string());
+        };
+
+        if payload.len() <= kind.prefix().len() || checksum != Self::checksum(payload) {
+            return Err("Некорректный формат секрета".to_string());
+        }
+
+        Ok(())
+    }
+
+    fn is_legacy_format_allowed() -> bool {
+        Self::db_config()
+            .legacy_secrets_until
+            .is_none_or(|until| Utc::now() < until)
+    }
+
+    fn db_config() -> &'static DbConfig {
+        DB_CONFIG.get_or_init(|| DbConfig::get().unwrap())
+    }
+
+    pub fn is_valid(&self, kind: SecretKind) -> bool {
+        self.check_format(kind).is_ok()
+    }
+
+    fn from_body(kind: SecretKind, body: &str) -> Self {
+        let payload = format!("{}{body}", kind.prefix());
+        let checksum = Self::checksum(&payload);
+
+        Self(format!("{payload}_{checksum}"))
+    }
+
+    fn checksum(payload: &str) -> String {
+        let hash = Sha256::digest(payload.as_bytes());
+
+        hex::encode(&hash[..CHECKSUM_LENGTH / 2])
+    }
+
+    fn new_random(kind: SecretKind) -> Self {
+        let body: String = rand::thread_rng()
             .sample_iter(&Alphanumeric)
-            .take(256)
+            .take(BODY_LENGTH)
             .map(char::from)
             .collect();
 
-        Self(secret)
+        Self::from_body(kind, &body)
+    }
+
+    pub(crate) fn pepper_mac() -> HmacSha256 {
+        HmacSha256::new_from_slice(Self::db_config().secret_pepper.0.as_bytes()).unwrap()
+    }
+
+    pub fn new_random_webhook_secret() -> Self {
+        Self::new_random(SecretKind::Webhook)
+    }
+
+    pub fn new_random_api_secret() -> Self {
+        Self::new_random(SecretKind::ApiToken)
     }
 
-    pub fn new_random_tfa_secret() -> Self {
+    pub fn new_random_tfa_secret(length: usize) -> Self {
         let mut rng = rand::thread_rng();
 
-        let secret: String = (0..5)
+        let secret: String = (0..length)
             .map(|_| {
                 let idx = rng.gen_range(0..CHARSET.len());
                 CHARSET[idx] as char
@@ -43,23 +184,7 @@ impl Secret {
     }
 
     pub fn new_random_session_secret() -> Self {
-        let secret: String = rand::thread_rng()
-            .sample_iter(&Alphanumeric)
-            .take(256)
-            .map(char::from)
-            .collect();
-
-        Self(secret)
-    }
-
-    pub fn new_random_csrf_secret() -> Self {
-        let secret: String = rand::thread_rng()
-            .sample_iter(&Alphanumeric)
-            .take(256)
-            .map(char::from)
-            .collect();
-
-        Self(secret)
+        Self::new_random(SecretKind::Session)
     }
 }
 
@@ -80,3 +205,84 @@ impl Debug for Secret {
         f.write_str("***")
     }
 }
+
+impl SecretHash {
+    /// Сравнивает хэш с секретом за постоянное время.
+    pub fn verify(&self, secret: &Secret) -> bool {
+        let Ok(hash) = hex::decode(&self.0) else {
+            return false;
+        };
+
+        let mut mac = Secret::pepper_mac();
+        mac.update(secret.0.as_bytes());
+
+        mac.verify_slice(&hash).is_ok()
+    }
+}
+
+impl Debug for SecretHash {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("***")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_secrets_have_their_kind() {
+        let secrets = [
+            (Secret::new_random_api_secret(), SecretKind::ApiToken),
+            (Secret::new_random_webhook_secret(), SecretKind::Webhook),
+            (Secret::new_random_session_secret(), SecretKind::Session),
+        ];
+
+        for (secret, kind) in secrets {
+            assert_eq!(secret.kind(), Some(kind));
+            assert!(secret.check_format(kind).is_ok());
+        }
+    }
+
+    #[test]
+    fn derived_secret_is_valid_and_stable() {
+        let parent = Secret::new_random_session_secret();
+        let csrf = parent.derive(SecretKind::Csrf);
+
+        assert!(csrf.is_valid(SecretKind::Csrf));
+        assert_eq!(csrf, parent.derive(SecretKind::Csrf));
+        assert_ne!(
+            csrf,
+            Secret::new_random_session_secret().derive(SecretKind::Csrf)
+        );
+    }
+
+    #[test]
+    fn rejects_wrong_kind() {
+        let secret = Secret::new_random_webhook_secret();
+
+        assert!(!secret.is_valid(SecretKind::ApiToken));
+    }
+
+    #[test]
+    fn rejects_broken_checksum() {
+        let secret = Secret::new_random_api_secret();
+        let (payload, checksum) = secret.0.rsplit_once('_').unwrap();
+        let first = if checksum.starts_with('0') { '1' } else { '0' };
+        let broken_checksum = format!("{first}{}", &checksum[1..]);
+
+        assert!(!Secret(format!("{payload}_{broken_checksum}")).is_valid(SecretKind::ApiToken));
+        assert!(!Secret(format!("{payload}x_{checksum}")).is_valid(SecretKind::ApiToken));
+        assert!(!Secret(payload.to_string()).is_valid(SecretKind::ApiToken));
+    }
+
+    #[test]
+    fn rejects_unknown_prefix_and_empty_body() {
+        assert!(!Secret("ib_foo_bar_1234abcd".to_string()).is_valid(SecretKind::ApiToken));
+
+        let checksum = Secret::checksum(SecretKind::ApiToken.prefix());
+        let empty = Secret(format!("{}_{checksum}", SecretKind::ApiToken.prefix()));
+
+        assert!(!empty.is_valid(SecretKind::ApiToken));
+    }
+}
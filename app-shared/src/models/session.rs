@@ -1,25 +1,41 @@
-use crate::models::{AccountId, Secret};
+use crate::models::{AccountId, Secret, SecretHash, SecretKind};
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 
+/// Несекретный ID сессии, по нему сессией управляют со страницы сессий.
+#[derive(
+    Debug, Default, Copy, Clone, Serialize, Deserialize, Hash, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[serde(transparent)]
+pub struct SessionId(pub i64);
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
-    pub secret: Secret,
-    pub api_secret: Secret,
-    pub csrf_token: Secret,
+    /// Присваивается при добавлении сессии в БД.
+    pub id: SessionId,
+    pub secret_hash: SecretHash,
+    pub api_secret_hash: SecretHash,
+    pub csrf_secret_hash: SecretHash,
     pub account_id: AccountId,
     pub created_at: DateTime<Utc>,
     pub expiration: DateTime<Utc>,
     pub user_agent: String,
     pub ip: String,
+    /// Название устройства, которое задал пользователь.
+    pub label: Option<String>,
+}
+
+/// Секреты сессии в открытом виде, API и CSRF секреты выводятся из секрета сессии.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionSecrets {
+    pub secret: Secret,
+    pub api_secret: Secret,
+    pub csrf_token: Secret,
 }
 
 impl Session {
-    #[allow(clippy::too_many_arguments)]
     pub fn new(
-        secret: Secret,
-        api_secret: Secret,
-        csrf_token: Secret,
+        secrets: &SessionSecrets,
         account_id: AccountId,
         custom_creation_date: Option<DateTime<Utc>>,
         duration: Duration,
@@ -27,14 +43,16 @@ impl Session {
         ip: String,
     ) -> Self {
         Self {
-            secret,
-            api_secret,
-            csrf_token,
+            id: SessionId::default(),
+            secret_hash: secrets.secret.hash(),
+            api_secret_hash: secrets.api_secret.hash(),
+            csrf_secret_hash: secrets.csrf_token.hash(),
             account_id,
             created_at: custom_creation_date.unwrap_or_else(Utc::now),
             expiration: Utc::now() + duration,
             user_agent,
             ip,
+            label: None,
         }
     }
 
@@ -42,3 +60,13 @@ impl Session {
         Utc::now() > self.expiration
     }
 }
+
+impl SessionSecrets {
+    pub fn new(secret: Secret) -> Self {
+        Self {
+            api_secret: secret.derive(SecretKind::ApiToken),
+            csrf_token: secret.derive(SecretKind::Csrf),
+            secret,
+        }
+    }
+}
@@ -0,0 +1,12 @@
+﻿use crate::models::AccountId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Запись истории смены имени пользователя.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsernameChange {
+    pub account_id: AccountId,
+    pub old_username: String,
+    pub new_username: String,
+    pub changed_at: DateTime<Utc>,
+}
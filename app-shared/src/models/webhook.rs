@@ -1,11 +1,15 @@
-﻿use super::{Secret, ServiceId};
+﻿use super::{Secret, SecretHash, ServiceId};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Webhook {
     pub name: String,
-    pub secret: Secret,
+    /// Секрет в открытом виде известен только при создании вебхука.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<Secret>,
+    #[serde(skip)]
+    pub secret_hash: SecretHash,
     pub service_id: ServiceId,
     pub created_at: DateTime<Utc>,
     pub configuration: WebhookConfiguration,
@@ -21,7 +25,8 @@ impl Webhook {
     ) -> Self {
         Self {
             name,
-            secret,
+            secret_hash: secret.hash(),
+            secret: Some(secret),
             service_id,
             created_at: custom_creation_time.unwrap_or_else(Utc::now),
             configuration,
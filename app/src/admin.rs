@@ -0,0 +1,354 @@
+use std::path::Path;
+
+use app_api::Journal;
+use app_shared::{
+    chrono::Duration,
+    models::{
+        AccountId, ActionType, Actor, AnyUserId, ApiToken, ApiTokenId, JournalChainProblem, Rights,
+        Role, RoleId, Secret,
+    },
+    prelude::*,
+    serde_json, Database,
+};
+
+const USAGE: &str = "\
+Использование: indigo-bot admin <команда>
+
+Команды:
+    list                                  системные токены
+    mint [--label <название>] [--days <N>] [--rights <JSON>]
+                                          выпустить системный токен, по умолчанию рутовый
+    rotate <id>                           сменить секрет системного токена
+    revoke <id>                           отозвать системный токен
+    journal-archive                       перенести устаревшие записи журнала в архив
+    journal-import <файл>                 вернуть записи из архива журнала в БД
+    journal-verify                        проверить цепочку хэшей журнала
+    mfa-reset <id аккаунта>               отключить второй фактор аккаунта
+    role-mfa <id роли> on|off             требовать ли второй фактор для прав роли
+
+Команды mint, rotate, revoke и mfa-reset принимают --reason <текст> - причину для журнала.
+Права для mint задаются как поле rights в API, например '{\"user\": 2048}'.
+Возвращённые записи снова попадут в архив при следующей архивации.";
+
+/// `indigo-bot admin`: управление рутовыми и системными токенами, архивом журнала
+/// и вторым фактором напрямую через БД.
+#[instrument]
+pub fn run(args: &[String]) {
+    trace!("run");
+
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    let result = match args.as_slice() {
+        ["list"] => list(),
+        ["mint", options @ ..] => mint(options),
+        ["rotate", token_id, options @ ..] => rotate(token_id, options),
+        ["revoke", token_id, options @ ..] => revoke(token_id, options),
+        ["journal-archive"] => journal_archive(),
+        ["journal-import", path] => journal_import(path),
+        ["journal-verify"] => journal_verify(),
+        ["mfa-reset", account_id, options @ ..] => mfa_reset(account_id, options),
+        ["role-mfa", role_id, "on"] => role_mfa(role_id, true),
+        ["role-mfa", role_id, "off"] => role_mfa(role_id, false),
+        _ => Err(USAGE.to_string()),
+    };
+
+    if let Err(err) = result {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
+
+fn list() -> Result<(), String> {
+    let tokens = Database::lock(|database| database.get_system_api_tokens());
+
+    for token in tokens {
+        let mut flags = String::new();
+
+        if token.is_root {
+            flags.push_str(" [root]");
+        }
+
+        if token.is_expired() {
+            flags.push_str(" [истёк]");
+        }
+
+        let never = || "никогда".to_string();
+
+        println!(
+            "#{} {:?}{flags}: создан {}, истекает {}, использован {}",
+            token.id.0,
+            token.label,
+            token.created_at,
+            token.expiration.map_or_else(never, |date| date.to_string()),
+            token
+                .last_used_at
+                .map_or_else(never, |date| date.to_string()),
+        );
+    }
+
+    Ok(())
+}
+
+fn mint(options: &[&str]) -> Result<(), String> {
+    let mut label = String::from("admin");
+    let mut duration = None;
+    let mut rights = None;
+    let mut reason = None;
+
+    for option in options.chunks(2) {
+        match option {
+            ["--label", value] => label = value.to_string(),
+            ["--rights", value] => {
+                rights = Some(
+                    serde_json::from_str(value)
+                        .map_err(|err| format!("Некорректные права: {err}"))?,
+                );
+            }
+            ["--reason", value] => reason = Some(value.to_string()),
+            ["--days", value] => {
+                let days = value
+                    .parse::<i64>()
+                    .ok()
+                    .filter(|days| *days >= 1)
+                    .ok_or_else(|| format!("Некорректное количество дней: {value}"))?;
+
+                duration = Some(Duration::days(days));
+            }
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+
+    let token = ApiToken {
+        is_root: rights.is_none(),
+        ..ApiToken::new(
+            Secret::new_random_api_secret(),
+            label,
+            rights.unwrap_or_else(Rights::full),
+            None,
+            duration,
+            true,
+            None,
+        )
+    };
+
+    let token = Database::lock(|database| database.add_api_token(token));
+
+    let action = ActionType::ApiTokenIssued {
+        token_id: token.id,
+        label: token.label.clone(),
+    };
+    Journal::lock(|journal| journal.log(Actor::System, None, action, reason));
+
+    let kind = if token.is_root {
+        "рутовый"
+    } else {
+        "системный"
+    };
+
+    println!(
+        "Выпущен {kind} токен #{}, секрет показывается только один раз:\n{}",
+        token.id.0,
+        token.secret.unwrap()
+    );
+
+    Ok(())
+}
+
+fn rotate(token_id: &str, options: &[&str]) -> Result<(), String> {
+    let token_id = parse_token_id(token_id)?;
+    let reason = parse_reason(options)?;
+
+    let token = find_system_token(token_id)?;
+
+    let secret = Secret::new_random_api_secret();
+
+    Database::lock(|database| database.update_api_token_secret(token_id, secret.clone()));
+
+    let action = ActionType::ApiTokenSecretRotated {
+        token_id,
+        label: token.label,
+    };
+    Journal::lock(|journal| journal.log(Actor::System, None, action, reason));
+
+    println!(
+        "Секрет токена #{} заменён, новый секрет показывается только один раз:\n{secret}",
+        token_id.0
+    );
+
+    Ok(())
+}
+
+fn revoke(token_id: &str, options: &[&str]) -> Result<(), String> {
+    let token_id = parse_token_id(token_id)?;
+    let reason = parse_reason(options)?;
+
+    let token = find_system_token(token_id)?;
+
+    Database::lock(|database| database.delete_api_token_by_id(token_id));
+
+    let action = ActionType::ApiTokenRevoked {
+        token_id,
+        label: token.label,
+    };
+    Journal::lock(|journal| journal.log(Actor::System, None, action, reason));
+
+    println!("Токен #{} отозван", token_id.0);
+
+    Ok(())
+}
+
+fn journal_archive() -> Result<(), String> {
+    match Journal::archive_expired() {
+        Ok(Some((path, count))) => {
+            println!("В архив {} перенесено записей: {count}", path.display())
+        }
+        Ok(None) => println!("Устаревших записей нет"),
+        Err(err) => return Err(format!("Не удалось сохранить архив: {err}")),
+    }
+
+    Ok(())
+}
+
+fn journal_import(path: &str) -> Result<(), String> {
+    let imported = Journal::lock(|journal| journal.import_archive(Path::new(path)))
+        .map_err(|err| format!("Не удалось прочитать архив {path}: {err}"))?;
+
+    println!("Возвращено записей: {imported}");
+
+    Ok(())
+}
+
+fn journal_verify() -> Result<(), String> {
+    let report = Journal::lock(|journal| journal.verify_chain());
+
+    println!(
+        "Проверено записей: {}, из них перенесено в архив: {}, обезличено: {}",
+        report.checked, report.archived, report.redacted
+    );
+
+    let Some(broken) = report.broken else {
+        println!("Цепочка цела");
+        return Ok(());
+    };
+
+    let problem = match broken.problem {
+        JournalChainProblem::Unsigned => "у записи нет хэша",
+        JournalChainProblem::ContentChanged => "содержимое не совпадает с хэшем",
+        JournalChainProblem::LinkBroken => "не совпадает хэш предыдущей записи",
+    };
+
+    Err(format!(
+        "Цепочка нарушена на записи #{}: {problem}",
+        broken.id.0
+    ))
+}
+
+fn mfa_reset(account_id: &str, options: &[&str]) -> Result<(), String> {
+    let reason = parse_reason(options)?;
+    let account_id = account_id
+        .parse()
+        .map(AccountId)
+        .map_err(|_| format!("Некорректный ID аккаунта: {account_id}"))?;
+
+    if Database::lock(|database| database.find_account(AnyUserId::AccountId(account_id))).is_none()
+    {
+        return Err(format!("Аккаунт #{} не найден", account_id.0));
+    }
+
+    if !Database::lock(|database| database.delete_account_mfa(account_id)) {
+        return Err(format!(
+            "У аккаунта #{} не подключён второй фактор",
+            account_id.0
+        ));
+    }
+
+    refresh_session_rights(account_id);
+
+    Journal::lock(|journal| {
+        journal.log(
+            Actor::System,
+            Some(Actor::User(account_id)),
+            ActionType::MfaDisabled,
+            reason,
+        )
+    });
+
+    println!("Второй фактор аккаунта #{} отключён", account_id.0);
+
+    Ok(())
+}
+
+fn role_mfa(role_id: &str, requires_mfa: bool) -> Result<(), String> {
+    let role_id = role_id
+        .parse()
+        .map(RoleId)
+        .map_err(|_| format!("Некорректный ID роли: {role_id}"))?;
+
+    if !Database::lock(|database| database.update_role_requires_mfa(role_id, requires_mfa)) {
+        return Err(format!("Роль #{} не найдена", role_id.0));
+    }
+
+    // Права сессий выдаются при входе, поэтому пересчитываются сразу
+    let accounts = Database::lock(|database| database.find_accounts_with_role(role_id));
+
+    for account in &accounts {
+        refresh_session_rights(account.id);
+    }
+
+    println!(
+        "Роль #{} {} второй фактор, обновлены сессии аккаунтов: {}",
+        role_id.0,
+        if requires_mfa {
+            "требует"
+        } else {
+            "не требует"
+        },
+        accounts.len()
+    );
+
+    Ok(())
+}
+
+/// Пересчитывает права сессий аккаунта по его ролям и второму фактору.
+fn refresh_session_rights(account_id: AccountId) {
+    let roles = Database::lock(|database| database.get_account_roles(account_id));
+    let mfa_enabled = Database::lock(|database| database.find_account_mfa(account_id))
+        .is_some_and(|mfa| mfa.is_enabled());
+    let rights = Role::sum_session_rights(roles, mfa_enabled);
+
+    for session in Database::lock(|database| database.get_account_sessions(account_id)) {
+        Database::lock(|database| {
+            database.update_api_token_rights(session.api_secret_hash, rights.clone())
+        });
+    }
+}
+
+fn find_system_token(token_id: ApiTokenId) -> Result<ApiToken, String> {
+    let Some(token) = Database::lock(|database| database.find_api_token_by_id(token_id)) else {
+        return Err(format!("Токен #{} не найден", token_id.0));
+    };
+
+    if token.creator.is_some() {
+        return Err(format!(
+            "Токен #{} принадлежит аккаунту, им управляет владелец",
+            token_id.0
+        ));
+    }
+
+    Ok(token)
+}
+
+fn parse_reason(options: &[&str]) -> Result<Option<String>, String> {
+    match options {
+        [] => Ok(None),
+        ["--reason", reason] => Ok(Some(reason.to_string())),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn parse_token_id(token_id: &str) -> Result<ApiTokenId, String> {
+    token_id
+        .parse()
+        .map(ApiTokenId)
+        .map_err(|_| format!("Некорректный ID токена: {token_id}"))
+}
@@ -1,3 +1,5 @@
+mod admin;
+
 use std::collections::HashMap;
 use std::str::FromStr;
 
@@ -85,6 +87,14 @@ fn main() {
     let journal = Journal::default();
     Journal::set_state(journal);
 
+    // Admin CLI
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if args.first().map(String::as_str) == Some("admin") {
+        admin::run(&args[1..]);
+        return;
+    }
+
     // User agent parser
     UserAgentParser::set_state(UserAgentParser::default());
 
@@ -94,6 +104,9 @@ fn main() {
     // Api
     Api::set_state(Api::default());
 
+    // Journal archive thread
+    std::thread::spawn(Journal::run_archiver);
+
     // Discord thread
     let discord_thread = std::thread::spawn(|| {
         let client = BotClient::default();